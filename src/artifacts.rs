@@ -0,0 +1,296 @@
+use super::effects;
+use openssl::sha::sha256;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+/// Default build directory, relative to the project root.
+pub const DEFAULT_DIR: &str = ".rustify";
+const INDEX_FILE: &str = "index.json";
+
+/// Build directory and environment (`dev` / `prod`) for this run.
+static TARGET: OnceLock<(PathBuf, String)> = OnceLock::new();
+
+/// Files rustify generated, keyed by their path below the build directory,
+/// so `clean` never touches anything it did not write.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Index {
+    #[serde(default)]
+    files: BTreeMap<String, Entry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    /// Content hash when written; edited files are left alone.
+    sha256: String,
+}
+
+/// Selects `<dir>/<environment>/` for the rest of the run.
+pub fn init(dir: &str, environment: &str) {
+    let _ = TARGET.set((PathBuf::from(dir), environment.to_string()));
+}
+
+fn target() -> &'static (PathBuf, String) {
+    TARGET.get_or_init(|| (PathBuf::from(DEFAULT_DIR), "dev".to_string()))
+}
+
+pub fn root() -> &'static Path {
+    &target().0
+}
+
+/// Where `file` is written for the current environment.
+pub fn path(file: &str) -> PathBuf {
+    let (root, environment) = target();
+    root.join(environment).join(file)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn load_index(root: &Path) -> io::Result<Index> {
+    let path = root.join(INDEX_FILE);
+    if !path.exists() {
+        return Ok(Index::default());
+    }
+    serde_json::from_str(&fs::read_to_string(&path)?).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid {}: {}", path.display(), e),
+        )
+    })
+}
+
+fn save_index(root: &Path, index: &Index) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(index)
+        .map_err(io::Error::other)?;
+    effects::write(root.join(INDEX_FILE), json + "\n")
+}
+
+/// Appends `entry` to the project's `.gitignore` unless it is already there.
+pub fn ensure_gitignored(entry: &str) -> io::Result<()> {
+    let gitignore = fs::read_to_string(".gitignore").unwrap_or_default();
+    let bare = entry.trim_matches('/');
+    if gitignore.lines().any(|l| l.trim().trim_matches('/') == bare) {
+        return Ok(());
+    }
+    let separator = if gitignore.is_empty() || gitignore.ends_with('\n') { "" } else { "\n" };
+    effects::write(".gitignore", format!("{}{}{}\n", gitignore, separator, entry))
+}
+
+/// Writes a generated file into the build directory and records it.
+pub fn write<C: AsRef<[u8]>>(file: &str, contents: C) -> io::Result<PathBuf> {
+    let (root, environment) = target();
+    ensure_gitignored(&format!("/{}/", root.display()))?;
+    write_in(root, environment, file, contents.as_ref())
+}
+
+fn write_in(root: &Path, environment: &str, file: &str, contents: &[u8]) -> io::Result<PathBuf> {
+    let path = root.join(environment).join(file);
+    effects::create_dir_all(root.join(environment))?;
+
+    effects::write(&path, contents)?;
+
+    // The index only ever describes files that really exist
+    if !effects::dry_run() {
+        let mut index = load_index(root)?;
+        index.files.insert(
+            format!("{}/{}", environment, file),
+            Entry {
+                sha256: hex(&sha256(contents)),
+            },
+        );
+        save_index(root, &index)?;
+    }
+    Ok(path)
+}
+
+/// Recorded files of `environment` (or every environment), in index order.
+pub fn owned(root: &Path, environment: Option<&str>) -> io::Result<Vec<PathBuf>> {
+    let index = load_index(root)?;
+    Ok(index
+        .files
+        .keys()
+        .filter(|key| environment.is_none_or(|env| key.starts_with(&format!("{}/", env))))
+        .map(|key| root.join(key))
+        .collect())
+}
+
+/// Removes recorded files of `environment` (or all of them). Files edited
+/// since rustify wrote them are kept and returned.
+pub fn clean(root: &Path, environment: Option<&str>) -> io::Result<(usize, Vec<PathBuf>)> {
+    let mut index = load_index(root)?;
+    let mut removed = 0;
+    let mut kept = Vec::new();
+
+    let keys: Vec<String> = index
+        .files
+        .keys()
+        .filter(|key| environment.is_none_or(|env| key.starts_with(&format!("{}/", env))))
+        .cloned()
+        .collect();
+
+    for key in keys {
+        let path = root.join(&key);
+        match fs::read(&path) {
+            Ok(contents) if hex(&sha256(&contents)) != index.files[&key].sha256 => {
+                kept.push(path);
+                continue;
+            }
+            Ok(_) => {
+                effects::remove_file(&path)?;
+                removed += 1;
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        if !effects::dry_run() {
+            index.files.remove(&key);
+        }
+    }
+
+    // Drop environment directories that are now empty
+    if let Ok(entries) = fs::read_dir(root) {
+        for entry in entries.flatten() {
+            let dir = entry.path();
+            if dir.is_dir() && fs::read_dir(&dir)?.next().is_none() {
+                effects::remove_dir_all(&dir)?;
+            }
+        }
+    }
+
+    if !effects::dry_run() {
+        if index.files.is_empty() {
+            if root.join(INDEX_FILE).exists() {
+                effects::remove_file(root.join(INDEX_FILE))?;
+            }
+            if root.is_dir() && fs::read_dir(root)?.next().is_none() {
+                effects::remove_dir_all(root)?;
+            }
+        } else {
+            save_index(root, &index)?;
+        }
+    }
+    Ok((removed, kept))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::MutexGuard;
+
+    /// Build directory in a scratch location, removed on drop.
+    struct BuildDir {
+        root: PathBuf,
+        _guard: MutexGuard<'static, ()>,
+    }
+
+    impl BuildDir {
+        fn new(name: &str) -> Self {
+            let guard = effects::TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+            let root = std::env::temp_dir().join(format!("rustify-artifacts-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            BuildDir { root, _guard: guard }
+        }
+
+        fn write(&self, environment: &str, file: &str, contents: &str) -> PathBuf {
+            write_in(&self.root, environment, file, contents.as_bytes()).unwrap()
+        }
+
+        fn indexed(&self) -> Vec<String> {
+            load_index(&self.root).unwrap().files.into_keys().collect()
+        }
+    }
+
+    impl Drop for BuildDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    #[test]
+    fn records_content_hashes_in_the_index() {
+        let build = BuildDir::new("index");
+        let path = build.write("dev", "Dockerfile", "hello");
+        assert_eq!(path, build.root.join("dev/Dockerfile"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello");
+        build.write("prod", "deployment.yaml", "kind: Deployment\n");
+
+        let index: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(build.root.join(INDEX_FILE)).unwrap()).unwrap();
+        assert_eq!(
+            index["files"]["dev/Dockerfile"]["sha256"],
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert_eq!(build.indexed(), ["dev/Dockerfile", "prod/deployment.yaml"]);
+
+        build.write("dev", "Dockerfile", "hello again");
+        assert_eq!(
+            load_index(&build.root).unwrap().files["dev/Dockerfile"].sha256,
+            hex(&sha256(b"hello again"))
+        );
+    }
+
+    #[test]
+    fn lists_owned_files_per_environment() {
+        let build = BuildDir::new("owned");
+        build.write("dev", "a", "1");
+        build.write("prod", "b", "2");
+        build.write("production", "c", "3");
+        assert_eq!(owned(&build.root, Some("prod")).unwrap(), [build.root.join("prod/b")]);
+        assert_eq!(owned(&build.root, None).unwrap().len(), 3);
+        assert!(owned(&build.root.join("missing"), None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn clean_keeps_edited_files() {
+        let build = BuildDir::new("edited");
+        build.write("dev", "untouched", "generated");
+        let edited = build.write("dev", "edited", "generated");
+        build.write("dev", "deleted", "generated");
+        build.write("prod", "other", "generated");
+        fs::write(&edited, "changed by hand").unwrap();
+        fs::remove_file(build.root.join("dev/deleted")).unwrap();
+
+        let (removed, kept) = clean(&build.root, Some("dev")).unwrap();
+        assert_eq!(removed, 1);
+        assert_eq!(kept, [edited.as_path()]);
+        assert!(!build.root.join("dev/untouched").exists());
+        assert_eq!(fs::read_to_string(&edited).unwrap(), "changed by hand");
+        assert_eq!(build.indexed(), ["dev/edited", "prod/other"]);
+    }
+
+    #[test]
+    fn clean_removes_empty_directories_and_the_index() {
+        let build = BuildDir::new("all");
+        build.write("dev", "a", "1");
+        build.write("prod", "b", "2");
+
+        let (removed, kept) = clean(&build.root, Some("prod")).unwrap();
+        assert_eq!((removed, kept.len()), (1, 0));
+        assert!(!build.root.join("prod").exists());
+        assert!(build.root.join(INDEX_FILE).exists());
+
+        let (removed, _) = clean(&build.root, None).unwrap();
+        assert_eq!(removed, 1);
+        assert!(!build.root.exists());
+    }
+
+    #[test]
+    fn dry_run_clean_touches_nothing() {
+        let build = BuildDir::new("dry-run");
+        build.write("dev", "a", "1");
+        effects::set_dry_run(true);
+        let result = clean(&build.root, None);
+        effects::set_dry_run(false);
+
+        assert_eq!(result.unwrap().0, 1);
+        assert!(build.root.join("dev/a").exists());
+        assert_eq!(build.indexed(), ["dev/a"]);
+    }
+}
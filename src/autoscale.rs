@@ -0,0 +1,482 @@
+use super::ScalingConfig;
+use serde_json::Value;
+use std::io;
+
+const DEFAULT_PROMETHEUS: &str = "http://prometheus-server.monitoring.svc:80";
+
+/// Name of the HPA for `app_name`. KEDA is told to use the same name, so
+/// `rustify scale --show` works in both modes.
+pub fn hpa_name(app_name: &str) -> String {
+    format!("{}-hpa", app_name)
+}
+
+/// CPU target in percent. Thresholds up to 1.0 are read as fractions.
+pub fn cpu_target(scaling: &ScalingConfig) -> u32 {
+    let threshold = scaling.auto_scale_threshold;
+    if threshold > 0.0 && threshold <= 1.0 {
+        (threshold * 100.0).round() as u32
+    } else {
+        threshold.round() as u32
+    }
+}
+
+pub fn validate(scaling: &ScalingConfig) -> io::Result<()> {
+    let invalid = |msg: String| Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+    if scaling.min_instances == 0 || scaling.min_instances > scaling.max_instances {
+        return invalid(format!(
+            "Invalid scaling range: minInstances {} / maxInstances {}",
+            scaling.min_instances, scaling.max_instances
+        ));
+    }
+    if !(1..=100).contains(&cpu_target(scaling)) {
+        return invalid(format!(
+            "autoScaleThreshold must be a CPU percentage between 1 and 100, got {}",
+            scaling.auto_scale_threshold
+        ));
+    }
+    Ok(())
+}
+
+/// `behavior` block; `indent` is the column of the `behavior:` key.
+fn behavior(scaling: &ScalingConfig, indent: usize) -> String {
+    let pad = " ".repeat(indent);
+    format!(
+        r#"{pad}behavior:
+{pad}  scaleUp:
+{pad}    stabilizationWindowSeconds: {up}
+{pad}    policies:
+{pad}    - type: Pods
+{pad}      value: 2
+{pad}      periodSeconds: 60
+{pad}  scaleDown:
+{pad}    stabilizationWindowSeconds: {down}
+{pad}    policies:
+{pad}    - type: Pods
+{pad}      value: 1
+{pad}      periodSeconds: 60
+"#,
+        up = scaling.scale_up_cooldown,
+        down = scaling.scale_down_cooldown,
+    )
+}
+
+/// HorizontalPodAutoscaler, or a KEDA ScaledObject when `keda` is set.
+pub fn manifest(app_name: &str, namespace: &str, scaling: &ScalingConfig) -> String {
+    if let Some(keda) = &scaling.keda {
+        return scaled_object(app_name, namespace, scaling, keda);
+    }
+
+    let mut metrics = format!(
+        r#"  - type: Resource
+    resource:
+      name: cpu
+      target:
+        type: Utilization
+        averageUtilization: {}
+"#,
+        cpu_target(scaling)
+    );
+    if let Some(memory) = scaling.memory_threshold {
+        metrics.push_str(&format!(
+            r#"  - type: Resource
+    resource:
+      name: memory
+      target:
+        type: Utilization
+        averageUtilization: {}
+"#,
+            memory
+        ));
+    }
+    if let Some(rps) = scaling.requests_per_second {
+        // Served by prometheus-adapter through the custom metrics API
+        metrics.push_str(&format!(
+            r#"  - type: Pods
+    pods:
+      metric:
+        name: {}
+      target:
+        type: AverageValue
+        averageValue: "{}"
+"#,
+            scaling.request_metric, rps
+        ));
+    }
+
+    format!(
+        r#"apiVersion: autoscaling/v2
+kind: HorizontalPodAutoscaler
+metadata:
+  name: {name}
+  namespace: {namespace}
+  labels:
+    app.kubernetes.io/managed-by: rustify
+spec:
+  scaleTargetRef:
+    apiVersion: apps/v1
+    kind: Deployment
+    name: {app_name}-deployment
+  minReplicas: {min}
+  maxReplicas: {max}
+  metrics:
+{metrics}{behavior}"#,
+        name = hpa_name(app_name),
+        min = scaling.min_instances,
+        max = scaling.max_instances,
+        behavior = behavior(scaling, 2),
+    )
+}
+
+fn scaled_object(
+    app_name: &str,
+    namespace: &str,
+    scaling: &ScalingConfig,
+    keda: &super::KedaConfig,
+) -> String {
+    let mut triggers = format!(
+        r#"  - type: cpu
+    metricType: Utilization
+    metadata:
+      value: "{}"
+"#,
+        cpu_target(scaling)
+    );
+    if let Some(memory) = scaling.memory_threshold {
+        triggers.push_str(&format!(
+            r#"  - type: memory
+    metricType: Utilization
+    metadata:
+      value: "{}"
+"#,
+            memory
+        ));
+    }
+    if let Some(rps) = scaling.requests_per_second {
+        let query = keda.query.clone().unwrap_or_else(|| {
+            format!(
+                r#"sum(rate(http_requests_total{{namespace="{}",pod=~"{}-deployment-.*"}}[2m]))"#,
+                namespace, app_name
+            )
+        });
+        triggers.push_str(&format!(
+            r#"  - type: prometheus
+    metadata:
+      serverAddress: {}
+      query: {}
+      threshold: "{}"
+"#,
+            keda.prometheus_address.as_deref().unwrap_or(DEFAULT_PROMETHEUS),
+            Value::String(query),
+            rps
+        ));
+    }
+
+    format!(
+        r#"apiVersion: keda.sh/v1alpha1
+kind: ScaledObject
+metadata:
+  name: {app_name}-scaler
+  namespace: {namespace}
+  labels:
+    app.kubernetes.io/managed-by: rustify
+spec:
+  scaleTargetRef:
+    name: {app_name}-deployment
+  minReplicaCount: {min}
+  maxReplicaCount: {max}
+  advanced:
+    horizontalPodAutoscalerConfig:
+      name: {name}
+{behavior}  triggers:
+{triggers}"#,
+        name = hpa_name(app_name),
+        min = scaling.min_instances,
+        max = scaling.max_instances,
+        behavior = behavior(scaling, 6),
+    )
+}
+
+/// Parses a Kubernetes quantity such as `250m` or `1500` into a number.
+fn parse_quantity(quantity: &str) -> Option<f64> {
+    let (number, scale) = match quantity.strip_suffix('m') {
+        Some(number) => (number, 0.001),
+        None => match quantity.strip_suffix('k') {
+            Some(number) => (number, 1000.0),
+            None => (quantity, 1.0),
+        },
+    };
+    number.parse::<f64>().ok().map(|n| n * scale)
+}
+
+/// Current and target value of one HPA metric, in the metric's own unit.
+struct MetricReading {
+    label: String,
+    current: Option<f64>,
+    target: f64,
+}
+
+fn metric_key(metric: &Value) -> String {
+    match metric["type"].as_str() {
+        Some("Resource") => metric["resource"]["name"].as_str().unwrap_or("?").to_string(),
+        Some("Pods") => metric["pods"]["metric"]["name"].as_str().unwrap_or("?").to_string(),
+        Some("External") => metric["external"]["metric"]["name"].as_str().unwrap_or("?").to_string(),
+        other => other.unwrap_or("?").to_string(),
+    }
+}
+
+fn metric_value(block: &Value) -> Option<(f64, bool)> {
+    if let Some(utilization) = block["averageUtilization"].as_f64() {
+        return Some((utilization, true));
+    }
+    block["averageValue"]
+        .as_str()
+        .or_else(|| block["value"].as_str())
+        .and_then(parse_quantity)
+        .map(|value| (value, false))
+}
+
+fn readings(hpa: &Value) -> Vec<MetricReading> {
+    let empty = Vec::new();
+    let current = hpa["status"]["currentMetrics"].as_array().unwrap_or(&empty);
+
+    hpa["spec"]["metrics"]
+        .as_array()
+        .unwrap_or(&empty)
+        .iter()
+        .filter_map(|spec| {
+            let kind = spec["type"].as_str()?.to_lowercase();
+            let key = metric_key(spec);
+            let (target, percent) = metric_value(&spec[&kind]["target"])?;
+            let current = current
+                .iter()
+                .find(|m| metric_key(m) == key)
+                .and_then(|m| metric_value(&m[&kind]["current"]))
+                .map(|(value, _)| value);
+            Some(MetricReading {
+                label: if percent { format!("{} (%)", key) } else { key },
+                current,
+                target,
+            })
+        })
+        .collect()
+}
+
+/// Prints why the HPA in `hpa_json` (from `kubectl get hpa -o json`) wants
+/// the replica count it has.
+pub fn explain(hpa_json: &str) -> io::Result<()> {
+    let hpa: Value = serde_json::from_str(hpa_json)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    let min = hpa["spec"]["minReplicas"].as_u64().unwrap_or(1);
+    let max = hpa["spec"]["maxReplicas"].as_u64().unwrap_or(0);
+    let current = hpa["status"]["currentReplicas"].as_u64().unwrap_or(0);
+    let desired = hpa["status"]["desiredReplicas"].as_u64().unwrap_or(0);
+
+    println!("📈 {} replicas now, {} desired (range {}-{})", current, desired, min, max);
+
+    let mut proposals = Vec::new();
+    for reading in readings(&hpa) {
+        match reading.current {
+            Some(value) if reading.target > 0.0 => {
+                // Same formula the HPA controller uses
+                let proposed = (current as f64 * value / reading.target).ceil() as u64;
+                proposals.push(proposed);
+                println!(
+                    "   • {}: {:.1} of target {:.1} → {} replicas",
+                    reading.label, value, reading.target, proposed
+                );
+            }
+            _ => println!("   • {}: no data yet (target {:.1})", reading.label, reading.target),
+        }
+    }
+    if let Some(highest) = proposals.iter().max() {
+        let clamped = (*highest).clamp(min, max.max(min));
+        println!("   The highest proposal wins: {} → {} after min/max", highest, clamped);
+    }
+
+    if let Some(conditions) = hpa["status"]["conditions"].as_array() {
+        for condition in conditions {
+            let ok = condition["status"].as_str() == Some("True");
+            println!(
+                "   {} {}: {}",
+                if ok { "✅" } else { "⚠️ " },
+                condition["type"].as_str().unwrap_or("?"),
+                condition["message"].as_str().unwrap_or("")
+            );
+        }
+    }
+    if let Some(last) = hpa["status"]["lastScaleTime"].as_str() {
+        println!("   Last scaled at {}", last);
+    }
+
+    if let Some(behavior) = hpa["spec"]["behavior"].as_object() {
+        for (direction, rules) in behavior {
+            if let Some(window) = rules["stabilizationWindowSeconds"].as_u64() {
+                println!("   {} stabilization window: {}s", direction, window);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KedaConfig;
+
+    fn scaling(threshold: f64, min: u32, max: u32) -> ScalingConfig {
+        ScalingConfig {
+            auto_scale_threshold: threshold,
+            min_instances: min,
+            max_instances: max,
+            ..ScalingConfig::default()
+        }
+    }
+
+    #[test]
+    fn reads_thresholds_as_percentages_or_fractions() {
+        assert_eq!(cpu_target(&scaling(70.0, 1, 5)), 70);
+        assert_eq!(cpu_target(&scaling(0.75, 1, 5)), 75);
+        assert_eq!(cpu_target(&scaling(1.0, 1, 5)), 100);
+        assert_eq!(cpu_target(&scaling(42.4, 1, 5)), 42);
+    }
+
+    #[test]
+    fn validates_the_scaling_range() {
+        assert!(validate(&scaling(70.0, 1, 5)).is_ok());
+        assert!(validate(&scaling(70.0, 3, 3)).is_ok());
+        for invalid in [scaling(70.0, 0, 5), scaling(70.0, 6, 5), scaling(0.0, 1, 5), scaling(150.0, 1, 5)] {
+            let err = validate(&invalid).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn renders_a_cpu_hpa() {
+        let expected = r#"apiVersion: autoscaling/v2
+kind: HorizontalPodAutoscaler
+metadata:
+  name: shop-hpa
+  namespace: prod
+  labels:
+    app.kubernetes.io/managed-by: rustify
+spec:
+  scaleTargetRef:
+    apiVersion: apps/v1
+    kind: Deployment
+    name: shop-deployment
+  minReplicas: 2
+  maxReplicas: 8
+  metrics:
+  - type: Resource
+    resource:
+      name: cpu
+      target:
+        type: Utilization
+        averageUtilization: 60
+  behavior:
+    scaleUp:
+      stabilizationWindowSeconds: 60
+      policies:
+      - type: Pods
+        value: 2
+        periodSeconds: 60
+    scaleDown:
+      stabilizationWindowSeconds: 300
+      policies:
+      - type: Pods
+        value: 1
+        periodSeconds: 60
+"#;
+        assert_eq!(manifest("shop", "prod", &scaling(60.0, 2, 8)), expected);
+    }
+
+    #[test]
+    fn adds_memory_and_request_metrics_to_the_hpa() {
+        let config = ScalingConfig {
+            memory_threshold: Some(80),
+            requests_per_second: Some(25.0),
+            ..scaling(70.0, 1, 5)
+        };
+        let hpa = manifest("shop", "prod", &config);
+        assert!(hpa.contains(
+            "  - type: Resource\n    resource:\n      name: memory\n      target:\n        type: Utilization\n        averageUtilization: 80\n"
+        ));
+        assert!(hpa.contains(
+            "  - type: Pods\n    pods:\n      metric:\n        name: http_requests_per_second\n      target:\n        type: AverageValue\n        averageValue: \"25\"\n"
+        ));
+        let cpu = hpa.find("name: cpu").unwrap();
+        let memory = hpa.find("name: memory").unwrap();
+        let rps = hpa.find("name: http_requests_per_second").unwrap();
+        assert!(cpu < memory && memory < rps, "{}", hpa);
+    }
+
+    #[test]
+    fn renders_a_keda_scaled_object() {
+        let config = ScalingConfig {
+            requests_per_second: Some(10.0),
+            keda: Some(KedaConfig::default()),
+            ..scaling(0.5, 2, 6)
+        };
+        let scaled = manifest("shop", "prod", &config);
+        assert!(scaled.starts_with("apiVersion: keda.sh/v1alpha1\nkind: ScaledObject\n"));
+        assert!(scaled.contains("  name: shop-scaler\n"));
+        assert!(scaled.contains("  minReplicaCount: 2\n  maxReplicaCount: 6\n"));
+        // KEDA names its HPA like ours so `scale --show` finds it
+        assert!(scaled.contains("    horizontalPodAutoscalerConfig:\n      name: shop-hpa\n      behavior:\n        scaleUp:\n"));
+        assert!(scaled.contains("  - type: cpu\n    metricType: Utilization\n    metadata:\n      value: \"50\"\n"));
+        assert!(scaled.contains(&format!("      serverAddress: {}\n", DEFAULT_PROMETHEUS)));
+        assert!(scaled.contains(
+            r#"      query: "sum(rate(http_requests_total{namespace=\"prod\",pod=~\"shop-deployment-.*\"}[2m]))""#
+        ));
+        assert!(scaled.contains("      threshold: \"10\"\n"));
+        assert!(!scaled.contains("type: memory"));
+    }
+
+    #[test]
+    fn keda_uses_the_configured_prometheus_query() {
+        let config = ScalingConfig {
+            memory_threshold: Some(75),
+            requests_per_second: Some(5.0),
+            keda: Some(KedaConfig {
+                prometheus_address: Some("http://prom:9090".to_string()),
+                query: Some("sum(rate(shop_requests[1m]))".to_string()),
+            }),
+            ..scaling(70.0, 1, 5)
+        };
+        let scaled = manifest("shop", "prod", &config);
+        assert!(scaled.contains("      serverAddress: http://prom:9090\n"));
+        assert!(scaled.contains("      query: \"sum(rate(shop_requests[1m]))\"\n"));
+        assert!(scaled.contains("  - type: memory\n    metricType: Utilization\n    metadata:\n      value: \"75\"\n"));
+    }
+
+    #[test]
+    fn pairs_hpa_targets_with_current_metrics() {
+        let hpa = serde_json::json!({
+            "spec": { "metrics": [
+                { "type": "Resource", "resource": { "name": "cpu", "target": { "type": "Utilization", "averageUtilization": 70 } } },
+                { "type": "Pods", "pods": { "metric": { "name": "rps" }, "target": { "type": "AverageValue", "averageValue": "10" } } },
+                { "type": "Resource", "resource": { "name": "memory", "target": { "type": "Utilization", "averageUtilization": 80 } } },
+            ] },
+            "status": { "currentMetrics": [
+                { "type": "Pods", "pods": { "metric": { "name": "rps" }, "current": { "averageValue": "12500m" } } },
+                { "type": "Resource", "resource": { "name": "cpu", "current": { "averageUtilization": 35 } } },
+            ] },
+        });
+        let readings: Vec<(String, Option<f64>, f64)> = readings(&hpa)
+            .into_iter()
+            .map(|r| (r.label, r.current, r.target))
+            .collect();
+        assert_eq!(
+            readings,
+            [
+                ("cpu (%)".to_string(), Some(35.0), 70.0),
+                ("rps".to_string(), Some(12.5), 10.0),
+                ("memory (%)".to_string(), None, 80.0),
+            ]
+        );
+        assert_eq!(parse_quantity("2k"), Some(2000.0));
+        assert_eq!(parse_quantity("abc"), None);
+    }
+}
@@ -0,0 +1,560 @@
+use super::{effects, packagejson::PackageJson, templates};
+use effects::Effect;
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// A template's package.json writes this instead of a range to take the
+/// version from the catalog, as pnpm catalogs do.
+pub const PROTOCOL: &str = "catalog:";
+const CATALOG_FILE: &str = "catalog.json";
+const EMBEDDED: &str = include_str!("../templates/catalog.json");
+
+/// Known-compatible dependency versions, one set per framework, so that a
+/// generated package.json never mixes releases that do not work together.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Catalog {
+    pub frameworks: BTreeMap<String, BTreeMap<String, Package>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Package {
+    pub version: String,
+    /// Range operator written into package.json, `^` by default. `~` for
+    /// packages whose minor releases break dependents, such as TypeScript
+    /// under Angular.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    /// Only peers that are part of the same set are checked.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub peer_dependencies: BTreeMap<String, String>,
+}
+
+impl Package {
+    pub fn range(&self) -> String {
+        format!("{}{}", self.prefix.as_deref().unwrap_or("^"), self.version)
+    }
+}
+
+/// Where `update-catalog` keeps the refreshed catalog; it replaces the
+/// built-in one while it exists.
+pub fn path() -> io::Result<PathBuf> {
+    Ok(templates::config_dir()?.join(CATALOG_FILE))
+}
+
+fn parse(source: &str, json: &str) -> io::Result<Catalog> {
+    serde_json::from_str(json).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid catalog {}: {}", source, e),
+        )
+    })
+}
+
+impl Catalog {
+    pub fn embedded() -> io::Result<Catalog> {
+        parse("(built in)", EMBEDDED)
+    }
+
+    pub fn load() -> io::Result<Catalog> {
+        let path = path()?;
+        match fs::read_to_string(&path) {
+            Ok(json) => parse(&path.display().to_string(), &json),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Self::embedded(),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self) -> io::Result<PathBuf> {
+        let path = path()?;
+        if let Some(dir) = path.parent() {
+            effects::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(io::Error::other)?;
+        effects::write_atomic(&path, json + "\n")?;
+        Ok(path)
+    }
+
+    /// Replaces every `catalog:` range in a rendered package.json with the
+    /// version from `framework`'s set.
+    pub fn resolve(&self, source: &str, contents: &str, framework: Option<&str>) -> io::Result<String> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut pkg =
+            PackageJson::parse(contents).map_err(|e| invalid(format!("{}: {}", source, e)))?;
+        pkg.map_ranges(|name, range| {
+            if range != PROTOCOL {
+                return Ok(None);
+            }
+            let framework = framework.ok_or_else(|| {
+                invalid(format!(
+                    "{}: {} uses {} but the template names no catalog",
+                    source, name, PROTOCOL
+                ))
+            })?;
+            self.frameworks
+                .get(framework)
+                .and_then(|set| set.get(name))
+                .map(|package| Some(package.range()))
+                .ok_or_else(|| {
+                    invalid(format!(
+                        "{}: the {} catalog has no version for {}",
+                        source, framework, name
+                    ))
+                })
+        })?;
+        pkg.render()
+    }
+
+    /// Peer dependency ranges a set does not satisfy, for one framework or
+    /// for all of them.
+    pub fn problems(&self, framework: Option<&str>) -> Vec<String> {
+        let mut problems = Vec::new();
+        for (name, set) in &self.frameworks {
+            if framework.is_some_and(|f| f != name) {
+                continue;
+            }
+            for (package_name, package) in set {
+                for (peer, range) in &package.peer_dependencies {
+                    let found = match set.get(peer) {
+                        Some(found) => found,
+                        None => continue,
+                    };
+                    let problem = match satisfies(&found.version, range) {
+                        Some(true) => continue,
+                        Some(false) => "needs",
+                        None => "has a range rustify cannot check:",
+                    };
+                    problems.push(format!(
+                        "{}: {}@{} {} {} {}, the catalog has {}",
+                        name, package_name, package.version, problem, peer, range, found.version
+                    ));
+                }
+            }
+        }
+        problems
+    }
+
+    /// Takes the entries of another catalog file, keeping packages it does
+    /// not mention. Returns the changed entries.
+    pub fn update_from_file(&mut self, file: &Path) -> io::Result<Vec<String>> {
+        let other = parse(&file.display().to_string(), &fs::read_to_string(file)?)?;
+        let mut changes = Vec::new();
+        for (framework, packages) in other.frameworks {
+            let set = self.frameworks.entry(framework.clone()).or_default();
+            for (name, package) in packages {
+                match set.get(&name) {
+                    Some(old) if *old == package => continue,
+                    Some(old) => changes.push(format!(
+                        "{}: {} {} → {}",
+                        framework, name, old.version, package.version
+                    )),
+                    None => changes.push(format!(
+                        "{}: {} {} (new)",
+                        framework, name, package.version
+                    )),
+                }
+                set.insert(name, package);
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Moves every package to the newest release within its current range
+    /// (`^` or `~`), or to the `latest` tag, as published on `registry`.
+    /// Returns the changed entries.
+    pub fn update_from_registry(&mut self, registry: &str, latest: bool) -> io::Result<Vec<String>> {
+        let mut documents: BTreeMap<String, serde_json::Value> = BTreeMap::new();
+        let mut changes = Vec::new();
+        for (framework, set) in self.frameworks.iter_mut() {
+            let names: BTreeSet<String> = set.keys().cloned().collect();
+            for (name, package) in set.iter_mut() {
+                if !documents.contains_key(name) {
+                    documents.insert(name.clone(), fetch(registry, name)?);
+                }
+                let document = &documents[name];
+                let versions = document["versions"].as_object();
+                let version = if latest {
+                    document["dist-tags"]["latest"].as_str().map(str::to_string)
+                } else {
+                    newest(versions.into_iter().flat_map(|v| v.keys()), &package.range())
+                };
+                let version = match version {
+                    Some(version) if version != package.version => version,
+                    _ => continue,
+                };
+
+                let peers = versions
+                    .and_then(|v| v.get(&version))
+                    .and_then(|v| v["peerDependencies"].as_object())
+                    .map(|peers| {
+                        peers
+                            .iter()
+                            .filter(|(peer, _)| names.contains(*peer))
+                            .filter_map(|(peer, range)| Some((peer.clone(), range.as_str()?.to_string())))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                changes.push(format!("{}: {} {} → {}", framework, name, package.version, version));
+                package.version = version;
+                package.peer_dependencies = peers;
+            }
+        }
+        Ok(changes)
+    }
+}
+
+/// Abbreviated registry metadata for `name`: dist-tags plus every version's
+/// dependencies.
+fn fetch(registry: &str, name: &str) -> io::Result<serde_json::Value> {
+    let url = format!(
+        "{}/{}",
+        registry.trim_end_matches('/'),
+        name.replace('/', "%2f")
+    );
+    let output = Command::new("curl")
+        .args(["-fsSL", "-H", "Accept: application/vnd.npm.install-v1+json", &url])
+        .run()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "Failed to fetch {} from {}: {}",
+            name,
+            registry,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    serde_json::from_slice(&output.stdout).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid registry response for {}: {}", name, e),
+        )
+    })
+}
+
+/// Highest release (no pre-release tag) among `versions` that satisfies
+/// `range`.
+fn newest<'a>(versions: impl Iterator<Item = &'a String>, range: &str) -> Option<String> {
+    versions
+        .filter_map(|v| Some((Version::parse(v)?, v)))
+        .filter(|(version, raw)| version.pre.is_empty() && satisfies(raw, range) == Some(true))
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, raw)| raw.clone())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Version {
+    major: u64,
+    minor: u64,
+    patch: u64,
+    pre: Vec<String>,
+}
+
+impl Version {
+    fn parse(text: &str) -> Option<Version> {
+        let text = text.trim().trim_start_matches('=').trim_start_matches('v');
+        let text = text.split('+').next()?;
+        let (core, pre) = match text.split_once('-') {
+            Some((core, pre)) => (core, pre.split('.').map(str::to_string).collect()),
+            None => (text, Vec::new()),
+        };
+        let mut parts = core.split('.').map(|p| p.parse::<u64>().ok());
+        let version = Version {
+            major: parts.next()??,
+            minor: parts.next()??,
+            patch: parts.next()??,
+            pre,
+        };
+        match parts.next() {
+            Some(_) => None,
+            None => Some(version),
+        }
+    }
+
+    fn new(major: u64, minor: u64, patch: u64) -> Version {
+        Version {
+            major,
+            minor,
+            patch,
+            pre: Vec::new(),
+        }
+    }
+
+    /// The lowest pre-release of this version, so `< x.y.z-0` excludes
+    /// pre-releases of `x.y.z` too.
+    fn floor(mut self) -> Version {
+        self.pre = vec!["0".to_string()];
+        self
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => {
+                    for (a, b) in self.pre.iter().zip(&other.pre) {
+                        let order = match (a.parse::<u64>(), b.parse::<u64>()) {
+                            (Ok(a), Ok(b)) => a.cmp(&b),
+                            (Ok(_), Err(_)) => Ordering::Less,
+                            (Err(_), Ok(_)) => Ordering::Greater,
+                            (Err(_), Err(_)) => a.cmp(b),
+                        };
+                        if order != Ordering::Equal {
+                            return order;
+                        }
+                    }
+                    self.pre.len().cmp(&other.pre.len())
+                }
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+}
+
+type Bound = (Op, Version);
+
+/// `1`, `1.2`, `1.x`, `*` and full versions; `None` parts are wildcards.
+struct Partial {
+    major: Option<u64>,
+    minor: Option<u64>,
+    patch: Option<u64>,
+    pre: Vec<String>,
+}
+
+fn partial(text: &str) -> Option<Partial> {
+    let text = text.trim_start_matches('v');
+    let text = text.split('+').next()?;
+    let (core, pre) = match text.split_once('-') {
+        Some((core, pre)) => (core, pre.split('.').map(str::to_string).collect()),
+        None => (text, Vec::new()),
+    };
+    let mut parts = [None; 3];
+    for (i, part) in core.split('.').enumerate() {
+        if i > 2 {
+            return None;
+        }
+        if matches!(part, "x" | "X" | "*" | "") {
+            break;
+        }
+        parts[i] = Some(part.parse().ok()?);
+    }
+    Some(Partial {
+        major: parts[0],
+        minor: parts[1],
+        patch: parts[2],
+        pre,
+    })
+}
+
+/// The bounds one comparator such as `^1.2`, `>=2` or `~0.15.0` stands for.
+fn comparator(token: &str) -> Option<Vec<Bound>> {
+    let operators = ["<=", ">=", "~>", "<", ">", "=", "^", "~"];
+    let op = operators
+        .iter()
+        .find(|op| token.starts_with(*op))
+        .copied()
+        .unwrap_or("");
+    let Partial {
+        major,
+        minor,
+        patch,
+        pre,
+    } = partial(token[op.len()..].trim())?;
+    let major = match major {
+        Some(major) => major,
+        // `*`, `x` or an empty range match everything, `<*` and `>*` nothing
+        None => {
+            return Some(match op {
+                "<" | ">" => vec![(Op::Lt, Version::new(0, 0, 0).floor())],
+                _ => Vec::new(),
+            })
+        }
+    };
+    let lower = Version {
+        major,
+        minor: minor.unwrap_or(0),
+        patch: patch.unwrap_or(0),
+        pre,
+    };
+    // First version past a partial version, e.g. 1.3.0 for 1.2
+    let past = match (minor, patch) {
+        (None, _) => Version::new(major + 1, 0, 0),
+        (Some(minor), None) => Version::new(major, minor + 1, 0),
+        (Some(minor), Some(patch)) => Version::new(major, minor, patch + 1),
+    };
+    let exact = minor.is_some() && patch.is_some();
+
+    Some(match op {
+        "^" => {
+            let upper = match (major, minor, patch) {
+                (0, Some(0), Some(patch)) => Version::new(0, 0, patch + 1),
+                (0, Some(minor), _) => Version::new(0, minor + 1, 0),
+                (major, _, _) => Version::new(major + 1, 0, 0),
+            };
+            vec![(Op::Ge, lower), (Op::Lt, upper.floor())]
+        }
+        "~" | "~>" => {
+            let upper = match minor {
+                Some(minor) => Version::new(major, minor + 1, 0),
+                None => Version::new(major + 1, 0, 0),
+            };
+            vec![(Op::Ge, lower), (Op::Lt, upper.floor())]
+        }
+        ">=" => vec![(Op::Ge, lower)],
+        "<" => vec![(Op::Lt, if exact { lower } else { lower.floor() })],
+        ">" if exact => vec![(Op::Gt, lower)],
+        ">" => vec![(Op::Ge, past)],
+        "<=" if exact => vec![(Op::Le, lower)],
+        "<=" => vec![(Op::Lt, past.floor())],
+        _ if exact => vec![(Op::Eq, lower)],
+        _ => vec![(Op::Ge, lower), (Op::Lt, past.floor())],
+    })
+}
+
+/// Bounds of one `||` alternative: space-separated comparators, or a
+/// hyphen range such as `1.2 - 2`.
+fn bounds(set: &str) -> Option<Vec<Bound>> {
+    if let Some((from, to)) = set.split_once(" - ") {
+        let mut bounds = comparator(&format!(">={}", from.trim()))?;
+        bounds.extend(comparator(&format!("<={}", to.trim()))?);
+        return Some(bounds);
+    }
+    let mut bounds = Vec::new();
+    let mut pending = String::new();
+    for token in set.split_whitespace() {
+        pending.push_str(token);
+        // `>= 1.2.3`: the operator and the version are separate tokens
+        if token.chars().all(|c| matches!(c, '<' | '>' | '=' | '^' | '~')) {
+            continue;
+        }
+        bounds.extend(comparator(&pending)?);
+        pending.clear();
+    }
+    if !pending.is_empty() {
+        return None;
+    }
+    Some(bounds)
+}
+
+/// Whether `version` is in the npm range `range`; `None` when either does
+/// not parse.
+fn satisfies(version: &str, range: &str) -> Option<bool> {
+    let version = Version::parse(version)?;
+    let mut matched = false;
+    for set in range.split("||") {
+        let bounds = bounds(set.trim())?;
+        let within = bounds.iter().all(|(op, bound)| match op {
+            Op::Lt => version < *bound,
+            Op::Le => version <= *bound,
+            Op::Gt => version > *bound,
+            Op::Ge => version >= *bound,
+            Op::Eq => version == *bound,
+        });
+        // Pre-releases only match ranges that name a pre-release of the
+        // same version
+        let pre_allowed = version.pre.is_empty()
+            || bounds.iter().any(|(_, bound)| {
+                !bound.pre.is_empty()
+                    && bound.pre != ["0"]
+                    && (bound.major, bound.minor, bound.patch)
+                        == (version.major, version.minor, version.patch)
+            });
+        matched |= within && pre_allowed;
+    }
+    Some(matched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(range: &str, inside: &[&str], outside: &[&str]) {
+        for version in inside {
+            assert_eq!(satisfies(version, range), Some(true), "{} in {}", version, range);
+        }
+        for version in outside {
+            assert_eq!(satisfies(version, range), Some(false), "{} not in {}", version, range);
+        }
+    }
+
+    #[test]
+    fn caret_ranges() {
+        check("^1.2.3", &["1.2.3", "1.9.0"], &["1.2.2", "2.0.0", "2.0.0-rc.1"]);
+        check("^0.2.3", &["0.2.3", "0.2.9"], &["0.3.0"]);
+        check("^0.0.3", &["0.0.3"], &["0.0.4"]);
+        check("^1.2", &["1.2.0", "1.99.0"], &["1.1.9", "2.0.0"]);
+    }
+
+    #[test]
+    fn tilde_ranges() {
+        check("~1.2.3", &["1.2.3", "1.2.9"], &["1.3.0", "1.2.2"]);
+        check("~1", &["1.0.0", "1.9.9"], &["2.0.0"]);
+        check("~> 0.15.0", &["0.15.4"], &["0.16.0"]);
+    }
+
+    #[test]
+    fn x_ranges() {
+        check("1.x", &["1.0.0", "1.9.9"], &["0.9.9", "2.0.0"]);
+        check("1.2.*", &["1.2.0", "1.2.9"], &["1.3.0"]);
+        check("*", &["0.0.1", "19.0.0"], &["19.0.0-rc.1"]);
+        check(">=2", &["2.0.0", "3.1.0"], &["1.9.9"]);
+        check("<=1.2", &["1.2.9"], &["1.3.0"]);
+    }
+
+    #[test]
+    fn hyphen_ranges() {
+        check("1.2.3 - 2.3", &["1.2.3", "2.3.9"], &["1.2.2", "2.4.0"]);
+        check("1 - 2.0.0", &["1.0.0", "2.0.0"], &["2.0.1"]);
+    }
+
+    #[test]
+    fn or_ranges() {
+        check("^17.0.0 || ^18.0.0", &["17.0.2", "18.2.0"], &["16.14.0", "19.0.0"]);
+        check(">= 4.0.0 < 5 || 6.x", &["4.2.0", "6.1.0"], &["5.0.0"]);
+    }
+
+    #[test]
+    fn pre_releases_match_only_their_own_version() {
+        check(
+            "^19.0.0-rc.1",
+            &["19.0.0-rc.1", "19.0.0-rc.2", "19.1.0"],
+            &["19.1.0-rc.1", "19.0.0-beta.1"],
+        );
+    }
+
+    #[test]
+    fn unparsable_ranges_are_unknown() {
+        assert_eq!(satisfies("1.0.0", "latest"), None);
+        assert_eq!(satisfies("1.0.0", ">="), None);
+        assert_eq!(satisfies("next", "^1.0.0"), None);
+    }
+
+    #[test]
+    fn embedded_catalog_is_consistent() {
+        let catalog = Catalog::embedded().unwrap();
+        assert!(catalog.problems(None).is_empty(), "{:#?}", catalog.problems(None));
+    }
+}
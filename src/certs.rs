@@ -0,0 +1,402 @@
+use openssl::{
+    asn1::{Asn1Integer, Asn1Time},
+    base64,
+    bn::{BigNum, MsbOption},
+    ec::{EcGroup, EcKey},
+    hash::MessageDigest,
+    nid::Nid,
+    pkey::{PKey, Private},
+    x509::{
+        extension::{
+            AuthorityKeyIdentifier, BasicConstraints, ExtendedKeyUsage, KeyUsage,
+            SubjectAlternativeName, SubjectKeyIdentifier,
+        },
+        X509Builder, X509NameBuilder, X509,
+    },
+};
+use super::effects;
+use std::{cmp::Ordering, env, fs, io, net::IpAddr, path::PathBuf};
+
+const CA_CERT_FILE: &str = "rootCA.pem";
+const CA_KEY_FILE: &str = "rootCA-key.pem";
+const CA_VALID_DAYS: u32 = 3650;
+
+/// Local certificate authority, laid out like mkcert's CAROOT so the same
+/// trust instructions apply.
+pub struct CertificateAuthority {
+    cert: X509,
+    key: PKey<Private>,
+}
+
+/// PEM encoded leaf certificate (followed by the CA) and its private key.
+pub struct LeafCertificate {
+    pub chain_pem: Vec<u8>,
+    pub key_pem: Vec<u8>,
+    pub not_after: String,
+}
+
+/// `$RUSTIFY_CAROOT`, or `~/.local/share/rustify` like mkcert.
+pub fn ca_root() -> io::Result<PathBuf> {
+    if let Ok(dir) = env::var("RUSTIFY_CAROOT") {
+        return Ok(PathBuf::from(dir));
+    }
+    env::var("HOME")
+        .or_else(|_| env::var("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".local").join("share").join("rustify"))
+        .map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "Cannot locate home directory; set RUSTIFY_CAROOT",
+            )
+        })
+}
+
+impl CertificateAuthority {
+    /// Loads the CA from `dir`, creating it on first use.
+    pub fn load_or_create(dir: PathBuf) -> io::Result<Self> {
+        let cert_path = dir.join(CA_CERT_FILE);
+        let key_path = dir.join(CA_KEY_FILE);
+
+        if cert_path.exists() && key_path.exists() {
+            let cert = X509::from_pem(&fs::read(&cert_path)?)?;
+            let key = PKey::private_key_from_pem(&fs::read(&key_path)?)?;
+            return Ok(CertificateAuthority { cert, key });
+        }
+
+        println!("🔏 Creating local certificate authority in {}", dir.display());
+        let key = new_key()?;
+
+        let mut name = X509NameBuilder::new()?;
+        name.append_entry_by_nid(Nid::ORGANIZATIONNAME, "rustify development CA")?;
+        name.append_entry_by_nid(Nid::COMMONNAME, "rustify local CA")?;
+        let name = name.build();
+
+        let mut builder = X509Builder::new()?;
+        builder.set_version(2)?;
+        let serial = random_serial()?;
+        builder.set_serial_number(&serial)?;
+        builder.set_subject_name(&name)?;
+        builder.set_issuer_name(&name)?;
+        builder.set_pubkey(&key)?;
+        let not_before = Asn1Time::days_from_now(0)?;
+        let not_after = Asn1Time::days_from_now(CA_VALID_DAYS)?;
+        builder.set_not_before(&not_before)?;
+        builder.set_not_after(&not_after)?;
+        builder.append_extension(BasicConstraints::new().critical().ca().pathlen(0).build()?)?;
+        builder.append_extension(KeyUsage::new().critical().key_cert_sign().crl_sign().build()?)?;
+        let ski = SubjectKeyIdentifier::new().build(&builder.x509v3_context(None, None))?;
+        builder.append_extension(ski)?;
+        builder.sign(&key, MessageDigest::sha256())?;
+        let cert = builder.build();
+
+        effects::create_dir_all(&dir)?;
+        effects::write(&cert_path, cert.to_pem()?)?;
+        effects::write_private(&key_path, key.private_key_to_pem_pkcs8()?)?;
+
+        print_trust_instructions(&cert_path);
+        Ok(CertificateAuthority { cert, key })
+    }
+
+    pub fn cert_pem(&self) -> io::Result<Vec<u8>> {
+        Ok(self.cert.to_pem()?)
+    }
+
+    /// Issues a server certificate for `hosts` (DNS names, wildcards or IPs).
+    pub fn issue(&self, hosts: &[String], valid_days: u32) -> io::Result<LeafCertificate> {
+        let first = hosts.first().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "No hosts to issue a certificate for")
+        })?;
+        let key = new_key()?;
+
+        let mut name = X509NameBuilder::new()?;
+        name.append_entry_by_nid(Nid::ORGANIZATIONNAME, "rustify development certificate")?;
+        name.append_entry_by_nid(Nid::COMMONNAME, first)?;
+        let name = name.build();
+
+        let mut builder = X509Builder::new()?;
+        builder.set_version(2)?;
+        let serial = random_serial()?;
+        builder.set_serial_number(&serial)?;
+        builder.set_subject_name(&name)?;
+        builder.set_issuer_name(self.cert.subject_name())?;
+        builder.set_pubkey(&key)?;
+        let not_before = Asn1Time::days_from_now(0)?;
+        let not_after = Asn1Time::days_from_now(valid_days)?;
+        builder.set_not_before(&not_before)?;
+        builder.set_not_after(&not_after)?;
+        builder.append_extension(BasicConstraints::new().critical().build()?)?;
+        builder.append_extension(
+            KeyUsage::new()
+                .critical()
+                .digital_signature()
+                .key_encipherment()
+                .build()?,
+        )?;
+        builder.append_extension(ExtendedKeyUsage::new().server_auth().build()?)?;
+
+        let mut san = SubjectAlternativeName::new();
+        for host in hosts {
+            if host.parse::<IpAddr>().is_ok() {
+                san.ip(host);
+            } else {
+                san.dns(host);
+            }
+        }
+        let san = san.build(&builder.x509v3_context(Some(&self.cert), None))?;
+        builder.append_extension(san)?;
+        let aki = AuthorityKeyIdentifier::new()
+            .keyid(false)
+            .build(&builder.x509v3_context(Some(&self.cert), None))?;
+        builder.append_extension(aki)?;
+        builder.sign(&self.key, MessageDigest::sha256())?;
+        let cert = builder.build();
+
+        let mut chain_pem = cert.to_pem()?;
+        chain_pem.extend(self.cert.to_pem()?);
+
+        Ok(LeafCertificate {
+            chain_pem,
+            key_pem: key.private_key_to_pem_pkcs8()?,
+            not_after: cert.not_after().to_string(),
+        })
+    }
+
+    /// Whether `cert_pem` must be replaced: it was not signed by this CA,
+    /// misses one of `hosts`, or expires within `renew_before_days`.
+    pub fn needs_rotation(
+        &self,
+        cert_pem: &[u8],
+        hosts: &[String],
+        renew_before_days: u32,
+    ) -> io::Result<bool> {
+        let cert = match X509::from_pem(cert_pem) {
+            Ok(cert) => cert,
+            Err(_) => return Ok(true),
+        };
+
+        if !cert.verify(&self.key).unwrap_or(false) {
+            return Ok(true);
+        }
+
+        let threshold = Asn1Time::days_from_now(renew_before_days)?;
+        if cert.not_after().compare(&threshold)? != Ordering::Greater {
+            return Ok(true);
+        }
+
+        let names: Vec<String> = cert
+            .subject_alt_names()
+            .map(|sans| {
+                sans.iter()
+                    .filter_map(|name| {
+                        name.dnsname().map(String::from).or_else(|| {
+                            name.ipaddress().and_then(|bytes| match bytes.len() {
+                                4 => Some(IpAddr::from(<[u8; 4]>::try_from(bytes).ok()?).to_string()),
+                                16 => Some(IpAddr::from(<[u8; 16]>::try_from(bytes).ok()?).to_string()),
+                                _ => None,
+                            })
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(hosts.iter().any(|host| !names.contains(host)))
+    }
+}
+
+fn new_key() -> io::Result<PKey<Private>> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+    Ok(PKey::from_ec_key(EcKey::generate(&group)?)?)
+}
+
+fn random_serial() -> io::Result<Asn1Integer> {
+    let mut serial = BigNum::new()?;
+    serial.rand(127, MsbOption::MAYBE_ZERO, false)?;
+    Ok(serial.to_asn1_integer()?)
+}
+
+fn print_trust_instructions(cert_path: &std::path::Path) {
+    let path = cert_path.display();
+    println!("📜 Trust bundle written to {}", path);
+    println!("   To trust it locally:");
+    if cfg!(target_os = "macos") {
+        println!(
+            "   sudo security add-trusted-cert -d -r trustRoot -k /Library/Keychains/System.keychain {}",
+            path
+        );
+    } else if cfg!(windows) {
+        println!("   certutil -addstore -f ROOT {}", path);
+    } else {
+        println!(
+            "   sudo cp {} /usr/local/share/ca-certificates/rustify-rootCA.crt && sudo update-ca-certificates",
+            path
+        );
+    }
+}
+
+/// `kubernetes.io/tls` Secret holding `cert` plus the CA as `ca.crt`.
+pub fn tls_secret_manifest(
+    name: &str,
+    namespace: &str,
+    cert: &LeafCertificate,
+    ca_pem: &[u8],
+) -> String {
+    format!(
+        r#"apiVersion: v1
+kind: Secret
+metadata:
+  name: {name}
+  namespace: {namespace}
+  labels:
+    app.kubernetes.io/managed-by: rustify
+  annotations:
+    rustify.dev/not-after: "{not_after}"
+type: kubernetes.io/tls
+data:
+  tls.crt: {crt}
+  tls.key: {key}
+  ca.crt: {ca}
+"#,
+        not_after = cert.not_after,
+        crt = base64::encode_block(&cert.chain_pem),
+        key = base64::encode_block(&cert.key_pem),
+        ca = base64::encode_block(ca_pem),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::MutexGuard;
+
+    /// CA root in a scratch directory, removed on drop.
+    struct CaRoot {
+        dir: PathBuf,
+        _guard: MutexGuard<'static, ()>,
+    }
+
+    impl CaRoot {
+        fn new(name: &str) -> Self {
+            let guard = effects::TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+            let dir = std::env::temp_dir().join(format!("rustify-certs-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            CaRoot { dir, _guard: guard }
+        }
+
+        fn load(&self) -> CertificateAuthority {
+            CertificateAuthority::load_or_create(self.dir.clone()).unwrap()
+        }
+    }
+
+    impl Drop for CaRoot {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn hosts(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn creates_the_ca_once_and_reloads_it() {
+        let root = CaRoot::new("reload");
+        let ca = root.load();
+        assert!(root.dir.join(CA_CERT_FILE).exists());
+        assert!(root.dir.join(CA_KEY_FILE).exists());
+
+        let cert = X509::from_pem(&ca.cert_pem().unwrap()).unwrap();
+        let constraints = cert.to_text().unwrap();
+        assert!(String::from_utf8_lossy(&constraints).contains("CA:TRUE"));
+        assert!(cert.verify(&cert.public_key().unwrap()).unwrap(), "CA is self-signed");
+
+        let reloaded = root.load();
+        assert_eq!(reloaded.cert_pem().unwrap(), ca.cert_pem().unwrap());
+    }
+
+    #[test]
+    fn issues_leaves_with_dns_and_ip_sans() {
+        let root = CaRoot::new("issue");
+        let ca = root.load();
+        let leaf = ca
+            .issue(&hosts(&["app.localhost", "*.app.localhost", "127.0.0.1", "::1"]), 30)
+            .unwrap();
+
+        let chain = X509::stack_from_pem(&leaf.chain_pem).unwrap();
+        assert_eq!(chain.len(), 2, "leaf followed by the CA");
+        assert_eq!(chain[1].to_pem().unwrap(), ca.cert_pem().unwrap());
+        assert!(chain[0].verify(&chain[1].public_key().unwrap()).unwrap());
+
+        let common_name = chain[0]
+            .subject_name()
+            .entries_by_nid(Nid::COMMONNAME)
+            .next()
+            .unwrap()
+            .data()
+            .as_utf8()
+            .unwrap()
+            .to_string();
+        assert_eq!(common_name, "app.localhost");
+
+        let sans = chain[0].subject_alt_names().unwrap();
+        let dns: Vec<&str> = sans.iter().filter_map(|name| name.dnsname()).collect();
+        assert_eq!(dns, ["app.localhost", "*.app.localhost"]);
+        let ips: Vec<usize> = sans.iter().filter_map(|name| name.ipaddress()).map(<[u8]>::len).collect();
+        assert_eq!(ips, [4, 16]);
+
+        let key = PKey::private_key_from_pem(&leaf.key_pem).unwrap();
+        assert!(chain[0].public_key().unwrap().public_eq(&key));
+        assert_eq!(leaf.not_after, chain[0].not_after().to_string());
+    }
+
+    #[test]
+    fn refuses_to_issue_without_hosts() {
+        let root = CaRoot::new("empty");
+        let err = root.load().issue(&[], 30).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn fresh_leaves_do_not_need_rotation() {
+        let root = CaRoot::new("fresh");
+        let ca = root.load();
+        let names = hosts(&["app.localhost", "10.0.0.1"]);
+        let leaf = ca.issue(&names, 90).unwrap();
+        assert!(!ca.needs_rotation(&leaf.chain_pem, &names, 30).unwrap());
+        assert!(
+            !ca.needs_rotation(&leaf.chain_pem, &names[..1], 30).unwrap(),
+            "dropping a host keeps the certificate"
+        );
+    }
+
+    #[test]
+    fn rotates_within_the_renewal_threshold() {
+        let root = CaRoot::new("threshold");
+        let ca = root.load();
+        let names = hosts(&["app.localhost"]);
+        let leaf = ca.issue(&names, 10).unwrap();
+        assert!(!ca.needs_rotation(&leaf.chain_pem, &names, 9).unwrap());
+        assert!(ca.needs_rotation(&leaf.chain_pem, &names, 10).unwrap());
+        assert!(ca.needs_rotation(&leaf.chain_pem, &names, 30).unwrap());
+    }
+
+    #[test]
+    fn rotates_for_new_hosts_other_cas_and_garbage() {
+        let root = CaRoot::new("rotate");
+        let ca = root.load();
+        let names = hosts(&["app.localhost"]);
+        let leaf = ca.issue(&names, 90).unwrap();
+
+        assert!(ca
+            .needs_rotation(&leaf.chain_pem, &hosts(&["app.localhost", "api.localhost"]), 30)
+            .unwrap());
+        assert!(ca.needs_rotation(b"not a certificate", &names, 30).unwrap());
+
+        let other_root = std::env::temp_dir().join(format!("rustify-certs-other-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&other_root);
+        let other = CertificateAuthority::load_or_create(other_root.clone()).unwrap();
+        let foreign = other.issue(&names, 90).unwrap();
+        let _ = fs::remove_dir_all(&other_root);
+        assert!(ca.needs_rotation(&foreign.chain_pem, &names, 30).unwrap());
+    }
+}
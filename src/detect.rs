@@ -0,0 +1,886 @@
+use super::stacks;
+use serde_json::Value;
+use std::{cmp::Reverse, fmt, fs, io, path::Path};
+
+/// Evidence that a project uses a framework, and what it is worth.
+#[derive(Debug, Clone, Copy)]
+enum Rule {
+    /// A package in `dependencies`; in `devDependencies` it counts two less.
+    Dep(&'static str, u32),
+    /// A dependency of a nested package, e.g. `server/package.json`.
+    DepIn(&'static str, &'static str, u32),
+    /// A file; `name.*` matches any extension.
+    File(&'static str, u32),
+    Dir(&'static str, u32),
+    /// A package.json script that runs this command.
+    Script(&'static str, u32),
+    /// Rules the framework out while this file exists.
+    Unless(&'static str),
+}
+
+use Rule::*;
+
+struct Definition {
+    name: &'static str,
+    /// Frameworks this one is built on. They lose to it when both match, so
+    /// a Remix app is not reported as React.
+    builds_on: &'static [&'static str],
+    rules: &'static [Rule],
+}
+
+/// Ties go to the framework listed first.
+const DEFINITIONS: &[Definition] = &[
+    Definition {
+        name: "next",
+        builds_on: &["react", "node"],
+        rules: &[Dep("next", 10), File("next.config.*", 6), Script("next", 4), Dir("pages", 1)],
+    },
+    Definition {
+        name: "remix",
+        builds_on: &["react", "node"],
+        rules: &[
+            Dep("@remix-run/react", 10),
+            Dep("@remix-run/dev", 6),
+            File("remix.config.*", 6),
+            File("app/root.tsx", 4),
+            File("app/root.jsx", 4),
+            Script("remix", 4),
+        ],
+    },
+    Definition {
+        name: "nuxt",
+        builds_on: &["vue", "node"],
+        rules: &[Dep("nuxt", 10), File("nuxt.config.*", 6), Script("nuxt", 4), Script("nuxi", 4)],
+    },
+    Definition {
+        name: "astro",
+        builds_on: &["react", "vue", "svelte", "node"],
+        rules: &[Dep("astro", 10), File("astro.config.*", 6), Script("astro", 4), Dir("src/pages", 1)],
+    },
+    Definition {
+        name: "angular",
+        builds_on: &["node"],
+        rules: &[Dep("@angular/core", 10), File("angular.json", 8), Script("ng", 4)],
+    },
+    Definition {
+        name: "mern",
+        builds_on: &["react", "node"],
+        rules: &[
+            File("client/package.json", 5),
+            File("server/package.json", 5),
+            DepIn("server/package.json", "mongoose", 6),
+            DepIn("server/package.json", "express", 3),
+        ],
+    },
+    Definition {
+        name: "svelte",
+        builds_on: &["node"],
+        rules: &[
+            Dep("@sveltejs/kit", 10),
+            Dep("svelte", 8),
+            Dep("@sveltejs/vite-plugin-svelte", 6),
+            File("svelte.config.*", 4),
+            File("src/App.svelte", 3),
+        ],
+    },
+    Definition {
+        name: "vue",
+        builds_on: &["node"],
+        rules: &[
+            Dep("vue", 8),
+            Dep("@vitejs/plugin-vue", 6),
+            File("vue.config.*", 6),
+            Script("vue-cli-service", 4),
+            File("src/App.vue", 3),
+        ],
+    },
+    Definition {
+        name: "react",
+        builds_on: &["node"],
+        rules: &[
+            Dep("react", 8),
+            Dep("react-dom", 4),
+            Dep("@vitejs/plugin-react", 6),
+            Dep("react-scripts", 6),
+            Script("react-scripts", 4),
+        ],
+    },
+    Definition {
+        name: "bun",
+        builds_on: &["node"],
+        rules: &[
+            File("bun.lockb", 6),
+            File("bun.lock", 6),
+            File("bunfig.toml", 4),
+            Dep("@types/bun", 4),
+            Script("bun", 2),
+        ],
+    },
+    Definition {
+        name: "node",
+        builds_on: &[],
+        rules: &[
+            File("package.json", 1),
+            Dep("express", 3),
+            Dep("fastify", 3),
+            Dep("koa", 3),
+            Dep("hono", 3),
+        ],
+    },
+    Definition {
+        name: "rust",
+        builds_on: &[],
+        rules: &[File("Cargo.toml", 10)],
+    },
+    Definition {
+        name: "go",
+        builds_on: &[],
+        rules: &[File("go.mod", 10)],
+    },
+    Definition {
+        name: "python",
+        builds_on: &[],
+        rules: &[
+            File("pyproject.toml", 10),
+            File("requirements.txt", 10),
+            File("uv.lock", 2),
+            File("manage.py", 2),
+        ],
+    },
+    Definition {
+        name: "static",
+        builds_on: &[],
+        // With a package.json, index.html is usually a bundler's entry point
+        rules: &[File("index.html", 3), Unless("package.json")],
+    },
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rendering {
+    /// Rendered per request by a server the app ships.
+    Ssr,
+    /// Pre-rendered HTML, served as files.
+    Ssg,
+    /// One HTML shell and client-side routing, served as files.
+    Spa,
+    /// An API or other backend service.
+    Server,
+}
+
+impl Rendering {
+    /// Whether the build output is plain files that need no app runtime.
+    pub fn is_static(self) -> bool {
+        matches!(self, Rendering::Ssg | Rendering::Spa)
+    }
+}
+
+impl fmt::Display for Rendering {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Rendering::Ssr => "SSR",
+            Rendering::Ssg => "SSG",
+            Rendering::Spa => "SPA",
+            Rendering::Server => "server",
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Candidate {
+    pub name: &'static str,
+    pub score: u32,
+    pub signals: Vec<String>,
+    /// The higher-level framework this one lost to.
+    pub part_of: Option<&'static str>,
+}
+
+#[derive(Debug)]
+pub struct Detection {
+    pub framework: &'static str,
+    pub score: u32,
+    pub rendering: Rendering,
+    pub output_dir: Option<String>,
+    pub start_command: Option<String>,
+    pub port: u16,
+    /// Where the port came from, e.g. "the start script".
+    pub port_source: String,
+    /// Every framework with a positive score, winner first.
+    pub candidates: Vec<Candidate>,
+}
+
+/// The project directory and its package.json, when it has one.
+struct Project<'a> {
+    root: &'a Path,
+    package: Option<Value>,
+}
+
+/// `path` under `root`, parsed; `None` when there is no such file.
+fn read_package(root: &Path, path: &str) -> io::Result<Option<Value>> {
+    match fs::read_to_string(root.join(path)) {
+        Ok(json) => serde_json::from_str(&json).map(Some).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("Invalid {}: {}", path, e))
+        }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Dependency section of `package` listing `name`.
+fn dependency_section(package: &Value, name: &str) -> Option<&'static str> {
+    ["dependencies", "devDependencies", "peerDependencies"]
+        .into_iter()
+        .find(|section| package[*section].get(name).is_some())
+}
+
+impl Project<'_> {
+    fn has(&self, name: &str) -> bool {
+        self.package
+            .as_ref()
+            .is_some_and(|p| dependency_section(p, name).is_some())
+    }
+
+    fn script(&self, name: &str) -> Option<&str> {
+        self.package.as_ref()?["scripts"][name].as_str()
+    }
+
+    /// Name of the first script that runs `command`.
+    fn script_running(&self, command: &str) -> Option<&str> {
+        let scripts = self.package.as_ref()?["scripts"].as_object()?;
+        scripts.iter().find_map(|(name, script)| {
+            let runs = script
+                .as_str()?
+                .split(|c: char| c.is_whitespace() || c == '&' || c == ';' || c == '|')
+                .any(|word| word == command);
+            runs.then(|| name.as_str())
+        })
+    }
+
+    /// How the project's package manager runs a script.
+    fn run(&self, script: &str) -> String {
+        let manager = if self.file("pnpm-lock.yaml").is_some() {
+            "pnpm"
+        } else if self.file("yarn.lock").is_some() {
+            "yarn"
+        } else if self.file("bun.lockb").or_else(|| self.file("bun.lock")).is_some() {
+            "bun run"
+        } else if script == "start" {
+            "npm"
+        } else {
+            "npm run"
+        };
+        format!("{} {}", manager, script)
+    }
+
+    /// Path of the file matching `pattern`, relative to the project, where
+    /// `name.*` matches any extension.
+    fn file(&self, pattern: &str) -> Option<String> {
+        file(self.root, pattern)
+    }
+
+    /// Whether `path` exists in the project.
+    fn exists(&self, path: &str) -> bool {
+        self.root.join(path).exists()
+    }
+
+    fn read(&self, path: &str) -> Option<String> {
+        fs::read_to_string(self.root.join(path)).ok()
+    }
+}
+
+fn file(root: &Path, pattern: &str) -> Option<String> {
+    let stem = match pattern.strip_suffix(".*") {
+        Some(stem) => stem,
+        None => return root.join(pattern).is_file().then(|| pattern.to_string()),
+    };
+    let path = Path::new(stem);
+    let dir = match path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        Some(dir) => root.join(dir),
+        None => root.to_path_buf(),
+    };
+    let prefix = format!("{}.", path.file_name()?.to_string_lossy());
+    let mut matches: Vec<String> = fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.path().is_file())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| name.starts_with(&prefix))
+        .collect();
+    matches.sort();
+    let name = matches.into_iter().next()?;
+    Some(match path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        Some(dir) => format!("{}/{}", dir.display(), name),
+        None => name,
+    })
+}
+
+/// Whether a framework config sets `key` to the string `value`, e.g.
+/// `output: 'export'`.
+fn config_sets(project: &Project, pattern: &str, key: &str, value: &str) -> bool {
+    project
+        .file(pattern)
+        .and_then(|path| project.read(&path))
+        .is_some_and(|source| {
+            let compact: String = source.chars().filter(|c| !c.is_whitespace()).collect();
+            ["'", "\"", "`"]
+                .iter()
+                .any(|q| compact.contains(&format!("{}:{}{}{}", key, q, value, q)))
+        })
+}
+
+fn score(definition: &Definition, project: &Project) -> Candidate {
+    let mut candidate = Candidate {
+        name: definition.name,
+        score: 0,
+        signals: Vec::new(),
+        part_of: None,
+    };
+    for rule in definition.rules {
+        let (weight, signal) = match *rule {
+            Dep(name, weight) => match project.package.as_ref().and_then(|p| dependency_section(p, name)) {
+                Some("dependencies") => (weight, format!("{} in dependencies", name)),
+                Some(section) => (weight.saturating_sub(2).max(1), format!("{} in {}", name, section)),
+                None => continue,
+            },
+            DepIn(manifest, name, weight) => {
+                let listed = read_package(project.root, manifest)
+                    .ok()
+                    .flatten()
+                    .is_some_and(|p| dependency_section(&p, name).is_some());
+                if !listed {
+                    continue;
+                }
+                (weight, format!("{} in {}", name, manifest))
+            }
+            File(pattern, weight) => match project.file(pattern) {
+                Some(path) => (weight, path),
+                None => continue,
+            },
+            Dir(dir, weight) => {
+                if !project.root.join(dir).is_dir() {
+                    continue;
+                }
+                (weight, format!("{}/", dir))
+            }
+            Script(command, weight) => match project.script_running(command) {
+                Some(script) => (weight, format!("`{}` script runs {}", script, command)),
+                None => continue,
+            },
+            Unless(path) => {
+                if project.exists(path) {
+                    return Candidate {
+                        score: 0,
+                        ..candidate
+                    };
+                }
+                continue;
+            }
+        };
+        candidate.score += weight;
+        candidate.signals.push(format!("{} (+{})", signal, weight));
+    }
+    candidate
+}
+
+fn definition(name: &str) -> &'static Definition {
+    DEFINITIONS
+        .iter()
+        .find(|d| d.name == name)
+        .expect("candidates come from DEFINITIONS")
+}
+
+/// Scores every known framework against the current directory and
+/// describes how the winner builds and runs.
+pub fn detect() -> io::Result<Detection> {
+    detect_in(Path::new("."))
+}
+
+/// Like `detect`, for the project in `root`. Rust and Python start commands
+/// still come from `stacks`, which reads the current directory.
+fn detect_in(root: &Path) -> io::Result<Detection> {
+    let project = Project {
+        root,
+        package: read_package(root, "package.json")?,
+    };
+    let mut candidates: Vec<Candidate> = DEFINITIONS
+        .iter()
+        .map(|d| score(d, &project))
+        .filter(|c| c.score > 0)
+        .collect();
+
+    for i in 0..candidates.len() {
+        let name = candidates[i].name;
+        candidates[i].part_of = candidates
+            .iter()
+            .filter(|c| definition(c.name).builds_on.contains(&name))
+            .max_by_key(|c| c.score)
+            .map(|c| c.name);
+    }
+    // Stable, so equal scores keep the DEFINITIONS order
+    candidates.sort_by_key(|c| (c.part_of.is_some(), Reverse(c.score)));
+
+    let winner = match candidates.first() {
+        Some(winner) if winner.part_of.is_none() => winner,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "No project found: expected package.json, Cargo.toml, go.mod, pyproject.toml, requirements.txt or index.html",
+            ))
+        }
+    };
+    let framework = winner.name;
+    let rendering = rendering(framework, &project);
+    let (port, port_source) = port(framework, &project);
+    Ok(Detection {
+        framework,
+        score: winner.score,
+        rendering,
+        output_dir: output_dir(framework, rendering, &project),
+        start_command: start_command(framework, rendering, &project, port),
+        port,
+        port_source,
+        candidates,
+    })
+}
+
+impl Detection {
+    /// The directory a web server can serve as is, when the project builds
+    /// to plain files.
+    pub fn static_output(&self) -> Option<&str> {
+        self.output_dir
+            .as_deref()
+            .filter(|_| self.rendering.is_static())
+    }
+}
+
+fn rendering(framework: &str, project: &Project) -> Rendering {
+    match framework {
+        "next" if config_sets(project, "next.config.*", "output", "export") => Rendering::Ssg,
+        "next" | "remix" => Rendering::Ssr,
+        "nuxt" => {
+            let generates = project
+                .script("build")
+                .is_some_and(|s| s.contains("nuxt generate") || s.contains("nuxi generate"));
+            if project
+                .file("nuxt.config.*")
+                .and_then(|p| project.read(&p))
+                .is_some_and(|c| c.chars().filter(|c| !c.is_whitespace()).collect::<String>().contains("ssr:false"))
+            {
+                Rendering::Spa
+            } else if generates {
+                Rendering::Ssg
+            } else {
+                Rendering::Ssr
+            }
+        }
+        "astro" => {
+            let adapter = ["@astrojs/node", "@astrojs/vercel", "@astrojs/netlify", "@astrojs/cloudflare"]
+                .iter()
+                .any(|a| project.has(a));
+            if adapter
+                || config_sets(project, "astro.config.*", "output", "server")
+                || config_sets(project, "astro.config.*", "output", "hybrid")
+            {
+                Rendering::Ssr
+            } else {
+                Rendering::Ssg
+            }
+        }
+        "svelte" if project.has("@sveltejs/kit") => {
+            if project.has("@sveltejs/adapter-static") {
+                Rendering::Ssg
+            } else {
+                Rendering::Ssr
+            }
+        }
+        "angular" if project.has("@angular/ssr") => Rendering::Ssr,
+        "react" | "vue" | "svelte" | "angular" => Rendering::Spa,
+        "static" => Rendering::Ssg,
+        _ => Rendering::Server,
+    }
+}
+
+fn output_dir(framework: &str, rendering: Rendering, project: &Project) -> Option<String> {
+    let dir = match framework {
+        "next" if rendering == Rendering::Ssg => "out",
+        "next" => ".next",
+        "remix" => "build",
+        "nuxt" if rendering == Rendering::Ssg => ".output/public",
+        "nuxt" => ".output",
+        "astro" | "vue" => "dist",
+        "svelte" if project.has("@sveltejs/kit") => "build",
+        "svelte" => "dist",
+        "react" if project.has("react-scripts") => "build",
+        "react" => "dist",
+        "angular" => return Some(angular_output_dir(project)),
+        "mern" => {
+            let vite = read_package(project.root, "client/package.json")
+                .ok()
+                .flatten()
+                .is_some_and(|p| dependency_section(&p, "vite").is_some());
+            if vite {
+                "client/dist"
+            } else {
+                "client/build"
+            }
+        }
+        "rust" => "target/release",
+        "static" => ".",
+        _ => return None,
+    };
+    Some(dir.to_string())
+}
+
+/// `outputPath` of the first project in angular.json. The application
+/// builder puts the browser bundle in a `browser` subdirectory.
+fn angular_output_dir(project: &Project) -> String {
+    let workspace = read_package(project.root, "angular.json").ok().flatten();
+    let build = workspace
+        .as_ref()
+        .and_then(|w| w["projects"].as_object()?.values().next())
+        .map(|project| &project["architect"]["build"]);
+    let build = match build {
+        Some(build) => build,
+        None => return "dist".to_string(),
+    };
+    let application = build["builder"]
+        .as_str()
+        .is_some_and(|b| b.ends_with(":application"));
+    match &build["options"]["outputPath"] {
+        Value::String(path) if application => format!("{}/browser", path),
+        Value::String(path) => path.clone(),
+        Value::Object(path) => format!(
+            "{}/{}",
+            path.get("base").and_then(Value::as_str).unwrap_or("dist"),
+            path.get("browser").and_then(Value::as_str).unwrap_or("browser")
+        ),
+        _ => "dist".to_string(),
+    }
+}
+
+fn start_command(framework: &str, rendering: Rendering, project: &Project, port: u16) -> Option<String> {
+    match framework {
+        "rust" => return Some(format!("./target/release/{}", stacks::rust_binary().ok()?)),
+        "go" => return Some("go run .".to_string()),
+        "python" => {
+            let command: Vec<String> =
+                serde_json::from_str(&stacks::python_command(&port.to_string()).ok()?).ok()?;
+            return Some(command.join(" "));
+        }
+        "static" => return None,
+        _ => {}
+    }
+    if project.script("start").is_some() {
+        return Some(project.run("start"));
+    }
+    match (framework, rendering) {
+        (_, rendering) if rendering.is_static() => None,
+        ("next", _) => Some("next start".to_string()),
+        ("nuxt", _) => Some("node .output/server/index.mjs".to_string()),
+        ("remix", _) => Some("remix-serve ./build/server/index.js".to_string()),
+        ("astro", _) => Some("node ./dist/server/entry.mjs".to_string()),
+        ("svelte", _) => Some("node build".to_string()),
+        _ => None,
+    }
+}
+
+/// Port given by `-p`, `--port` or `PORT=` in a script.
+fn script_port(script: &str) -> Option<u16> {
+    let words: Vec<&str> = script.split_whitespace().collect();
+    words.iter().enumerate().find_map(|(i, word)| {
+        if let Some(port) = word.strip_prefix("--port=").or_else(|| word.strip_prefix("PORT=")) {
+            return port.parse().ok();
+        }
+        if matches!(*word, "-p" | "--port") {
+            return words.get(i + 1)?.parse().ok();
+        }
+        None
+    })
+}
+
+fn port(framework: &str, project: &Project) -> (u16, String) {
+    for script in ["start", "preview"] {
+        if let Some(port) = project.script(script).and_then(script_port) {
+            return (port, format!("the {} script", script));
+        }
+    }
+    let from_env = project.read(".env").and_then(|env| {
+        env.lines()
+            .find_map(|line| line.trim().strip_prefix("PORT="))
+            .and_then(|port| port.trim().trim_matches('"').parse().ok())
+    });
+    if let Some(port) = from_env {
+        return (port, ".env".to_string());
+    }
+
+    let port = match framework {
+        "angular" => 4200,
+        "astro" => 4321,
+        "vue" if project.has("@vue/cli-service") => 8080,
+        "react" if project.has("react-scripts") => 3000,
+        "react" | "vue" | "svelte" if project.has("vite") => 5173,
+        "python" => 8000,
+        "go" | "rust" => 8080,
+        "static" => 80,
+        _ => 3000,
+    };
+    (port, format!("{} default", framework))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// A scratch project, removed when dropped.
+    struct Fixture {
+        dir: PathBuf,
+    }
+
+    impl Fixture {
+        fn new(name: &str, files: &[(&str, &str)]) -> Self {
+            let dir = std::env::temp_dir().join(format!("rustify-detect-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            for (path, contents) in files {
+                let path = dir.join(path);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, contents).unwrap();
+            }
+            fs::create_dir_all(&dir).unwrap();
+            Fixture { dir }
+        }
+
+        fn detect(&self) -> Detection {
+            detect_in(&self.dir).unwrap()
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn assert_detects(
+        detection: &Detection,
+        framework: &str,
+        rendering: Rendering,
+        output_dir: Option<&str>,
+        port: u16,
+    ) {
+        assert_eq!(detection.framework, framework, "{:#?}", detection.candidates);
+        assert_eq!(detection.rendering, rendering);
+        assert_eq!(detection.output_dir.as_deref(), output_dir);
+        assert_eq!(detection.port, port, "port from {}", detection.port_source);
+    }
+
+    #[test]
+    fn next() {
+        let project = Fixture::new(
+            "next",
+            &[
+                (
+                    "package.json",
+                    r#"{"scripts": {"dev": "next dev", "build": "next build", "start": "next start"},
+                        "dependencies": {"next": "14.2.3", "react": "^18", "react-dom": "^18"}}"#,
+                ),
+                ("next.config.mjs", "export default { reactStrictMode: true };\n"),
+            ],
+        );
+        let detection = project.detect();
+        assert_detects(&detection, "next", Rendering::Ssr, Some(".next"), 3000);
+        assert_eq!(detection.start_command.as_deref(), Some("npm start"));
+    }
+
+    #[test]
+    fn next_static_export() {
+        let project = Fixture::new(
+            "next-export",
+            &[
+                ("package.json", r#"{"dependencies": {"next": "14.2.3", "react": "^18"}}"#),
+                ("next.config.js", "module.exports = {\n  output: 'export',\n};\n"),
+            ],
+        );
+        let detection = project.detect();
+        assert_detects(&detection, "next", Rendering::Ssg, Some("out"), 3000);
+        assert_eq!(detection.static_output(), Some("out"));
+    }
+
+    #[test]
+    fn remix_beats_react() {
+        let project = Fixture::new(
+            "remix",
+            &[
+                (
+                    "package.json",
+                    r#"{"scripts": {"build": "remix vite:build"},
+                        "dependencies": {"@remix-run/node": "^2.9.0", "@remix-run/react": "^2.9.0",
+                                         "react": "^18.2.0", "react-dom": "^18.2.0"},
+                        "devDependencies": {"@remix-run/dev": "^2.9.0", "vite": "^5.1.0"}}"#,
+                ),
+                ("app/root.tsx", "export default function App() {}\n"),
+            ],
+        );
+        let detection = project.detect();
+        assert_detects(&detection, "remix", Rendering::Ssr, Some("build"), 3000);
+        let react = detection.candidates.iter().find(|c| c.name == "react").unwrap();
+        assert_eq!(react.part_of, Some("remix"));
+    }
+
+    #[test]
+    fn vite_svelte_with_dev_dependencies_only() {
+        let project = Fixture::new(
+            "svelte",
+            &[
+                (
+                    "package.json",
+                    r#"{"type": "module", "scripts": {"dev": "vite", "build": "vite build"},
+                        "devDependencies": {"@sveltejs/vite-plugin-svelte": "^3.0.2", "svelte": "^4.2.12",
+                                            "vite": "^5.2.0"}}"#,
+                ),
+                ("src/App.svelte", "<h1>demo</h1>\n"),
+            ],
+        );
+        let detection = project.detect();
+        assert_detects(&detection, "svelte", Rendering::Spa, Some("dist"), 5173);
+        assert_eq!(detection.start_command, None);
+    }
+
+    #[test]
+    fn vite_vue_with_dev_dependencies_only() {
+        let project = Fixture::new(
+            "vue",
+            &[(
+                "package.json",
+                r#"{"scripts": {"dev": "vite", "build": "vite build", "preview": "vite preview"},
+                    "devDependencies": {"@vitejs/plugin-vue": "^5.0.4", "vite": "^5.2.0", "vue": "^3.4.21"}}"#,
+            )],
+        );
+        assert_detects(&project.detect(), "vue", Rendering::Spa, Some("dist"), 5173);
+    }
+
+    #[test]
+    fn nuxt() {
+        let project = Fixture::new(
+            "nuxt",
+            &[
+                (
+                    "package.json",
+                    r#"{"scripts": {"build": "nuxt build", "dev": "nuxt dev"},
+                        "dependencies": {"nuxt": "^3.11.0", "vue": "^3.4.21"}}"#,
+                ),
+                ("nuxt.config.ts", "export default defineNuxtConfig({});\n"),
+            ],
+        );
+        let detection = project.detect();
+        assert_detects(&detection, "nuxt", Rendering::Ssr, Some(".output"), 3000);
+        assert_eq!(detection.start_command.as_deref(), Some("node .output/server/index.mjs"));
+    }
+
+    #[test]
+    fn nuxt_generate() {
+        let project = Fixture::new(
+            "nuxt-generate",
+            &[(
+                "package.json",
+                r#"{"scripts": {"build": "nuxt generate"}, "dependencies": {"nuxt": "^3.11.0"}}"#,
+            )],
+        );
+        assert_detects(&project.detect(), "nuxt", Rendering::Ssg, Some(".output/public"), 3000);
+    }
+
+    #[test]
+    fn angular() {
+        let project = Fixture::new(
+            "angular",
+            &[
+                (
+                    "package.json",
+                    r#"{"scripts": {"start": "ng serve", "build": "ng build"},
+                        "dependencies": {"@angular/core": "^17.3.0"},
+                        "devDependencies": {"@angular/cli": "^17.3.0"}}"#,
+                ),
+                (
+                    "angular.json",
+                    r#"{"projects": {"demo": {"architect": {"build": {
+                        "builder": "@angular-devkit/build-angular:application",
+                        "options": {"outputPath": "dist/demo"}}}}}}"#,
+                ),
+            ],
+        );
+        assert_detects(&project.detect(), "angular", Rendering::Spa, Some("dist/demo/browser"), 4200);
+    }
+
+    #[test]
+    fn astro() {
+        let project = Fixture::new(
+            "astro",
+            &[
+                (
+                    "package.json",
+                    r#"{"scripts": {"build": "astro build"}, "dependencies": {"astro": "^4.5.0"}}"#,
+                ),
+                ("astro.config.mjs", "export default defineConfig({});\n"),
+                ("src/pages/index.astro", "<h1>demo</h1>\n"),
+            ],
+        );
+        assert_detects(&project.detect(), "astro", Rendering::Ssg, Some("dist"), 4321);
+    }
+
+    #[test]
+    fn astro_with_adapter() {
+        let project = Fixture::new(
+            "astro-ssr",
+            &[(
+                "package.json",
+                r#"{"dependencies": {"astro": "^4.5.0", "@astrojs/node": "^8.2.0"}}"#,
+            )],
+        );
+        assert_detects(&project.detect(), "astro", Rendering::Ssr, Some("dist"), 4321);
+    }
+
+    #[test]
+    fn mern() {
+        let project = Fixture::new(
+            "mern",
+            &[
+                (
+                    "package.json",
+                    r#"{"scripts": {"start": "node server/index.js"}}"#,
+                ),
+                (
+                    "client/package.json",
+                    r#"{"dependencies": {"react": "^18.2.0"}, "devDependencies": {"vite": "^5.2.0"}}"#,
+                ),
+                (
+                    "server/package.json",
+                    r#"{"dependencies": {"express": "^4.19.0", "mongoose": "^8.2.0"}}"#,
+                ),
+                (".env", "MONGODB_URI=mongodb://localhost/demo\nPORT=5000\n"),
+            ],
+        );
+        let detection = project.detect();
+        assert_detects(&detection, "mern", Rendering::Server, Some("client/dist"), 5000);
+        assert_eq!(detection.port_source, ".env");
+    }
+
+    #[test]
+    fn start_script_sets_port() {
+        let project = Fixture::new(
+            "port",
+            &[(
+                "package.json",
+                r#"{"scripts": {"start": "next start -p 4000"}, "dependencies": {"next": "14.2.3"}}"#,
+            )],
+        );
+        let detection = project.detect();
+        assert_eq!(detection.port, 4000);
+        assert_eq!(detection.port_source, "the start script");
+    }
+
+    #[test]
+    fn empty_directory() {
+        let project = Fixture::new("empty", &[]);
+        let err = detect_in(&project.dir).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+}
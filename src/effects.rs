@@ -0,0 +1,563 @@
+use std::{
+    fs, io,
+    io::Write,
+    path::Path,
+    process::{Command, ExitStatus, Output, Stdio},
+    sync::atomic::{AtomicBool, Ordering},
+};
+#[cfg(test)]
+use std::sync::Mutex;
+
+/// Every filesystem write and every command that may change something goes
+/// through here, so `--dry-run` can describe it instead of doing it.
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+const DIFF_CONTEXT: usize = 3;
+/// Above this many line pairs the diff is summarized instead of computed.
+const DIFF_LIMIT: usize = 4_000_000;
+
+/// Dry-run is process-wide: tests that flip it and tests that write through
+/// here hold this so they never overlap.
+#[cfg(test)]
+pub static TEST_LOCK: Mutex<()> = Mutex::new(());
+
+pub fn set_dry_run(enabled: bool) {
+    DRY_RUN.store(enabled, Ordering::SeqCst);
+}
+
+pub fn dry_run() -> bool {
+    DRY_RUN.load(Ordering::SeqCst)
+}
+
+fn announce(action: &str) {
+    println!("🔸 [dry-run] would {}", action);
+}
+
+fn describe(command: &Command) -> String {
+    let mut words = vec![command.get_program().to_string_lossy().into_owned()];
+    for arg in command.get_args() {
+        let arg = arg.to_string_lossy();
+        if arg.is_empty() || arg.contains(char::is_whitespace) {
+            words.push(format!("'{}'", arg));
+        } else {
+            words.push(arg.into_owned());
+        }
+    }
+    words.join(" ")
+}
+
+/// First argument that is neither a flag nor the value of a global flag
+/// such as `--context`.
+fn verb_position(args: &[String]) -> Option<usize> {
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--context" | "--kubeconfig" | "--namespace" | "-n" | "--host" | "-H" => i += 2,
+            arg if arg.starts_with('-') => i += 1,
+            _ => return Some(i),
+        }
+    }
+    None
+}
+
+/// Whether `command` only reads state. Anything not recognized here counts
+/// as a side effect, so new commands are safe by default.
+fn is_read_only(command: &Command) -> bool {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let program = Path::new(&program)
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or(program);
+    let args: Vec<String> = command
+        .get_args()
+        .map(|a| a.to_string_lossy().into_owned())
+        .collect();
+    let verb = verb_position(&args);
+    let word = |offset: usize| {
+        verb.and_then(|i| args.get(i + offset))
+            .map(String::as_str)
+            .unwrap_or("")
+    };
+    if word(0) == "version" || args.iter().any(|a| a == "--version") {
+        return true;
+    }
+    // Credential helpers only look credentials up
+    if program.starts_with("docker-credential-") {
+        return word(0) == "get";
+    }
+
+    match program.as_str() {
+        "kubectl" => {
+            if args.iter().any(|a| a.starts_with("--dry-run=") && a != "--dry-run=none") {
+                return true;
+            }
+            match word(0) {
+                "get" | "describe" | "diff" | "top" | "logs" | "explain" | "api-resources"
+                | "api-versions" | "cluster-info" | "kustomize" | "wait" | "auth" => true,
+                "rollout" => matches!(word(1), "status" | "history"),
+                "config" => matches!(word(1), "current-context" | "get-contexts" | "view"),
+                _ => false,
+            }
+        }
+        "docker" => match word(0) {
+            "info" | "inspect" | "ps" | "images" | "logs" | "stats" => true,
+            "image" | "container" | "network" | "volume" | "context" => {
+                matches!(word(1), "inspect" | "ls")
+            }
+            "system" => word(1) == "df",
+            "buildx" => word(1) == "ls",
+            _ => false,
+        },
+        "docker-compose" => matches!(word(0), "ps" | "config" | "logs"),
+        "helm" => matches!(word(0), "lint" | "template" | "list" | "status" | "get"),
+        "kind" => word(0) == "get",
+        "k3d" => matches!(word(0), "cluster" | "node") && word(1) == "list",
+        "minikube" => matches!(word(0), "status" | "ip") || word(1) == "list",
+        "curl" => curl_is_read_only(&args),
+        "shasum" | "which" | "where" | "uname" => true,
+        _ => false,
+    }
+}
+
+/// A curl call is a plain GET when every flag is one known to neither send
+/// a body, upload, pick a method nor write a file. Anything else, including
+/// flags not listed here, counts as a side effect.
+fn curl_is_read_only(args: &[String]) -> bool {
+    let mut i = 0;
+    while i < args.len() {
+        let arg = args[i].as_str();
+        match arg {
+            // Flags taking a value
+            "-H" | "--header" | "-w" | "--write-out" | "-m" | "--max-time" | "--connect-timeout"
+            | "--retry" | "-A" | "--user-agent" | "--resolve" => i += 1,
+            "-o" | "--output" if args.get(i + 1).map(String::as_str) == Some("/dev/null") => i += 1,
+            "--silent" | "--show-error" | "--fail" | "--location" | "--insecure" | "--head"
+            | "--include" | "--verbose" | "--compressed" => {}
+            // Bundled short flags such as `-fsSL`
+            _ if arg.starts_with('-')
+                && !arg.starts_with("--")
+                && arg[1..].chars().all(|c| "sSfLkIiv".contains(c)) => {}
+            _ if arg.starts_with('-') => return false,
+            _ => {}
+        }
+        i += 1;
+    }
+    true
+}
+
+fn skipped() -> Output {
+    Output {
+        status: success_status(),
+        stdout: Vec::new(),
+        stderr: Vec::new(),
+    }
+}
+
+#[cfg(unix)]
+fn success_status() -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    ExitStatus::from_raw(0)
+}
+
+#[cfg(windows)]
+fn success_status() -> ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+    ExitStatus::from_raw(0)
+}
+
+/// Process execution through the gateway. Commands that only read state
+/// always run; the rest are printed and report success in dry-run mode.
+pub trait Effect {
+    fn run(&mut self) -> io::Result<Output>;
+    fn run_status(&mut self) -> io::Result<ExitStatus>;
+    /// Runs with `input` on stdin, e.g. manifests holding secrets.
+    fn run_with_input(&mut self, input: &[u8]) -> io::Result<Output>;
+}
+
+impl Effect for Command {
+    fn run(&mut self) -> io::Result<Output> {
+        if dry_run() && !is_read_only(self) {
+            announce(&format!("run: {}", describe(self)));
+            return Ok(skipped());
+        }
+        self.output()
+    }
+
+    fn run_status(&mut self) -> io::Result<ExitStatus> {
+        if dry_run() && !is_read_only(self) {
+            announce(&format!("run: {}", describe(self)));
+            return Ok(success_status());
+        }
+        self.status()
+    }
+
+    fn run_with_input(&mut self, input: &[u8]) -> io::Result<Output> {
+        if dry_run() && !is_read_only(self) {
+            // The input is not shown; it usually carries secrets
+            announce(&format!("run: {} ({} bytes on stdin)", describe(self), input.len()));
+            return Ok(skipped());
+        }
+        let mut child = self
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(input)?;
+        }
+        child.wait_with_output()
+    }
+}
+
+/// Writes `contents` to `path`; in dry-run mode prints a unified diff
+/// against the current file instead.
+pub fn write<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> io::Result<()> {
+    let path = path.as_ref();
+    let contents = contents.as_ref();
+    if !dry_run() {
+        return fs::write(path, contents);
+    }
+
+    let old = fs::read(path).ok();
+    if old.as_deref() == Some(contents) {
+        return Ok(());
+    }
+    announce(&format!(
+        "{} {}",
+        if old.is_some() { "overwrite" } else { "create" },
+        path.display()
+    ));
+    let old = String::from_utf8_lossy(old.as_deref().unwrap_or_default()).into_owned();
+    let new = String::from_utf8_lossy(contents);
+    for line in unified_diff(&path.display().to_string(), &old, &new) {
+        println!("    {}", line);
+    }
+    Ok(())
+}
+
+/// Like `write`, but the contents go to a temporary file next to `path`
+/// that is then renamed over it, so an interrupted run never leaves a
+/// half-written file behind.
+pub fn write_atomic<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> io::Result<()> {
+    let path = path.as_ref();
+    if dry_run() {
+        return write(path, contents);
+    }
+
+    let name = path.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Not a file path: {}", path.display()),
+        )
+    })?;
+    let temp = path.with_file_name(format!(".{}.rustify-tmp", name.to_string_lossy()));
+    let written = (|| {
+        let mut file = fs::File::create(&temp)?;
+        file.write_all(contents.as_ref())?;
+        file.sync_all()?;
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(&temp, metadata.permissions())?;
+        }
+        fs::rename(&temp, path)
+    })();
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written
+}
+
+/// Like `write`, but for private keys and credentials: the file is created
+/// readable by the owner only and its contents are never printed.
+pub fn write_private<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> io::Result<()> {
+    let path = path.as_ref();
+    if dry_run() {
+        announce(&format!("write {} (mode 0600, contents hidden)", path.display()));
+        return Ok(());
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?;
+        // `mode` only applies to new files
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        file.write_all(contents.as_ref())
+    }
+    #[cfg(not(unix))]
+    {
+        fs::write(path, contents)
+    }
+}
+
+pub fn create_dir_all<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    let path = path.as_ref();
+    if dry_run() {
+        if !path.is_dir() {
+            announce(&format!("create directory {}", path.display()));
+        }
+        return Ok(());
+    }
+    fs::create_dir_all(path)
+}
+
+pub fn remove_file<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    let path = path.as_ref();
+    if dry_run() {
+        announce(&format!("remove {}", path.display()));
+        return Ok(());
+    }
+    fs::remove_file(path)
+}
+
+pub fn remove_dir_all<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    let path = path.as_ref();
+    if dry_run() {
+        announce(&format!("remove directory {}", path.display()));
+        return Ok(());
+    }
+    fs::remove_dir_all(path)
+}
+
+pub fn set_permissions<P: AsRef<Path>>(path: P, permissions: fs::Permissions) -> io::Result<()> {
+    let path = path.as_ref();
+    if dry_run() {
+        announce(&format!("change permissions of {}", path.display()));
+        return Ok(());
+    }
+    fs::set_permissions(path, permissions)
+}
+
+pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> io::Result<()> {
+    let (from, to) = (from.as_ref(), to.as_ref());
+    if dry_run() {
+        announce(&format!("move {} to {}", from.display(), to.display()));
+        return Ok(());
+    }
+    fs::rename(from, to)
+}
+
+pub fn copy<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> io::Result<()> {
+    let (from, to) = (from.as_ref(), to.as_ref());
+    if dry_run() {
+        announce(&format!("copy {} to {}", from.display(), to.display()));
+        return Ok(());
+    }
+    fs::copy(from, to).map(|_| ())
+}
+
+/// `diff -u` style hunks between `old` and `new`.
+pub fn unified_diff(name: &str, old: &str, new: &str) -> Vec<String> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+    if a.len().saturating_mul(b.len()) > DIFF_LIMIT {
+        return vec![format!("({} lines → {} lines, too large to diff)", a.len(), b.len())];
+    }
+
+    // Longest common subsequence table, filled from the end
+    let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    // (tag, old line index, new line index, text)
+    let mut ops = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            ops.push((' ', i, j, a[i]));
+            i += 1;
+            j += 1;
+        } else if j < b.len() && (i == a.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            ops.push(('+', i, j, b[j]));
+            j += 1;
+        } else {
+            ops.push(('-', i, j, a[i]));
+            i += 1;
+        }
+    }
+
+    let changed: Vec<usize> = (0..ops.len()).filter(|&k| ops[k].0 != ' ').collect();
+    if changed.is_empty() {
+        return Vec::new();
+    }
+
+    // Group changes whose context windows touch into hunks
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &k in &changed {
+        let start = k.saturating_sub(DIFF_CONTEXT);
+        let end = (k + DIFF_CONTEXT + 1).min(ops.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    let mut lines = vec![format!("--- {}", name), format!("+++ {}", name)];
+    for (start, end) in ranges {
+        let hunk = &ops[start..end];
+        let old_len = hunk.iter().filter(|op| op.0 != '+').count();
+        let new_len = hunk.iter().filter(|op| op.0 != '-').count();
+        let old_start = if old_len == 0 { hunk[0].1 } else { hunk[0].1 + 1 };
+        let new_start = if new_len == 0 { hunk[0].2 } else { hunk[0].2 + 1 };
+        lines.push(format!(
+            "@@ -{},{} +{},{} @@",
+            old_start, old_len, new_start, new_len
+        ));
+        lines.extend(hunk.iter().map(|(tag, _, _, text)| format!("{}{}", tag, text)));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{path::PathBuf, sync::MutexGuard};
+
+    struct DryRun<'a> {
+        dir: PathBuf,
+        _guard: MutexGuard<'a, ()>,
+    }
+
+    impl DryRun<'_> {
+        /// A scratch directory holding `existing.txt`, with dry-run enabled
+        /// until the value is dropped.
+        fn new(name: &str) -> Self {
+            let guard = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+            let dir = std::env::temp_dir().join(format!("rustify-effects-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("existing.txt"), "before\n").unwrap();
+            set_dry_run(true);
+            DryRun { dir, _guard: guard }
+        }
+
+        fn path(&self, name: &str) -> PathBuf {
+            self.dir.join(name)
+        }
+
+        /// Names and contents of everything in the directory.
+        fn snapshot(&self) -> Vec<(String, Option<String>)> {
+            let mut entries: Vec<_> = fs::read_dir(&self.dir)
+                .unwrap()
+                .flatten()
+                .map(|e| {
+                    (
+                        e.file_name().to_string_lossy().into_owned(),
+                        fs::read_to_string(e.path()).ok(),
+                    )
+                })
+                .collect();
+            entries.sort();
+            entries
+        }
+
+        fn untouched(&self) -> Vec<(String, Option<String>)> {
+            vec![("existing.txt".to_string(), Some("before\n".to_string()))]
+        }
+    }
+
+    impl Drop for DryRun<'_> {
+        fn drop(&mut self) {
+            set_dry_run(false);
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn writes_are_skipped() {
+        let t = DryRun::new("write");
+        write(t.path("new.txt"), "new\n").unwrap();
+        write(t.path("existing.txt"), "after\n").unwrap();
+        write_atomic(t.path("existing.txt"), "after\n").unwrap();
+        write_private(t.path("secret.json"), "{}").unwrap();
+        write_private(t.path("existing.txt"), "after\n").unwrap();
+        assert_eq!(t.snapshot(), t.untouched());
+    }
+
+    #[test]
+    fn removals_and_moves_are_skipped() {
+        let t = DryRun::new("remove");
+        fs::create_dir(t.path("sub")).unwrap();
+        remove_file(t.path("existing.txt")).unwrap();
+        remove_dir_all(t.path("sub")).unwrap();
+        create_dir_all(t.path("created/deep")).unwrap();
+        rename(t.path("existing.txt"), t.path("renamed.txt")).unwrap();
+        copy(t.path("existing.txt"), t.path("copied.txt")).unwrap();
+        let mut expected = t.untouched();
+        expected.push(("sub".to_string(), None));
+        assert_eq!(t.snapshot(), expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn commands_are_skipped() {
+        let t = DryRun::new("run");
+        let target = t.path("touched.txt");
+        let output = Command::new("touch").arg(&target).run().unwrap();
+        assert!(output.status.success());
+        assert!(Command::new("touch").arg(&target).run_status().unwrap().success());
+        let script = format!("cat > '{}'", target.display());
+        Command::new("sh")
+            .args(["-c", &script])
+            .run_with_input(b"input")
+            .unwrap();
+        assert_eq!(t.snapshot(), t.untouched());
+    }
+
+    #[test]
+    fn classifies_read_only_commands() {
+        let mut get = Command::new("kubectl");
+        get.args(["--context", "prod", "get", "pods"]);
+        assert!(is_read_only(&get));
+        let mut apply = Command::new("kubectl");
+        apply.args(["apply", "-f", "-"]);
+        assert!(!is_read_only(&apply));
+        let mut client_dry_run = Command::new("kubectl");
+        client_dry_run.args(["apply", "--dry-run=client", "-f", "-"]);
+        assert!(is_read_only(&client_dry_run));
+
+        for args in [
+            &["-fsSL", "-H", "Accept: application/json", "https://example.com"][..],
+            &["-s", "-o", "/dev/null", "-w", "%{http_code}", "--max-time", "2", "https://example.com"],
+            &["--head", "https://example.com"],
+            &["-sk", "-o", "/dev/null", "--resolve", "shop.test:443:127.0.0.1", "https://shop.test"],
+        ] {
+            let mut curl = Command::new("curl");
+            curl.args(args);
+            assert!(is_read_only(&curl), "{:?}", args);
+        }
+        for args in [
+            &["-o", "file", "https://example.com"][..],
+            &["-O", "https://example.com/file"],
+            &["-sSo", "file", "https://example.com"],
+            &["--output", "file", "https://example.com"],
+            &["-X", "DELETE", "https://example.com"],
+            &["--request", "POST", "https://example.com"],
+            &["-d", "a=1", "https://example.com"],
+            &["--data", "a=1", "https://example.com"],
+            &["--data-binary", "@file", "https://example.com"],
+            &["--data-raw", "a=1", "https://example.com"],
+            &["--data-urlencode", "a=1", "https://example.com"],
+            &["-F", "file=@x", "https://example.com"],
+            &["--form", "file=@x", "https://example.com"],
+            &["-T", "file", "https://example.com"],
+            &["--upload-file", "file", "https://example.com"],
+            &["--json", "{}", "https://example.com"],
+        ] {
+            let mut curl = Command::new("curl");
+            curl.args(args);
+            assert!(!is_read_only(&curl), "{:?}", args);
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use openssl::{base64, sha::Sha256};
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+/// Prefixes of variables that frameworks inline into the client bundle, so
+/// they have to be set while the image builds.
+pub const PUBLIC_PREFIXES: &[&str] = &[
+    "NEXT_PUBLIC_",
+    "NUXT_PUBLIC_",
+    "VITE_",
+    "REACT_APP_",
+    "VUE_APP_",
+    "PUBLIC_",
+];
+
+pub fn is_public(key: &str) -> bool {
+    PUBLIC_PREFIXES.iter().any(|prefix| key.starts_with(prefix))
+}
+
+/// Variables collected from the project's `.env*` files, split by whether
+/// they must be delivered as a Kubernetes Secret.
+#[derive(Debug, Clone, Default)]
+pub struct ProjectEnv {
+    /// Files that were found, in the order they were applied.
+    pub files: Vec<String>,
+    pub plain: BTreeMap<String, String>,
+    pub secret: BTreeMap<String, String>,
+    /// SHA-256 over the layered files; changes whenever any of them does.
+    pub hash: String,
+}
+
+impl ProjectEnv {
+    /// Every variable, secret or not, for delivery as container env.
+    pub fn all(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.plain.iter().chain(self.secret.iter())
+    }
+
+    /// Public variables for `docker build`. Secrets stay out of the image
+    /// even with a public prefix.
+    pub fn build_args(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.plain.iter().filter(|(key, _)| is_public(key))
+    }
+
+    /// Adds decrypted store secrets, which win over `.env` values. `source`
+    /// is the encrypted store content, folded into the hash so a rotated
+    /// secret rolls the pods too.
+    pub fn add_secrets(&mut self, secrets: BTreeMap<String, String>, source: &[u8]) {
+        for (key, value) in secrets {
+            self.plain.remove(&key);
+            self.secret.insert(key, value);
+        }
+
+        let mut hasher = Sha256::new();
+        hasher.update(self.hash.as_bytes());
+        hasher.update(&[0]);
+        hasher.update(source);
+        self.hash = hex(&hasher.finish());
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Layer order for `environment`; later files override earlier ones.
+pub fn layer_files(environment: &str) -> Vec<String> {
+    vec![
+        ".env".to_string(),
+        ".env.local".to_string(),
+        format!(".env.{}", environment),
+        format!(".env.{}.local", environment),
+    ]
+}
+
+pub fn load(environment: &str, secret_patterns: &[String]) -> io::Result<ProjectEnv> {
+    load_from(Path::new("."), environment, secret_patterns)
+}
+
+fn load_from(dir: &Path, environment: &str, secret_patterns: &[String]) -> io::Result<ProjectEnv> {
+    let mut env = ProjectEnv::default();
+    let mut merged = BTreeMap::new();
+    let mut hasher = Sha256::new();
+
+    for file in layer_files(environment) {
+        let path = dir.join(&file);
+        if !path.exists() {
+            continue;
+        }
+        let content = fs::read_to_string(&path)?;
+        hasher.update(file.as_bytes());
+        hasher.update(&[0]);
+        hasher.update(content.as_bytes());
+        hasher.update(&[0]);
+
+        for (key, value) in parse(&content) {
+            merged.insert(key, value);
+        }
+        env.files.push(file);
+    }
+
+    for (key, value) in merged {
+        if secret_patterns
+            .iter()
+            .any(|pattern| super::matches_wildcard(pattern, &key))
+        {
+            env.secret.insert(key, value);
+        } else {
+            env.plain.insert(key, value);
+        }
+    }
+
+    env.hash = hex(&hasher.finish());
+    Ok(env)
+}
+
+/// Parses dotenv syntax: `KEY=value`, optional `export`, `#` comments and
+/// single/double quoted values (double quotes understand `\n`, `\r`, `\t`,
+/// `\"` and `\\` escapes).
+pub fn parse(content: &str) -> Vec<(String, String)> {
+    let mut vars = Vec::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+
+        let (key, raw) = match line.split_once('=') {
+            Some((key, raw)) => (key.trim(), raw.trim()),
+            None => continue,
+        };
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            continue;
+        }
+
+        let value = if let Some(inner) = raw.strip_prefix('"').and_then(|r| r.strip_suffix('"')) {
+            unescape(inner)
+        } else if let Some(inner) = raw.strip_prefix('\'').and_then(|r| r.strip_suffix('\'')) {
+            inner.to_string()
+        } else {
+            // Unquoted values end at an inline comment
+            match raw.find(" #") {
+                Some(idx) => raw[..idx].trim_end().to_string(),
+                None => raw.to_string(),
+            }
+        };
+
+        vars.push((key.to_string(), value));
+    }
+
+    vars
+}
+
+/// Resolves the escapes of a double-quoted value in one left-to-right pass,
+/// so `\\n` stays a backslash followed by `n`. Unknown escapes are kept.
+fn unescape(inner: &str) -> String {
+    let mut value = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('r') => value.push('\r'),
+            Some('t') => value.push('\t'),
+            Some(escaped @ ('"' | '\\')) => value.push(escaped),
+            Some(other) => {
+                value.push('\\');
+                value.push(other);
+            }
+            None => value.push('\\'),
+        }
+    }
+    value
+}
+
+pub fn config_map_manifest(name: &str, namespace: &str, values: &BTreeMap<String, String>) -> String {
+    let mut manifest = format!(
+        r#"apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: {name}
+  namespace: {namespace}
+  labels:
+    app.kubernetes.io/managed-by: rustify
+data:
+"#
+    );
+    for (key, value) in values {
+        // A JSON string is a valid double-quoted YAML scalar
+        manifest.push_str(&format!(
+            "  {}: {}\n",
+            key,
+            serde_json::Value::String(value.clone())
+        ));
+    }
+    manifest
+}
+
+pub fn secret_manifest(name: &str, namespace: &str, values: &BTreeMap<String, String>) -> String {
+    let mut manifest = format!(
+        r#"apiVersion: v1
+kind: Secret
+metadata:
+  name: {name}
+  namespace: {namespace}
+  labels:
+    app.kubernetes.io/managed-by: rustify
+type: Opaque
+data:
+"#
+    );
+    for (key, value) in values {
+        manifest.push_str(&format!(
+            "  {}: {}\n",
+            key,
+            base64::encode_block(value.as_bytes())
+        ));
+    }
+    manifest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// `.env*` files in a scratch directory, removed on drop.
+    struct EnvDir {
+        dir: PathBuf,
+    }
+
+    impl EnvDir {
+        fn new(name: &str, files: &[(&str, &str)]) -> Self {
+            let dir = std::env::temp_dir().join(format!("rustify-env-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            for (file, contents) in files {
+                fs::write(dir.join(file), contents).unwrap();
+            }
+            EnvDir { dir }
+        }
+
+        fn load(&self, environment: &str, secret_patterns: &[&str]) -> ProjectEnv {
+            let patterns: Vec<String> = secret_patterns.iter().map(|p| p.to_string()).collect();
+            load_from(&self.dir, environment, &patterns).unwrap()
+        }
+    }
+
+    impl Drop for EnvDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn value<'a>(vars: &'a [(String, String)], key: &str) -> &'a str {
+        vars.iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+            .unwrap_or_else(|| panic!("{} not parsed", key))
+    }
+
+    #[test]
+    fn later_layers_override_earlier_ones() {
+        let dir = EnvDir::new(
+            "layers",
+            &[
+                (".env", "A=env\nB=env\nC=env\nD=env\n"),
+                (".env.local", "B=local\nC=local\nD=local\n"),
+                (".env.prod", "C=prod\nD=prod\n"),
+                (".env.prod.local", "D=prod-local\n"),
+                (".env.dev", "A=dev\n"),
+            ],
+        );
+        let env = dir.load("prod", &[]);
+        assert_eq!(env.files, [".env", ".env.local", ".env.prod", ".env.prod.local"]);
+        let values: Vec<(&str, &str)> = env
+            .plain
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        assert_eq!(
+            values,
+            [("A", "env"), ("B", "local"), ("C", "prod"), ("D", "prod-local")]
+        );
+    }
+
+    #[test]
+    fn secret_patterns_split_the_variables() {
+        let dir = EnvDir::new("secrets", &[(".env", "DATABASE_URL=postgres://\nAPI_KEY=k\nPORT=3000\n")]);
+        let env = dir.load("dev", &["*_KEY", "DATABASE_*"]);
+        assert_eq!(env.plain.keys().collect::<Vec<_>>(), ["PORT"]);
+        assert_eq!(env.secret.keys().collect::<Vec<_>>(), ["API_KEY", "DATABASE_URL"]);
+    }
+
+    #[test]
+    fn hash_follows_the_file_contents() {
+        let first = EnvDir::new("hash-a", &[(".env", "A=1\n")]).load("dev", &[]).hash;
+        let same = EnvDir::new("hash-b", &[(".env", "A=1\n")]).load("dev", &[]).hash;
+        let changed = EnvDir::new("hash-c", &[(".env", "A=2\n")]).load("dev", &[]).hash;
+        assert_eq!(first, same);
+        assert_ne!(first, changed);
+        assert_eq!(EnvDir::new("hash-d", &[]).load("dev", &[]).files, Vec::<String>::new());
+    }
+
+    #[test]
+    fn parses_export_and_comments() {
+        let vars = parse(
+            "# leading comment\n\n  export NAME=shop\nURL=http://x/#anchor # trailing\nSPACED = padded \nnot a line\n=missing key\nBAD KEY=1\n",
+        );
+        assert_eq!(value(&vars, "NAME"), "shop");
+        assert_eq!(value(&vars, "URL"), "http://x/#anchor");
+        assert_eq!(value(&vars, "SPACED"), "padded");
+        assert_eq!(vars.len(), 3, "{:?}", vars);
+    }
+
+    #[test]
+    fn parses_quoted_values() {
+        let vars = parse(concat!(
+            "SINGLE='keep \\n and # as is'\n",
+            "DOUBLE=\"line one\\nline two\"\n",
+            "QUOTE=\"say \\\"hi\\\"\"\n",
+            "PATH_WIN=\"C:\\\\new\"\n",
+            "TAB=\"a\\tb\"\n",
+            "UNKNOWN=\"\\d+\"\n",
+            "HASH=\"a # b\"\n",
+        ));
+        assert_eq!(value(&vars, "SINGLE"), "keep \\n and # as is");
+        assert_eq!(value(&vars, "DOUBLE"), "line one\nline two");
+        assert_eq!(value(&vars, "QUOTE"), "say \"hi\"");
+        assert_eq!(value(&vars, "PATH_WIN"), "C:\\new");
+        assert_eq!(value(&vars, "TAB"), "a\tb");
+        assert_eq!(value(&vars, "UNKNOWN"), "\\d+");
+        assert_eq!(value(&vars, "HASH"), "a # b");
+    }
+
+    #[test]
+    fn build_args_are_public_plain_variables() {
+        let mut env = ProjectEnv::default();
+        for (key, value) in parse("NEXT_PUBLIC_API=https://api\nVITE_MODE=demo\nDATABASE_HOST=db\n") {
+            env.plain.insert(key, value);
+        }
+        env.secret.insert("VITE_TOKEN".to_string(), "abc".to_string());
+
+        let names: Vec<&str> = env.build_args().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(names, ["NEXT_PUBLIC_API", "VITE_MODE"]);
+    }
+}
@@ -0,0 +1,818 @@
+use super::{
+    autoscale,
+    effects::{self, Effect},
+    AvailabilityPlan, ClusterImage, EnvSources, IngressConfig, ResourceAmounts, RustifyConfig,
+    Workload,
+};
+use serde_json::Value;
+use std::{
+    io,
+    path::Path,
+    process::Command,
+};
+
+/// The app being exported and its `rustify.json`.
+pub struct ExportContext<'a> {
+    pub app_name: &'a str,
+    pub port: &'a str,
+    pub config: &'a RustifyConfig,
+    /// The image serves a static build and has no Node runtime.
+    pub static_files: bool,
+}
+
+impl ExportContext<'_> {
+    fn namespace(&self) -> String {
+        self.config
+            .kubernetes
+            .namespace
+            .clone()
+            .unwrap_or_else(super::default_namespace)
+    }
+
+    fn replicas(&self) -> i32 {
+        self.config.scaling.min_instances.max(1) as i32
+    }
+
+    fn availability(&self, mode: &str) -> io::Result<AvailabilityPlan> {
+        self.config
+            .kubernetes
+            .availability
+            .resolve(mode, self.replicas())
+    }
+
+    fn cluster_image(&self) -> ClusterImage {
+        let (repository, tag, pull_policy) = self.image();
+        ClusterImage {
+            reference: format!("{}:{}", repository, tag),
+            pull_policy,
+            pull_secret: self
+                .config
+                .registry
+                .as_ref()
+                .map(|_| format!("{}-registry-credentials", self.app_name)),
+        }
+    }
+
+    fn image(&self) -> (String, String, &'static str) {
+        match &self.config.registry {
+            Some(registry) => (
+                format!("{}/{}", registry.url.trim_end_matches('/'), self.app_name),
+                registry.tag.clone(),
+                "IfNotPresent",
+            ),
+            None => (self.app_name.to_string(), "latest".to_string(), "IfNotPresent"),
+        }
+    }
+
+    fn resources(&self, mode: &str) -> (ResourceAmounts, ResourceAmounts) {
+        super::container_resources(mode, self.static_files)
+    }
+}
+
+/// Exported files, keyed by their path in the output directory.
+type Files = Vec<(String, String)>;
+
+/// Helm chart names must be lowercase alphanumerics and dashes.
+fn chart_name(app_name: &str) -> String {
+    let name: String = app_name
+        .rsplit('/')
+        .next()
+        .unwrap_or(app_name)
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let name = name.trim_matches('-');
+    if name.is_empty() {
+        "app".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// YAML double-quoted scalar.
+fn quote(value: &str) -> String {
+    Value::String(value.to_string()).to_string()
+}
+
+fn write_files(dir: &Path, files: &Files) -> io::Result<()> {
+    for (file, content) in files {
+        let path = dir.join(file);
+        if let Some(parent) = path.parent() {
+            effects::create_dir_all(parent)?;
+        }
+        effects::write(path, content)?;
+    }
+    Ok(())
+}
+
+/// Helm expressions handed to the shared renderers in place of the app
+/// name, namespace and port. The template name is backquoted so it
+/// survives the JSON quoting of KEDA queries.
+const APP: &str = "{{ include `rustify.name` . }}";
+const NAMESPACE: &str = "{{ .Release.Namespace }}";
+const PORT: &str = "{{ .Values.port }}";
+
+const HELPERS_TPL: &str = r#"{{- define "rustify.name" -}}
+{{- default .Chart.Name .Values.nameOverride | trunc 63 | trimSuffix "-" -}}
+{{- end -}}
+"#;
+
+/// Rendered with the pull secret and envFrom sources set to `{{ . }}`, the
+/// Deployment contains these blocks; the chart swaps in the `with` blocks
+/// that read them from the values.
+const PULL_SECRETS_RENDERED: &str = r#"
+      imagePullSecrets:
+      - name: {{ . }}"#;
+const PULL_SECRETS_TPL: &str = r#"
+      {{- with .Values.imagePullSecrets }}
+      imagePullSecrets:
+      {{- range . }}
+      - name: {{ . }}
+      {{- end }}
+      {{- end }}"#;
+const ENV_FROM_RENDERED: &str = r#"
+        envFrom:
+        - configMapRef:
+            name: {{ . }}
+        - secretRef:
+            name: {{ . }}"#;
+const ENV_FROM_TPL: &str = r#"
+        {{- if or .Values.envFrom.configMap .Values.envFrom.secret }}
+        envFrom:
+        {{- with .Values.envFrom.configMap }}
+        - configMapRef:
+            name: {{ . }}
+        {{- end }}
+        {{- with .Values.envFrom.secret }}
+        - secretRef:
+            name: {{ . }}
+        {{- end }}
+        {{- end }}"#;
+
+pub fn write_helm_chart(dir: &Path, ctx: &ExportContext, mode: &str) -> io::Result<()> {
+    write_files(dir, &helm_chart(ctx, mode)?)
+}
+
+/// The chart's templates are the manifests `rustify deploy` applies in
+/// `mode`, rendered by the same functions, with values.yaml settings
+/// swapped in.
+fn helm_chart(ctx: &ExportContext, mode: &str) -> io::Result<Files> {
+    let name = chart_name(ctx.app_name);
+    let (_, tag, _) = ctx.image();
+    let plan = ctx.availability(mode)?;
+
+    let mut files: Files = vec![
+        (
+            "Chart.yaml".to_string(),
+            format!(
+                r#"apiVersion: v2
+name: {name}
+description: Kubernetes resources for {app}, exported by rustify
+type: application
+version: 0.1.0
+appVersion: {tag}
+"#,
+                app = ctx.app_name,
+                tag = quote(&tag),
+            ),
+        ),
+        ("values.yaml".to_string(), helm_values(ctx, mode, &plan)),
+        ("templates/_helpers.tpl".to_string(), HELPERS_TPL.to_string()),
+    ];
+    let mut template = |file: &str, manifest: String| {
+        files.push((format!("templates/{}", file), format!("{}\n", manifest.trim_end())));
+    };
+
+    let image = ClusterImage {
+        reference: r#""{{ .Values.image.repository }}:{{ .Values.image.tag }}""#.to_string(),
+        pull_policy: "{{ .Values.image.pullPolicy }}",
+        pull_secret: Some("{{ . }}".to_string()),
+    };
+    let env_sources = EnvSources {
+        config_map: Some("{{ . }}".to_string()),
+        secret: Some("{{ . }}".to_string()),
+        hash: String::new(),
+    };
+    let workload = super::render_kubernetes_manifests(&Workload {
+        app_name: APP,
+        port: PORT,
+        replicas: ctx.replicas(),
+        namespace: NAMESPACE,
+        mode,
+        image: &image,
+        env_sources: &env_sources,
+        availability: &plan,
+        static_files: ctx.static_files,
+        health_path: "{{ .Values.healthPath }}",
+    });
+    for (file, manifest) in workload {
+        let manifest = match file {
+            "k8s-deployment.yaml" => deployment_template(ctx, mode, &plan, manifest)?,
+            "k8s-pdb.yaml" => toggled("podDisruptionBudget", &manifest),
+            _ => manifest,
+        };
+        template(file.trim_start_matches("k8s-"), manifest);
+    }
+
+    template(
+        "ingress.yaml",
+        toggled(
+            "ingress",
+            &super::render_ingress(APP, NAMESPACE, PORT, &ctx.config.ingress),
+        ),
+    );
+    if mode == "prod" {
+        autoscale::validate(&ctx.config.scaling)?;
+    }
+    template(
+        "autoscaler.yaml",
+        toggled(
+            "autoscaling",
+            &autoscale::manifest(APP, NAMESPACE, &ctx.config.scaling),
+        ),
+    );
+    template(
+        "network-policy.yaml",
+        toggled(
+            "networkPolicy",
+            &super::render_network_policy(
+                APP,
+                NAMESPACE,
+                PORT,
+                "{{ .Values.networkPolicy.ingressNamespace }}",
+                ctx.config.kubernetes.egress.as_deref(),
+            ),
+        ),
+    );
+    template(
+        "quota.yaml",
+        toggled(
+            "quota",
+            &super::render_quota(NAMESPACE, mode, ctx.config, ctx.static_files),
+        ),
+    );
+    template(
+        "nginx-config.yaml",
+        toggled("nginx", &super::render_nginx_config(NAMESPACE)),
+    );
+    Ok(files)
+}
+
+/// Swaps the replica count, resources, NODE_ENV, pull secrets and envFrom
+/// of the rendered Deployment for the values.
+fn deployment_template(
+    ctx: &ExportContext,
+    mode: &str,
+    plan: &AvailabilityPlan,
+    deployment: String,
+) -> io::Result<String> {
+    let (requests, limits) = ctx.resources(mode);
+    let mut swaps = vec![
+        (
+            format!("\n  replicas: {}\n", ctx.replicas()),
+            "\n  replicas: {{ .Values.replicaCount }}\n".to_string(),
+        ),
+        (
+            super::render_resources(&requests, &limits),
+            "\n        resources:\n          {{- toYaml .Values.resources | nindent 10 }}".to_string(),
+        ),
+        (PULL_SECRETS_RENDERED.to_string(), PULL_SECRETS_TPL.to_string()),
+        (ENV_FROM_RENDERED.to_string(), ENV_FROM_TPL.to_string()),
+    ];
+    if plan.pre_stop_sleep_seconds > 0 {
+        // The cluster is only known at install time
+        let seconds = plan.pre_stop_sleep_seconds;
+        swaps.push((
+            super::render_pre_stop(seconds, true),
+            format!(
+                "\n{{{{- if semverCompare \">=1.30-0\" .Capabilities.KubeVersion.Version }}}}{}\n{{{{- else }}}}{}\n{{{{- end }}}}",
+                super::render_pre_stop(seconds, true),
+                super::render_pre_stop(seconds, false)
+            ),
+        ));
+    }
+    if !ctx.static_files {
+        swaps.push((
+            format!("- name: NODE_ENV\n          value: \"{}\"", mode),
+            "- name: NODE_ENV\n          value: {{ .Values.nodeEnv | quote }}".to_string(),
+        ));
+    }
+
+    let mut deployment = deployment;
+    for (from, to) in swaps {
+        // A renderer change that moves one of these must fail the export,
+        // not silently drop the value
+        if deployment.matches(&from).count() != 1 {
+            return Err(io::Error::other(format!(
+                "Cannot template the Deployment: {:?} is not in it exactly once",
+                from
+            )));
+        }
+        deployment = deployment.replacen(&from, &to, 1);
+    }
+    Ok(deployment)
+}
+
+/// `manifest`, rendered only while `.Values.<key>.enabled` is set.
+fn toggled(key: &str, manifest: &str) -> String {
+    format!(
+        "{{{{- if .Values.{}.enabled }}}}\n{}\n{{{{- end }}}}",
+        key,
+        manifest.trim_end()
+    )
+}
+
+fn helm_values(ctx: &ExportContext, mode: &str, plan: &AvailabilityPlan) -> String {
+    let config = ctx.config;
+    let (repository, tag, pull_policy) = ctx.image();
+    let (requests, limits) = ctx.resources(mode);
+    let amounts = |a: &ResourceAmounts| {
+        format!("{{ cpu: {}, memory: {} }}", quote(&a.cpu), quote(&a.memory))
+    };
+
+    let pull_secrets = match ctx.cluster_image().pull_secret {
+        Some(secret) => format!("\n- {}", quote(&secret)),
+        None => " []".to_string(),
+    };
+    let node_env = if ctx.static_files {
+        String::new()
+    } else {
+        format!("nodeEnv: {}\n", quote(mode))
+    };
+    let pdb = if plan.min_available.is_some() {
+        "podDisruptionBudget:\n  enabled: true\n"
+    } else {
+        ""
+    };
+
+    format!(
+        r#"# Generated by `rustify export helm`. Mirrors what `rustify deploy{flag}` applies.
+# Hosts, TLS, scaling targets, shutdown and quota settings are part of the
+# templates; export the chart again after changing them in rustify.json.
+nameOverride: ""
+
+image:
+  repository: {repository}
+  tag: {tag}
+  pullPolicy: {pull_policy}
+imagePullSecrets:{pull_secrets}
+
+replicaCount: {replicas}
+port: {port}
+{node_env}# Requested by the liveness, readiness and startup probes
+healthPath: {health_path}
+
+# ConfigMap / Secret loaded with envFrom (created outside the chart)
+envFrom:
+  configMap: ""
+  secret: ""
+
+resources:
+  requests: {requests}
+  limits: {limits}
+
+ingress:
+  enabled: true
+autoscaling:
+  enabled: {autoscaling}
+{pdb}networkPolicy:
+  enabled: true
+  ingressNamespace: {ingress_namespace}
+quota:
+  enabled: true
+nginx:
+  enabled: true
+"#,
+        flag = if mode == "prod" { " --prod" } else { "" },
+        repository = quote(&repository),
+        tag = quote(&tag),
+        replicas = ctx.replicas(),
+        port = ctx.port,
+        health_path = quote(&config.kubernetes.health_path),
+        requests = amounts(&requests),
+        limits = amounts(&limits),
+        autoscaling = mode == "prod",
+        ingress_namespace = quote(&config.kubernetes.ingress_namespace),
+    )
+}
+
+pub fn write_kustomize(dir: &Path, ctx: &ExportContext) -> io::Result<()> {
+    write_files(dir, &kustomize(ctx)?)
+}
+
+/// `base/` with the dev rendering of every resource and
+/// `overlays/{dev,prod}` patching replicas, resources, quotas and ingress TLS.
+fn kustomize(ctx: &ExportContext) -> io::Result<Files> {
+    let app = ctx.app_name;
+    let namespace = ctx.namespace();
+    let image = ctx.cluster_image();
+    let dev = ctx.availability("dev")?;
+    let mut files = Files::new();
+
+    // Base: dev rendering without TLS; overlays add what differs per mode
+    let mut base_files = Vec::new();
+    if namespace != "default" {
+        files.push((
+            "base/namespace.yaml".to_string(),
+            format!(
+                r#"apiVersion: v1
+kind: Namespace
+metadata:
+  name: {namespace}
+  labels:
+    app.kubernetes.io/managed-by: rustify
+"#
+            ),
+        ));
+        base_files.push("namespace.yaml".to_string());
+    }
+
+    let workload = super::render_kubernetes_manifests(&Workload {
+        app_name: app,
+        port: ctx.port,
+        replicas: ctx.replicas(),
+        namespace: &namespace,
+        mode: "dev",
+        image: &image,
+        env_sources: &EnvSources::default(),
+        availability: &dev,
+        static_files: ctx.static_files,
+        health_path: &ctx.config.kubernetes.health_path,
+    });
+    for (file, content) in workload {
+        // The PDB and PriorityClass only belong to the overlays that want them
+        if file == "k8s-pdb.yaml" || file == "k8s-priorityclass.yaml" {
+            continue;
+        }
+        let name = file.trim_start_matches("k8s-");
+        files.push((format!("base/{}", name), format!("{}\n", content)));
+        base_files.push(name.to_string());
+    }
+
+    let plain_ingress = IngressConfig {
+        tls_secret: None,
+        cluster_issuer: None,
+        ..ctx.config.ingress.clone()
+    };
+    files.push((
+        "base/ingress.yaml".to_string(),
+        super::render_ingress(app, &namespace, ctx.port, &plain_ingress),
+    ));
+    files.push((
+        "base/network-policy.yaml".to_string(),
+        format!(
+            "{}\n",
+            super::render_network_policy(
+                app,
+                &namespace,
+                ctx.port,
+                &ctx.config.kubernetes.ingress_namespace,
+                ctx.config.kubernetes.egress.as_deref(),
+            )
+        ),
+    ));
+    files.push((
+        "base/quota.yaml".to_string(),
+        format!("{}\n", super::render_quota(&namespace, "dev", ctx.config, ctx.static_files)),
+    ));
+    base_files.extend(["ingress.yaml", "network-policy.yaml", "quota.yaml"].map(String::from));
+    files.push((
+        "base/kustomization.yaml".to_string(),
+        kustomization(&namespace, &base_files, &[], &[]),
+    ));
+
+    for mode in ["dev", "prod"] {
+        overlay(&mut files, ctx, &namespace, &image, mode)?;
+    }
+    Ok(files)
+}
+
+fn overlay(
+    files: &mut Files,
+    ctx: &ExportContext,
+    namespace: &str,
+    image: &ClusterImage,
+    mode: &str,
+) -> io::Result<()> {
+    let app = ctx.app_name;
+    let overlay = format!("overlays/{}", mode);
+    let plan = ctx.availability(mode)?;
+    let (requests, limits) = ctx.resources(mode);
+    let mut resources = vec!["../../base".to_string()];
+    let mut patches = Vec::new();
+
+    let mut pod_extras = format!(
+        "\n      terminationGracePeriodSeconds: {}",
+        plan.termination_grace_period_seconds
+    );
+    if let Some(priority_class) = &plan.priority_class {
+        pod_extras.push_str(&format!("\n      priorityClassName: {}", priority_class));
+    }
+    if plan.anti_affinity {
+        pod_extras.push_str(&format!(
+            r#"
+      affinity:
+        podAntiAffinity:
+          preferredDuringSchedulingIgnoredDuringExecution:
+          - weight: 100
+            podAffinityTerm:
+              topologyKey: kubernetes.io/hostname
+              labelSelector:
+                matchLabels:
+                  app: {app}"#
+        ));
+    }
+    let lifecycle = super::render_pre_stop(plan.pre_stop_sleep_seconds, plan.sleep_action);
+    // Static images have no Node runtime and get no NODE_ENV
+    let env = if ctx.static_files {
+        String::new()
+    } else {
+        format!(
+            r#"
+        env:
+        - name: NODE_ENV
+          value: "{mode}""#
+        )
+    };
+    files.push((
+        format!("{}/deployment-patch.yaml", overlay),
+        format!(
+            r#"apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: {app}-deployment
+  namespace: {namespace}
+spec:
+  replicas: {replicas}
+  template:
+    spec:{pod_extras}
+      containers:
+      - name: {app}{env}
+        resources:
+          requests:
+            cpu: {req_cpu}
+            memory: {req_memory}
+          limits:
+            cpu: {lim_cpu}
+            memory: {lim_memory}{lifecycle}
+"#,
+            replicas = ctx.replicas(),
+            req_cpu = quote(&requests.cpu),
+            req_memory = quote(&requests.memory),
+            lim_cpu = quote(&limits.cpu),
+            lim_memory = quote(&limits.memory),
+        ),
+    ));
+    patches.push("deployment-patch.yaml".to_string());
+
+    // Same objects as the base, so kustomize merges them by name
+    files.push((
+        format!("{}/quota-patch.yaml", overlay),
+        format!("{}\n", super::render_quota(namespace, mode, ctx.config, ctx.static_files)),
+    ));
+    patches.push("quota-patch.yaml".to_string());
+
+    let ingress = &ctx.config.ingress;
+    if let Some(secret) = ingress.tls_secret_name(app) {
+        let mut annotations = String::from("    nginx.ingress.kubernetes.io/ssl-redirect: \"true\"");
+        if let Some(issuer) = &ingress.cluster_issuer {
+            annotations.push_str(&format!("\n    cert-manager.io/cluster-issuer: {}", issuer));
+        }
+        let hosts: String = ingress
+            .hosts_for(app)
+            .iter()
+            .map(|host| format!("\n    - {}", quote(host)))
+            .collect();
+        files.push((
+            format!("{}/ingress-tls-patch.yaml", overlay),
+            format!(
+                r#"apiVersion: networking.k8s.io/v1
+kind: Ingress
+metadata:
+  name: {app}-ingress
+  namespace: {namespace}
+  annotations:
+{annotations}
+spec:
+  tls:
+  - hosts:{hosts}
+    secretName: {secret}
+"#
+            ),
+        ));
+        patches.push("ingress-tls-patch.yaml".to_string());
+    }
+
+    let extras = super::render_kubernetes_manifests(&Workload {
+        app_name: app,
+        port: ctx.port,
+        replicas: ctx.replicas(),
+        namespace,
+        mode,
+        image,
+        env_sources: &EnvSources::default(),
+        availability: &plan,
+        static_files: ctx.static_files,
+        health_path: &ctx.config.kubernetes.health_path,
+    });
+    for (file, content) in extras {
+        if file == "k8s-pdb.yaml" || file == "k8s-priorityclass.yaml" {
+            let name = file.trim_start_matches("k8s-");
+            files.push((format!("{}/{}", overlay, name), format!("{}\n", content)));
+            resources.push(name.to_string());
+        }
+    }
+    if mode == "prod" {
+        autoscale::validate(&ctx.config.scaling)?;
+        files.push((
+            format!("{}/autoscaler.yaml", overlay),
+            autoscale::manifest(app, namespace, &ctx.config.scaling),
+        ));
+        resources.push("autoscaler.yaml".to_string());
+    }
+
+    files.push((
+        format!("{}/kustomization.yaml", overlay),
+        kustomization(namespace, &resources, &patches, &[("environment", mode)]),
+    ));
+    Ok(())
+}
+
+fn kustomization(
+    namespace: &str,
+    resources: &[String],
+    patches: &[String],
+    labels: &[(&str, &str)],
+) -> String {
+    let mut out = format!(
+        "# Generated by `rustify export kustomize`.\napiVersion: kustomize.config.k8s.io/v1beta1\nkind: Kustomization\nnamespace: {}\nresources:\n",
+        namespace
+    );
+    for resource in resources {
+        out.push_str(&format!("- {}\n", resource));
+    }
+    if !patches.is_empty() {
+        out.push_str("patches:\n");
+        for patch in patches {
+            out.push_str(&format!("- path: {}\n", patch));
+        }
+    }
+    if !labels.is_empty() {
+        // Kept off selectors, which are immutable on existing Deployments
+        out.push_str("labels:\n- includeSelectors: false\n  pairs:\n");
+        for (key, value) in labels {
+            out.push_str(&format!("    {}: {}\n", key, value));
+        }
+    }
+    out
+}
+
+/// Runs `kubectl kustomize` on each overlay when kubectl is available.
+pub fn validate_kustomize(dir: &Path) -> io::Result<()> {
+    // Nothing was written to validate
+    if effects::dry_run() {
+        return Ok(());
+    }
+    for mode in ["dev", "prod"] {
+        let overlay = dir.join("overlays").join(mode);
+        let output = match super::kubectl().arg("kustomize").arg(&overlay).run() {
+            Ok(output) => output,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                println!("ℹ️  kubectl not found, skipping kustomize validation");
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "kubectl kustomize {} failed: {}",
+                overlay.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        println!("✅ overlays/{} builds", mode);
+    }
+    Ok(())
+}
+
+/// Runs `helm lint` and `helm template` on `dir` when helm is installed.
+pub fn validate_helm_chart(dir: &Path) -> io::Result<()> {
+    // Nothing was written to validate
+    if effects::dry_run() {
+        return Ok(());
+    }
+    let dir = dir.to_string_lossy();
+    for args in [vec!["lint", dir.as_ref()], vec!["template", "rustify-check", dir.as_ref()]] {
+        let output = match Command::new("helm").args(&args).run() {
+            Ok(output) => output,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                println!("ℹ️  helm not found, skipping chart validation");
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "helm {} failed:\n{}{}",
+                args[0],
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+        println!("✅ helm {} passed", args[0]);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+
+    const CONFIG: &str = r#"{
+        "registry": {"url": "ghcr.io/acme", "tag": "1.2.0"},
+        "kubernetes": {"namespace": "shop"},
+        "ingress": {"hosts": ["shop.example.com"], "clusterIssuer": "letsencrypt", "tlsSecret": null},
+        "scaling": {"minInstances": 2, "maxInstances": 6, "memoryThreshold": 80}
+    }"#;
+
+    fn render(files: Files) -> String {
+        files
+            .into_iter()
+            .map(|(path, contents)| format!("=== {} ===\n{}\n", path, contents))
+            .collect()
+    }
+
+    /// Set `UPDATE_SNAPSHOTS=1` to rewrite the snapshot after an intended
+    /// manifest change.
+    fn assert_snapshot(name: &str, actual: &str) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/snapshots/export")
+            .join(format!("{}.snap", name));
+        if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, actual).unwrap();
+            return;
+        }
+        let expected = fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            expected == actual,
+            "{} differs from tests/snapshots/export (rerun with UPDATE_SNAPSHOTS=1 if intended)",
+            name
+        );
+    }
+
+    fn export(config: &RustifyConfig, f: impl Fn(&ExportContext) -> io::Result<Files>) -> String {
+        let ctx = ExportContext {
+            app_name: "shop",
+            port: "3000",
+            config,
+            static_files: false,
+        };
+        render(f(&ctx).unwrap())
+    }
+
+    #[test]
+    fn kustomize_matches_snapshot() {
+        let config = serde_json::from_str(CONFIG).unwrap();
+        assert_snapshot("kustomize", &export(&config, kustomize));
+    }
+
+    #[test]
+    fn helm_prod_matches_snapshot() {
+        let config = serde_json::from_str(CONFIG).unwrap();
+        assert_snapshot("helm-prod", &export(&config, |ctx| helm_chart(ctx, "prod")));
+    }
+
+    #[test]
+    fn helm_dev_matches_snapshot() {
+        let config = RustifyConfig::default();
+        assert_snapshot("helm-dev", &export(&config, |ctx| helm_chart(ctx, "dev")));
+    }
+
+    #[test]
+    fn helm_values_pull_from_the_registry() {
+        let config: RustifyConfig = serde_json::from_str(CONFIG).unwrap();
+        let chart = export(&config, |ctx| helm_chart(ctx, "prod"));
+        assert!(chart.contains("imagePullSecrets:\n- \"shop-registry-credentials\"\n"));
+
+        let chart = export(&RustifyConfig::default(), |ctx| helm_chart(ctx, "prod"));
+        assert!(chart.contains("imagePullSecrets: []\n"));
+    }
+
+    #[test]
+    fn helm_templates_the_static_deployment() {
+        let config = RustifyConfig::default();
+        let ctx = ExportContext {
+            app_name: "site",
+            port: "8080",
+            config: &config,
+            static_files: true,
+        };
+        let chart = render(helm_chart(&ctx, "prod").unwrap());
+        assert!(chart.contains("{{- toYaml .Values.resources | nindent 10 }}"));
+        assert!(chart.contains("requests: { cpu: \"50m\", memory: \"32Mi\" }"));
+        assert!(!chart.contains("NODE_ENV"));
+    }
+}
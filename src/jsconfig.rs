@@ -0,0 +1,864 @@
+use std::fmt;
+
+/// One key rustify wants in the config, e.g. `build.sourcemap = false`.
+pub struct Edit {
+    pub path: &'static [&'static str],
+    /// JavaScript source of the value.
+    pub value: &'static str,
+}
+
+/// Why a config could not be merged.
+#[derive(Debug)]
+pub struct Refusal(pub String);
+
+impl fmt::Display for Refusal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+fn refuse<T>(message: impl Into<String>) -> Result<T, Refusal> {
+    Err(Refusal(message.into()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Ident,
+    Str,
+    Num,
+    Template,
+    Regex,
+    Punct,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Token {
+    kind: Kind,
+    start: usize,
+    end: usize,
+}
+
+fn is_ident_byte(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_' || c == b'$' || c >= 0x80
+}
+
+/// Skips code inside `${ ... }` up to the matching `}`; returns the index after it.
+fn skip_interpolation(b: &[u8], mut i: usize) -> Result<usize, Refusal> {
+    let mut depth = 1;
+    while i < b.len() {
+        match b[i] {
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(i + 1);
+                }
+            }
+            b'\'' | b'"' => i = skip_string(b, i)? - 1,
+            b'`' => i = skip_template(b, i)? - 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    refuse("unterminated template literal")
+}
+
+fn skip_template(b: &[u8], mut i: usize) -> Result<usize, Refusal> {
+    i += 1;
+    while i < b.len() {
+        match b[i] {
+            b'\\' => i += 1,
+            b'`' => return Ok(i + 1),
+            b'$' if b.get(i + 1) == Some(&b'{') => {
+                i = skip_interpolation(b, i + 2)?;
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    refuse("unterminated template literal")
+}
+
+fn skip_string(b: &[u8], mut i: usize) -> Result<usize, Refusal> {
+    let quote = b[i];
+    i += 1;
+    while i < b.len() {
+        match b[i] {
+            b'\\' => i += 1,
+            b'\n' => break,
+            c if c == quote => return Ok(i + 1),
+            _ => {}
+        }
+        i += 1;
+    }
+    refuse("unterminated string literal")
+}
+
+fn skip_regex(b: &[u8], mut i: usize) -> Result<usize, Refusal> {
+    let mut class = false;
+    i += 1;
+    while i < b.len() {
+        match b[i] {
+            b'\\' => i += 1,
+            b'[' => class = true,
+            b']' => class = false,
+            b'/' if !class => {
+                i += 1;
+                while i < b.len() && is_ident_byte(b[i]) {
+                    i += 1;
+                }
+                return Ok(i);
+            }
+            b'\n' => break,
+            _ => {}
+        }
+        i += 1;
+    }
+    refuse("unterminated regular expression")
+}
+
+/// A `/` starts a regular expression unless it follows a value.
+fn regex_allowed(src: &str, previous: Option<&Token>) -> bool {
+    match previous {
+        None => true,
+        Some(t) => match t.kind {
+            Kind::Punct => !matches!(&src[t.start..t.end], ")" | "]" | "}"),
+            Kind::Ident => matches!(
+                &src[t.start..t.end],
+                "return" | "typeof" | "case" | "do" | "else" | "in" | "of" | "new" | "void"
+            ),
+            _ => false,
+        },
+    }
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, Refusal> {
+    let b = src.as_bytes();
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut i = 0;
+    while i < b.len() {
+        let c = b[i];
+        if c.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == b'/' && b.get(i + 1) == Some(&b'/') {
+            while i < b.len() && b[i] != b'\n' {
+                i += 1;
+            }
+            continue;
+        }
+        if c == b'/' && b.get(i + 1) == Some(&b'*') {
+            match src[i + 2..].find("*/") {
+                Some(end) => i += end + 4,
+                None => return refuse("unterminated comment"),
+            }
+            continue;
+        }
+
+        let start = i;
+        let kind = match c {
+            b'\'' | b'"' => {
+                i = skip_string(b, i)?;
+                Kind::Str
+            }
+            b'`' => {
+                i = skip_template(b, i)?;
+                Kind::Template
+            }
+            b'0'..=b'9' => {
+                while i < b.len() && (is_ident_byte(b[i]) || b[i] == b'.') {
+                    i += 1;
+                }
+                Kind::Num
+            }
+            c if is_ident_byte(c) => {
+                while i < b.len() && is_ident_byte(b[i]) {
+                    i += 1;
+                }
+                Kind::Ident
+            }
+            b'/' if regex_allowed(src, tokens.last()) => {
+                i = skip_regex(b, i)?;
+                Kind::Regex
+            }
+            b'.' if src[i..].starts_with("...") => {
+                i += 3;
+                Kind::Punct
+            }
+            b'=' if src[i..].starts_with("=>") => {
+                i += 2;
+                Kind::Punct
+            }
+            _ => {
+                i += 1;
+                Kind::Punct
+            }
+        };
+        tokens.push(Token { kind, start, end: i });
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    tokens: Vec<Token>,
+}
+
+/// A property of an object literal, as token indices.
+struct Property {
+    key: String,
+    key_token: usize,
+    value_start: usize,
+    /// Index of the `,` or `}` ending the value.
+    value_end: usize,
+    /// Methods, getters and shorthand properties cannot be descended into.
+    plain: bool,
+}
+
+struct Object {
+    open: usize,
+    close: usize,
+    properties: Vec<Property>,
+    /// `...spread` or `[computed]` keys make the final key set unknowable.
+    dynamic: bool,
+}
+
+impl<'a> Parser<'a> {
+    fn text(&self, i: usize) -> &'a str {
+        self.tokens
+            .get(i)
+            .map(|t| &self.src[t.start..t.end])
+            .unwrap_or("")
+    }
+
+    fn is(&self, i: usize, text: &str) -> bool {
+        self.tokens
+            .get(i)
+            .is_some_and(|t| t.kind == Kind::Punct && &self.src[t.start..t.end] == text)
+    }
+
+    /// Index of the bracket closing the one at `open`.
+    fn matching(&self, open: usize) -> Result<usize, Refusal> {
+        let mut depth = 0;
+        for i in open..self.tokens.len() {
+            if self.tokens[i].kind != Kind::Punct {
+                continue;
+            }
+            match self.text(i) {
+                "(" | "[" | "{" => depth += 1,
+                ")" | "]" | "}" => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(i);
+                    }
+                }
+                _ => {}
+            }
+        }
+        refuse("unbalanced brackets")
+    }
+
+    /// Skips an expression; returns the index of the `,` / `}` / `)` / `;`
+    /// that ends it at the current nesting level.
+    fn skip_expression(&self, mut i: usize) -> Result<usize, Refusal> {
+        while i < self.tokens.len() {
+            match self.text(i) {
+                "(" | "[" | "{" if self.tokens[i].kind == Kind::Punct => i = self.matching(i)?,
+                "," | "}" | ")" | "]" | ";" if self.tokens[i].kind == Kind::Punct => return Ok(i),
+                _ => {}
+            }
+            i += 1;
+        }
+        Ok(i)
+    }
+
+    fn object(&self, open: usize) -> Result<Object, Refusal> {
+        let close = self.matching(open)?;
+        let mut properties = Vec::new();
+        let mut dynamic = false;
+        let mut i = open + 1;
+        while i < close {
+            if self.is(i, "...") {
+                dynamic = true;
+                i = self.skip_expression(i + 1)?;
+            } else if self.is(i, "[") {
+                dynamic = true;
+                i = self.skip_expression(self.matching(i)? + 1)?;
+            } else {
+                let token = self.tokens[i];
+                let mut key = match token.kind {
+                    Kind::Ident | Kind::Num => self.text(i).to_string(),
+                    Kind::Str => {
+                        let quoted = self.text(i);
+                        quoted[1..quoted.len() - 1].to_string()
+                    }
+                    _ => return refuse(format!("unexpected `{}` in object literal", self.text(i))),
+                };
+                // `async foo() {}`, `get foo() {}`
+                let mut method = false;
+                if matches!(key.as_str(), "async" | "get" | "set")
+                    && self.tokens.get(i + 1).is_some_and(|t| t.kind == Kind::Ident)
+                {
+                    i += 1;
+                    key = self.text(i).to_string();
+                    method = true;
+                }
+                if self.is(i + 1, ":") && !method {
+                    let end = self.skip_expression(i + 2)?;
+                    properties.push(Property {
+                        key,
+                        key_token: i,
+                        value_start: i + 2,
+                        value_end: end,
+                        plain: true,
+                    });
+                    i = end;
+                } else if self.is(i + 1, "(") {
+                    let body = self.matching(i + 1)? + 1;
+                    let end = self.skip_expression(body)?;
+                    properties.push(Property {
+                        key,
+                        key_token: i,
+                        value_start: i,
+                        value_end: end,
+                        plain: false,
+                    });
+                    i = end;
+                } else {
+                    // Shorthand `{ plugins }`
+                    properties.push(Property {
+                        key,
+                        key_token: i,
+                        value_start: i,
+                        value_end: i + 1,
+                        plain: false,
+                    });
+                    i += 1;
+                }
+            }
+            if self.is(i, ",") {
+                i += 1;
+            }
+        }
+        Ok(Object {
+            open,
+            close,
+            properties,
+            dynamic,
+        })
+    }
+
+    /// Top-level `const name = <expr>` bindings, by name.
+    fn binding(&self, name: &str) -> Option<usize> {
+        let mut i = 0;
+        let mut found = None;
+        while i < self.tokens.len() {
+            if matches!(self.text(i), "(" | "[" | "{") && self.tokens[i].kind == Kind::Punct {
+                i = self.matching(i).ok()? + 1;
+                continue;
+            }
+            if matches!(self.text(i), "const" | "let" | "var") && self.text(i + 1) == name {
+                // Skip a TypeScript annotation such as `: NextConfig`
+                let mut j = i + 2;
+                while j < self.tokens.len() && !self.is(j, "=") && !self.is(j, ";") {
+                    j += 1;
+                }
+                if self.is(j, "=") {
+                    found = Some(j + 1);
+                }
+            }
+            i += 1;
+        }
+        found
+    }
+
+    /// Token index where the exported config expression starts.
+    fn export(&self) -> Result<usize, Refusal> {
+        let mut i = 0;
+        let mut found = None;
+        while i < self.tokens.len() {
+            if matches!(self.text(i), "(" | "[" | "{") && self.tokens[i].kind == Kind::Punct {
+                i = self.matching(i)? + 1;
+                continue;
+            }
+            if self.text(i) == "export" && self.text(i + 1) == "default" {
+                found = Some(i + 2);
+            }
+            if self.text(i) == "module"
+                && self.is(i + 1, ".")
+                && self.text(i + 2) == "exports"
+                && self.is(i + 3, "=")
+            {
+                found = Some(i + 4);
+            }
+            i += 1;
+        }
+        found.map_or_else(
+            || refuse("no `export default` or `module.exports =` found"),
+            Ok,
+        )
+    }
+
+    /// Follows identifiers and wrapper calls (`defineConfig({...})`,
+    /// `withPlugins(config)`) down to the exported object literal.
+    fn resolve(&self, i: usize, depth: usize) -> Result<usize, Refusal> {
+        if depth > 8 {
+            return refuse("the exported config is too indirect");
+        }
+        let text = self.text(i);
+        if self.is(i, "{") {
+            return Ok(i);
+        }
+        if self.is(i, "(") {
+            if self.is(self.matching(i)? + 1, "=>") {
+                return refuse("the config is exported as a function");
+            }
+            return self.resolve(i + 1, depth + 1);
+        }
+        if matches!(text, "function" | "async" | "class" | "await") {
+            return refuse("the config is exported as a function");
+        }
+        if self.tokens.get(i).is_none_or(|t| t.kind != Kind::Ident) {
+            return refuse(format!("the exported value `{}` is not an object", text));
+        }
+        if self.is(i + 1, "=>") {
+            return refuse("the config is exported as a function");
+        }
+        if self.is(i + 1, "(") {
+            // `withPlugins(options)(config)` wraps the last argument list
+            let mut call = i + 1;
+            while self.is(self.matching(call)? + 1, "(") {
+                call = self.matching(call)? + 1;
+            }
+            if self.is(call + 1, ")") {
+                return refuse(format!("the config is built at runtime by `{}()`", text));
+            }
+            // `withPlugins([plugins], config)` takes the config last
+            let mut argument = call + 1;
+            let mut end = self.skip_expression(argument)?;
+            while self.is(end, ",") && !self.is(end + 1, ")") {
+                argument = end + 1;
+                end = self.skip_expression(argument)?;
+            }
+            return self.resolve(argument, depth + 1);
+        }
+        if self.is(i + 1, ".") || self.is(i + 1, "[") {
+            return refuse(format!("the config is read from `{}.…`", text));
+        }
+        match self.binding(text) {
+            Some(value) => self.resolve(value, depth + 1),
+            None => refuse(format!("`{}` is imported or not a top-level constant", text)),
+        }
+    }
+
+    /// Whitespace before the token at `i` on its line, if it starts the line.
+    fn line_indent(&self, i: usize) -> Option<&'a str> {
+        let start = self.tokens[i].start;
+        let line_start = self.src[..start].rfind('\n').map_or(0, |p| p + 1);
+        let indent = &self.src[line_start..start];
+        indent.chars().all(|c| c == ' ' || c == '\t').then_some(indent)
+    }
+
+    fn indent_unit(&self) -> &'static str {
+        if self.src.lines().any(|l| l.starts_with('\t')) {
+            "\t"
+        } else {
+            "  "
+        }
+    }
+
+    /// Indentation of the line holding token `i`.
+    fn indent_of_line(&self, i: usize) -> &'a str {
+        let start = self.tokens[i].start;
+        let line_start = self.src[..start].rfind('\n').map_or(0, |p| p + 1);
+        let line = &self.src[line_start..];
+        let width = line.len() - line.trim_start_matches([' ', '\t']).len();
+        &line[..width]
+    }
+}
+
+fn is_literal(text: &str) -> bool {
+    matches!(text, "true" | "false" | "null" | "undefined")
+        || text.parse::<f64>().is_ok()
+        || (text.len() >= 2
+            && (text.starts_with('\'') || text.starts_with('"'))
+            && text.ends_with(&text[..1]))
+}
+
+fn key_source(key: &str) -> String {
+    let valid = key
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+    if valid {
+        key.to_string()
+    } else {
+        format!("'{}'", key)
+    }
+}
+
+/// `a: { b: value }` on one line.
+fn inline(path: &[&str], value: &str) -> String {
+    match path {
+        [key] => format!("{}: {}", key_source(key), value),
+        [key, rest @ ..] => format!("{}: {{ {} }}", key_source(key), inline(rest, value)),
+        [] => String::new(),
+    }
+}
+
+/// `key: value` for `path`, nesting objects for the remaining keys.
+fn nested(path: &[&str], value: &str, indent: &str, unit: &str) -> String {
+    match path {
+        [key] => format!("{}: {}", key_source(key), value),
+        [key, rest @ ..] => format!(
+            "{}: {{\n{}{}{},\n{}}}",
+            key_source(key),
+            indent,
+            unit,
+            nested(rest, value, &format!("{}{}", indent, unit), unit),
+            indent
+        ),
+        [] => String::new(),
+    }
+}
+
+/// Result of one edit.
+enum Outcome {
+    Changed(String),
+    Unchanged,
+    /// The key is set to an expression; it is the user's call.
+    Kept(String),
+}
+
+fn apply_edit(src: &str, edit: &Edit) -> Result<Outcome, Refusal> {
+    let parser = Parser {
+        src,
+        tokens: tokenize(src)?,
+    };
+    let mut object = parser.object(parser.resolve(parser.export()?, 0)?)?;
+    let dotted = edit.path.join(".");
+
+    for (depth, key) in edit.path.iter().enumerate() {
+        let last = depth + 1 == edit.path.len();
+        if object.dynamic {
+            return refuse(format!(
+                "the object holding `{}` uses spread or computed keys",
+                dotted
+            ));
+        }
+        let property = object.properties.iter().rev().find(|p| p.key == *key);
+        let property = match property {
+            Some(property) => property,
+            None => return Ok(Outcome::Changed(insert(&parser, &object, &edit.path[depth..], edit.value))),
+        };
+
+        let start = parser.tokens[property.value_start].start;
+        let end = parser.tokens[property.value_end - 1].end;
+        let current = &src[start..end];
+        if last {
+            if current == edit.value {
+                return Ok(Outcome::Unchanged);
+            }
+            // Only a lone literal is rustify's to replace; `'a' + 'b'` or
+            // `isProd ? 1 : 2` is the user's expression
+            let single = property.value_end - property.value_start == 1;
+            if !property.plain || !single || !is_literal(current) {
+                return Ok(Outcome::Kept(format!(
+                    "`{}` is set to `{}`, left as is",
+                    dotted, current
+                )));
+            }
+            return Ok(Outcome::Changed(format!(
+                "{}{}{}",
+                &src[..start],
+                edit.value,
+                &src[end..]
+            )));
+        }
+
+        let is_object = property.plain
+            && parser.is(property.value_start, "{")
+            && parser.matching(property.value_start)? + 1 == property.value_end;
+        if !is_object {
+            return refuse(format!(
+                "`{}` is `{}`, not an object literal",
+                edit.path[..=depth].join("."),
+                current
+            ));
+        }
+        object = parser.object(property.value_start)?;
+    }
+    Ok(Outcome::Unchanged)
+}
+
+/// Adds `path: value` as the last property of `object`.
+fn insert(parser: &Parser, object: &Object, path: &[&str], value: &str) -> String {
+    let src = parser.src;
+    let unit = parser.indent_unit();
+    let open = parser.tokens[object.open];
+    let close = parser.tokens[object.close];
+    let multiline = src[open.end..close.start].contains('\n');
+
+    let last = match object.properties.last() {
+        Some(last) => last,
+        None => {
+            let outer = parser.indent_of_line(object.open);
+            let inner = format!("{}{}", outer, unit);
+            let body = nested(path, value, &inner, unit);
+            if multiline {
+                // Keep comments inside `{ /* ... */ }`
+                let before_close = src[..close.start].trim_end_matches([' ', '\t']);
+                return format!(
+                    "{}{}{},\n{}{}",
+                    before_close,
+                    if before_close.ends_with('\n') { inner.clone() } else { format!("\n{}", inner) },
+                    body,
+                    outer,
+                    &src[close.start..]
+                );
+            }
+            return format!(
+                "{}{{\n{}{},\n{}}}{}",
+                &src[..open.start],
+                inner,
+                body,
+                outer,
+                &src[close.end..]
+            );
+        }
+    };
+
+    let value_end = parser.tokens[last.value_end - 1].end;
+    let has_comma = parser.is(last.value_end, ",");
+    let after_value = if has_comma {
+        parser.tokens[last.value_end].end
+    } else {
+        value_end
+    };
+
+    if !multiline {
+        let body = inline(path, value);
+        return if has_comma {
+            format!("{} {},{}", &src[..after_value], body, &src[after_value..])
+        } else {
+            format!("{}, {}{}", &src[..after_value], body, &src[after_value..])
+        };
+    }
+
+    let indent = object
+        .properties
+        .first()
+        .and_then(|p| parser.line_indent(p.key_token))
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{}{}", parser.indent_of_line(object.open), unit));
+    // Insert after a trailing `// comment` on the last property's line
+    let line_end = src[after_value..]
+        .find('\n')
+        .map_or(src.len(), |p| after_value + p);
+    let next_token = if has_comma { last.value_end + 1 } else { last.value_end };
+    let at = if parser.tokens[next_token].start > line_end {
+        line_end
+    } else {
+        after_value
+    };
+    let body = nested(path, value, &indent, unit);
+    let mut out = String::with_capacity(src.len() + body.len() + indent.len() + 4);
+    out.push_str(&src[..value_end]);
+    if !has_comma {
+        out.push(',');
+    }
+    out.push_str(&src[value_end..at]);
+    out.push('\n');
+    out.push_str(&indent);
+    out.push_str(&body);
+    out.push(',');
+    out.push_str(&src[at..]);
+    out
+}
+
+/// Applies `edits` to the object a config file exports. Only the spans of
+/// the touched keys change, so the rest of the file, comments included,
+/// stays byte for byte the same. Configs that compute the exported value at
+/// runtime are refused rather than guessed at. Returns the new source and
+/// notes about keys that were left alone.
+pub fn merge(src: &str, edits: &[Edit]) -> Result<(String, Vec<String>), Refusal> {
+    let mut current = src.to_string();
+    let mut notes = Vec::new();
+    for edit in edits {
+        match apply_edit(&current, edit)? {
+            Outcome::Changed(next) => current = next,
+            Outcome::Unchanged => {}
+            Outcome::Kept(note) => notes.push(note),
+        }
+    }
+    // A merge that produces an unparsable file must never be written
+    let check = Parser {
+        src: &current,
+        tokens: tokenize(&current)?,
+    };
+    check.object(check.resolve(check.export()?, 0)?)?;
+    Ok((current, notes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMPRESS: &[Edit] = &[Edit { path: &["compress"], value: "true" }];
+    const SOURCEMAP: &[Edit] = &[Edit { path: &["build", "sourcemap"], value: "false" }];
+
+    fn merged(src: &str, edits: &[Edit]) -> String {
+        let (out, notes) = merge(src, edits).unwrap();
+        assert!(notes.is_empty(), "{:?}", notes);
+        out
+    }
+
+    fn refusal(src: &str, edits: &[Edit]) -> String {
+        merge(src, edits).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn tells_regex_from_division() {
+        let src = "const half = 10 / 2 / 1;\nconst re = /}{\\//g;\nmodule.exports = { compress: false, test: re };\n";
+        assert_eq!(
+            merged(src, COMPRESS),
+            "const half = 10 / 2 / 1;\nconst re = /}{\\//g;\nmodule.exports = { compress: true, test: re };\n"
+        );
+        let src = "module.exports = { test: /\\.svg$/, ratio: (4) / 2 };\n";
+        assert_eq!(
+            merged(src, COMPRESS),
+            "module.exports = { test: /\\.svg$/, ratio: (4) / 2, compress: true };\n"
+        );
+    }
+
+    #[test]
+    fn keeps_comments() {
+        let src = "// next.config.js\nmodule.exports = {\n  /* { not a key } */\n  reactStrictMode: true, // strict\n};\n";
+        assert_eq!(
+            merged(src, COMPRESS),
+            "// next.config.js\nmodule.exports = {\n  /* { not a key } */\n  reactStrictMode: true, // strict\n  compress: true,\n};\n"
+        );
+    }
+
+    #[test]
+    fn refuses_to_merge_into_spreads() {
+        let src = "const base = require('./base');\nmodule.exports = { ...base, compress: false };\n";
+        assert!(refusal(src, COMPRESS).contains("spread"));
+        let src = "export default defineConfig({ build: { ...shared } });\n";
+        assert!(refusal(src, SOURCEMAP).contains("`build.sourcemap`"));
+    }
+
+    #[test]
+    fn refuses_runtime_configs() {
+        assert!(refusal("module.exports = (phase) => ({});\n", COMPRESS).contains("function"));
+        assert!(refusal("module.exports = makeConfig();\n", COMPRESS).contains("runtime"));
+        assert!(refusal("import config from './base';\nexport default config;\n", COMPRESS)
+            .contains("imported"));
+        assert!(refusal("const a = 1;\n", COMPRESS).contains("export default"));
+    }
+
+    #[test]
+    fn follows_wrapper_calls() {
+        let src = "const withPlugins = require('next-compose-plugins');\nconst nextConfig = { reactStrictMode: true };\nmodule.exports = withPlugins([withBundleAnalyzer], nextConfig);\n";
+        assert_eq!(
+            merged(src, COMPRESS),
+            "const withPlugins = require('next-compose-plugins');\nconst nextConfig = { reactStrictMode: true, compress: true };\nmodule.exports = withPlugins([withBundleAnalyzer], nextConfig);\n"
+        );
+        let src = "export default withPWA({ dest: 'public' })({ compress: false });\n";
+        assert_eq!(
+            merged(src, COMPRESS),
+            "export default withPWA({ dest: 'public' })({ compress: true });\n"
+        );
+        let src = "import { defineConfig } from 'vite';\n\nexport default defineConfig({\n  plugins: [react()],\n});\n";
+        assert_eq!(
+            merged(src, SOURCEMAP),
+            "import { defineConfig } from 'vite';\n\nexport default defineConfig({\n  plugins: [react()],\n  build: {\n    sourcemap: false,\n  },\n});\n"
+        );
+    }
+
+    #[test]
+    fn follows_typescript_annotations() {
+        let src = "import type { NextConfig } from 'next';\n\nconst nextConfig: NextConfig = {\n  compress: false,\n};\n\nexport default nextConfig;\n";
+        assert_eq!(
+            merged(src, COMPRESS),
+            "import type { NextConfig } from 'next';\n\nconst nextConfig: NextConfig = {\n  compress: true,\n};\n\nexport default nextConfig;\n"
+        );
+        let src = "export default {\n  build: { target: 'esnext' },\n} satisfies UserConfig;\n";
+        assert_eq!(
+            merged(src, SOURCEMAP),
+            "export default {\n  build: { target: 'esnext', sourcemap: false },\n} satisfies UserConfig;\n"
+        );
+        let src = "const config = { compress: false } as const;\nexport default config;\n";
+        assert_eq!(
+            merged(src, COMPRESS),
+            "const config = { compress: true } as const;\nexport default config;\n"
+        );
+    }
+
+    #[test]
+    fn inserts_into_empty_objects() {
+        assert_eq!(
+            merged("module.exports = {};\n", SOURCEMAP),
+            "module.exports = {\n  build: {\n    sourcemap: false,\n  },\n};\n"
+        );
+        assert_eq!(
+            merged("export default {\n\t// nothing yet\n};\n", COMPRESS),
+            "export default {\n\t// nothing yet\n\tcompress: true,\n};\n"
+        );
+    }
+
+    #[test]
+    fn inserts_into_inline_objects() {
+        assert_eq!(
+            merged("module.exports = { reactStrictMode: true };\n", COMPRESS),
+            "module.exports = { reactStrictMode: true, compress: true };\n"
+        );
+        assert_eq!(
+            merged("module.exports = { reactStrictMode: true, };\n", SOURCEMAP),
+            "module.exports = { reactStrictMode: true, build: { sourcemap: false }, };\n"
+        );
+    }
+
+    #[test]
+    fn inserts_into_multiline_objects() {
+        let src = "module.exports = {\n    images: {\n        domains: ['cdn.example.com'],\n    },\n    reactStrictMode: true\n}\n";
+        assert_eq!(
+            merged(src, COMPRESS),
+            "module.exports = {\n    images: {\n        domains: ['cdn.example.com'],\n    },\n    reactStrictMode: true,\n    compress: true,\n}\n"
+        );
+        let src = "export default defineConfig({\n  build: {\n    outDir: 'dist',\n  },\n});\n";
+        assert_eq!(
+            merged(src, SOURCEMAP),
+            "export default defineConfig({\n  build: {\n    outDir: 'dist',\n    sourcemap: false,\n  },\n});\n"
+        );
+    }
+
+    #[test]
+    fn keeps_user_expressions() {
+        for value in ["'a' + 'b'", "isProd ? true : false", "process.env.COMPRESS", "-1"] {
+            let src = format!("module.exports = {{ compress: {} }};\n", value);
+            let (out, notes) = merge(&src, COMPRESS).unwrap();
+            assert_eq!(out, src);
+            assert_eq!(notes, [format!("`compress` is set to `{}`, left as is", value)]);
+        }
+    }
+
+    #[test]
+    fn replaces_literals() {
+        for value in ["false", "'no'", "0", "null"] {
+            let src = format!("module.exports = {{ compress: {} }};\n", value);
+            assert_eq!(merged(&src, COMPRESS), "module.exports = { compress: true };\n");
+        }
+        let src = "module.exports = { compress: true };\n";
+        assert_eq!(merged(src, COMPRESS), src);
+    }
+
+    #[test]
+    fn refuses_non_object_parents() {
+        let src = "export default { build: buildOptions };\n";
+        assert!(refusal(src, SOURCEMAP).contains("not an object literal"));
+    }
+}
@@ -1,16 +1,33 @@
 use chrono::Local;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
+use std::sync::OnceLock;
 use std::thread;
 use std::time::Duration;
 use std::{
     fs,
-    io::{self, Write},
+    io::{self, Read, Write},
     path::Path,
     process::Command,
 };
+mod artifacts;
+mod autoscale;
+mod catalog;
+mod certs;
+mod detect;
+mod effects;
+mod envfile;
+mod export;
 mod gradient;
+mod jsconfig;
+mod optimize;
+mod packagejson;
+mod plan;
+mod registry;
+mod secrets;
+mod stacks;
+mod templates;
 use clap::{App, Arg, SubCommand};
+use effects::Effect;
 use gradient::GradientText;
 
 // Replace the Unix-specific import with conditional compilation
@@ -66,19 +83,560 @@ pub struct PerformanceMetrics {
     cpu_usage_percent: f64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ScalingConfig {
-    #[serde(default)]
+    /// Target CPU utilization in percent.
+    #[serde(default = "default_scale_threshold")]
     auto_scale_threshold: f64,
     #[serde(default = "default_min_instances")]
     min_instances: u32,
     #[serde(default = "default_max_instances")]
     max_instances: u32,
-    #[serde(default)]
+    /// Scale-up stabilization window in seconds.
+    #[serde(default = "default_scale_up_cooldown")]
     scale_up_cooldown: u64,
-    #[serde(default)]
+    /// Scale-down stabilization window in seconds.
+    #[serde(default = "default_scale_down_cooldown")]
     scale_down_cooldown: u64,
+    /// Target memory utilization in percent.
+    #[serde(default)]
+    memory_threshold: Option<u32>,
+    /// Target requests per second per pod.
+    #[serde(default)]
+    requests_per_second: Option<f64>,
+    /// Pods metric exposed by prometheus-adapter for `requestsPerSecond`.
+    #[serde(default = "default_request_metric")]
+    request_metric: String,
+    /// Generates a KEDA ScaledObject instead of a plain HPA.
+    #[serde(default)]
+    keda: Option<KedaConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct KedaConfig {
+    #[serde(default)]
+    prometheus_address: Option<String>,
+    /// PromQL returning the total request rate of the app.
+    #[serde(default)]
+    query: Option<String>,
+}
+
+impl Default for ScalingConfig {
+    fn default() -> Self {
+        ScalingConfig {
+            auto_scale_threshold: default_scale_threshold(),
+            min_instances: default_min_instances(),
+            max_instances: default_max_instances(),
+            scale_up_cooldown: default_scale_up_cooldown(),
+            scale_down_cooldown: default_scale_down_cooldown(),
+            memory_threshold: None,
+            requests_per_second: None,
+            request_metric: default_request_metric(),
+            keda: None,
+        }
+    }
+}
+
+/// Project-level settings read from `rustify.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RustifyConfig {
+    #[serde(default)]
+    kubernetes: KubernetesConfig,
+    #[serde(default)]
+    registry: Option<RegistryConfig>,
+    #[serde(default)]
+    env: EnvConfig,
+    #[serde(default)]
+    tls: TlsConfig,
+    #[serde(default)]
+    ingress: IngressConfig,
+    #[serde(default)]
+    scaling: ScalingConfig,
+    /// Where generated artifacts go, one subdirectory per environment.
+    /// Defaults to `.rustify`.
+    #[serde(default)]
+    build_dir: Option<String>,
+}
+
+impl RustifyConfig {
+    fn build_dir(&self) -> &str {
+        self.build_dir.as_deref().unwrap_or(artifacts::DEFAULT_DIR)
+    }
+}
+
+/// Ingress in front of the app. All hosts share the same path rules.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngressConfig {
+    /// Hosts to route. Defaults to `<app>.local`.
+    #[serde(default)]
+    hosts: Vec<String>,
+    /// Path backends. Defaults to `/` on the app service.
+    #[serde(default)]
+    paths: Vec<IngressPath>,
+    #[serde(default = "default_ingress_class")]
+    class_name: String,
+    /// Secret with the certificate; `null` serves plain HTTP.
+    #[serde(default = "default_ingress_tls_secret")]
+    tls_secret: Option<String>,
+    /// cert-manager ClusterIssuer that fills `tlsSecret` instead of the local CA.
+    #[serde(default)]
+    cluster_issuer: Option<String>,
+    #[serde(default = "default_ingress_timeout")]
+    timeout_seconds: u32,
+    #[serde(default = "default_ingress_body_size")]
+    max_body_size: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngressPath {
+    path: String,
+    /// Backend service. Defaults to the app service.
+    #[serde(default)]
+    service: Option<String>,
+    /// Backend port. Defaults to the app port.
+    #[serde(default)]
+    port: Option<String>,
+}
+
+impl Default for IngressConfig {
+    fn default() -> Self {
+        IngressConfig {
+            hosts: Vec::new(),
+            paths: Vec::new(),
+            class_name: default_ingress_class(),
+            tls_secret: default_ingress_tls_secret(),
+            cluster_issuer: None,
+            timeout_seconds: default_ingress_timeout(),
+            max_body_size: default_ingress_body_size(),
+        }
+    }
+}
+
+impl IngressConfig {
+    fn hosts_for(&self, app_name: &str) -> Vec<String> {
+        if self.hosts.is_empty() {
+            vec![format!("{}.local", app_name)]
+        } else {
+            self.hosts.clone()
+        }
+    }
+
+    /// Paths with service and port filled in.
+    fn paths_for(&self, app_name: &str, app_port: &str) -> Vec<IngressPath> {
+        let default_path = [IngressPath {
+            path: "/".to_string(),
+            service: None,
+            port: None,
+        }];
+        let paths = if self.paths.is_empty() {
+            &default_path[..]
+        } else {
+            &self.paths[..]
+        };
+
+        paths
+            .iter()
+            .map(|p| IngressPath {
+                path: p.path.clone(),
+                service: Some(
+                    p.service
+                        .clone()
+                        .unwrap_or_else(|| format!("{}-service", app_name)),
+                ),
+                port: Some(p.port.clone().unwrap_or_else(|| app_port.to_string())),
+            })
+            .collect()
+    }
+
+    /// Secret the ingress terminates TLS with. cert-manager needs a name
+    /// even when none is configured.
+    fn tls_secret_name(&self, app_name: &str) -> Option<String> {
+        match (&self.tls_secret, &self.cluster_issuer) {
+            (Some(secret), _) => Some(secret.clone()),
+            (None, Some(_)) => Some(format!("{}-tls", app_name)),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Certificates issued by the local CA for the ingress and nginx secrets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TlsConfig {
+    /// Names and IPs to cover. Defaults to the ingress hosts and `localhost`.
+    #[serde(default)]
+    hosts: Vec<String>,
+    #[serde(default = "default_cert_valid_days")]
+    valid_days: u32,
+    /// Certificates expiring within this many days are reissued on deploy.
+    #[serde(default = "default_cert_renew_before_days")]
+    renew_before_days: u32,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        TlsConfig {
+            hosts: Vec::new(),
+            valid_days: default_cert_valid_days(),
+            renew_before_days: default_cert_renew_before_days(),
+        }
+    }
+}
+
+impl TlsConfig {
+    /// Configured hosts, or the ingress hosts plus `localhost`.
+    fn hosts_for(&self, ingress_hosts: &[String]) -> Vec<String> {
+        if self.hosts.is_empty() {
+            let mut hosts = ingress_hosts.to_vec();
+            hosts.push("localhost".to_string());
+            hosts
+        } else {
+            self.hosts.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvConfig {
+    /// Key patterns (`*` wildcards) delivered as a Kubernetes Secret.
+    #[serde(default = "default_secret_keys")]
+    secret_keys: Vec<String>,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        EnvConfig {
+            secret_keys: default_secret_keys(),
+        }
+    }
+}
+
+/// Remote registry images are pushed to before a Kubernetes deploy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryConfig {
+    /// Registry and optional repository prefix, e.g. `ghcr.io/acme`.
+    url: String,
+    #[serde(default = "default_image_tag")]
+    tag: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KubernetesConfig {
+    /// Registry reachable from the cluster (e.g. `localhost:5001`). When set,
+    /// images are pushed there instead of being loaded into the cluster nodes.
+    #[serde(default)]
+    local_registry: Option<String>,
+    /// Kubeconfig context to deploy to. Defaults to the current context.
+    #[serde(default)]
+    context: Option<String>,
+    /// Namespace to deploy into. Defaults to the context's namespace.
+    #[serde(default)]
+    namespace: Option<String>,
+    /// Context name patterns (`*` wildcards) that require confirmation.
+    #[serde(default = "default_production_contexts")]
+    production_contexts: Vec<String>,
+    /// Namespace the ingress controller runs in; allowed through the NetworkPolicy.
+    #[serde(default = "default_ingress_namespace")]
+    ingress_namespace: String,
+    /// Destinations the app may connect to besides DNS. Unset allows all
+    /// egress.
+    #[serde(default)]
+    egress: Option<Vec<EgressRule>>,
+    /// Overrides the per-mode ResourceQuota of the app namespace.
+    #[serde(default)]
+    quota: Option<QuotaConfig>,
+    /// Overrides the per-mode LimitRange of the app namespace.
+    #[serde(default)]
+    limit_range: Option<LimitRangeConfig>,
+    /// Disruption budget, scheduling and shutdown settings.
+    #[serde(default)]
+    availability: AvailabilityConfig,
+    /// Path the liveness, readiness and startup probes request.
+    #[serde(default = "default_health_path")]
+    health_path: String,
+}
+
+/// Settings left unset fall back to per-mode defaults (see `resolve`).
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AvailabilityConfig {
+    #[serde(default)]
+    pod_disruption_budget: Option<bool>,
+    /// PDB `minAvailable` (count or percentage). Sized from replicas by default.
+    #[serde(default)]
+    min_available: Option<String>,
+    /// PriorityClass for the pods. Created when `priorityValue` is set.
+    #[serde(default)]
+    priority_class: Option<String>,
+    #[serde(default)]
+    priority_value: Option<i32>,
+    /// Preferred anti-affinity spreading pods across nodes.
+    #[serde(default)]
+    anti_affinity: Option<bool>,
+    #[serde(default)]
+    termination_grace_period_seconds: Option<u32>,
+    /// Sleep before SIGTERM so ingress endpoints drain first.
+    #[serde(default)]
+    pre_stop_sleep_seconds: Option<u32>,
+}
+
+/// `AvailabilityConfig` with every default applied.
+#[derive(Debug, Clone)]
+struct AvailabilityPlan {
+    /// `None` when no PodDisruptionBudget should exist.
+    min_available: Option<String>,
+    priority_class: Option<String>,
+    priority_value: Option<i32>,
+    anti_affinity: bool,
+    termination_grace_period_seconds: u32,
+    pre_stop_sleep_seconds: u32,
+    /// The kubelet's own `sleep` preStop action, which Kubernetes 1.30
+    /// added. Older clusters reject it and get `exec sleep` instead.
+    sleep_action: bool,
+}
+
+impl AvailabilityConfig {
+    fn resolve(&self, mode: &str, replicas: i32) -> io::Result<AvailabilityPlan> {
+        let prod = mode == "prod";
+
+        // A budget on a single replica would block node drains forever
+        let min_available = if self.pod_disruption_budget.unwrap_or(prod) && replicas > 1 {
+            Some(
+                self.min_available
+                    .clone()
+                    .unwrap_or_else(|| (replicas - (replicas / 4).max(1)).to_string()),
+            )
+        } else {
+            None
+        };
+
+        let plan = AvailabilityPlan {
+            min_available,
+            priority_class: self.priority_class.clone(),
+            priority_value: self.priority_value,
+            anti_affinity: self.anti_affinity.unwrap_or(prod),
+            termination_grace_period_seconds: self
+                .termination_grace_period_seconds
+                .unwrap_or(if prod { 60 } else { 30 }),
+            pre_stop_sleep_seconds: self.pre_stop_sleep_seconds.unwrap_or(if prod { 10 } else { 0 }),
+            sleep_action: true,
+        };
+
+        if plan.priority_value.is_some() && plan.priority_class.is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "availability.priorityValue requires availability.priorityClass",
+            ));
+        }
+        if plan.pre_stop_sleep_seconds >= plan.termination_grace_period_seconds {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "preStopSleepSeconds ({}) must be shorter than terminationGracePeriodSeconds ({})",
+                    plan.pre_stop_sleep_seconds, plan.termination_grace_period_seconds
+                ),
+            ));
+        }
+        Ok(plan)
+    }
+}
+
+/// CPU and memory in Kubernetes quantity notation (`500m`, `2Gi`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceAmounts {
+    cpu: String,
+    memory: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaConfig {
+    requests: ResourceAmounts,
+    limits: ResourceAmounts,
+}
+
+/// One allowed egress destination: pods in `namespace`, addresses in
+/// `cidr`, or anywhere when both are unset. No `ports` means every port.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct EgressRule {
+    #[serde(default)]
+    namespace: Option<String>,
+    #[serde(default)]
+    cidr: Option<String>,
+    #[serde(default)]
+    ports: Vec<u16>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LimitRangeConfig {
+    default_request: ResourceAmounts,
+    default_limit: ResourceAmounts,
+    max: ResourceAmounts,
+}
+
+impl ResourceAmounts {
+    fn new(cpu: &str, memory: &str) -> Self {
+        ResourceAmounts {
+            cpu: cpu.to_string(),
+            memory: memory.to_string(),
+        }
+    }
+
+    /// `count` times these amounts, e.g. `500m` × 3 = `1500m`.
+    fn times(&self, count: u32) -> Self {
+        let times = |quantity: &str| {
+            let digits = quantity
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .unwrap_or(quantity.len());
+            let (number, unit) = quantity.split_at(digits);
+            let number = match number.parse::<f64>() {
+                Ok(number) => number * f64::from(count),
+                Err(_) => return quantity.to_string(),
+            };
+            // `4000m` reads better as `4`, `4096Mi` as `4Gi`
+            match unit {
+                "m" if number % 1000.0 == 0.0 => format!("{}", number / 1000.0),
+                "Mi" if number % 1024.0 == 0.0 => format!("{}Gi", number / 1024.0),
+                _ => format!("{}{}", number, unit),
+            }
+        };
+        ResourceAmounts {
+            cpu: times(&self.cpu),
+            memory: times(&self.memory),
+        }
+    }
+}
+
+impl QuotaConfig {
+    /// Room for `pods` app containers at the Deployment's requests and
+    /// limits.
+    fn for_pods(mode: &str, static_files: bool, pods: u32) -> Self {
+        let (requests, limits) = container_resources(mode, static_files);
+        QuotaConfig {
+            requests: requests.times(pods),
+            limits: limits.times(pods),
+        }
+    }
+}
+
+/// Most app pods that run at once: the autoscaler's maximum plus the 25%
+/// surge of a rolling update.
+fn peak_pods(scaling: &ScalingConfig) -> u32 {
+    let most = scaling.max_instances.max(scaling.min_instances).max(1);
+    most + (most + 3) / 4
+}
+
+impl LimitRangeConfig {
+    // Defaults mirror the container resources of the generated Deployment
+    fn for_mode(mode: &str) -> Self {
+        if mode == "prod" {
+            LimitRangeConfig {
+                default_request: ResourceAmounts::new("1", "2Gi"),
+                default_limit: ResourceAmounts::new("2", "4Gi"),
+                max: ResourceAmounts::new("4", "8Gi"),
+            }
+        } else {
+            LimitRangeConfig {
+                default_request: ResourceAmounts::new("500m", "512Mi"),
+                default_limit: ResourceAmounts::new("1", "1Gi"),
+                max: ResourceAmounts::new("2", "2Gi"),
+            }
+        }
+    }
+}
+
+impl Default for KubernetesConfig {
+    fn default() -> Self {
+        KubernetesConfig {
+            local_registry: None,
+            context: None,
+            namespace: None,
+            production_contexts: default_production_contexts(),
+            ingress_namespace: default_ingress_namespace(),
+            egress: None,
+            quota: None,
+            limit_range: None,
+            availability: AvailabilityConfig::default(),
+            health_path: default_health_path(),
+        }
+    }
+}
+
+/// Context and namespace selected for this run, plus the matching client
+/// configuration. Nothing is ever written back to the kubeconfig.
+#[derive(Clone)]
+struct KubeTarget {
+    context: String,
+    namespace: String,
+    client_config: kube::Config,
+}
+
+/// Flavour of cluster behind the current kubeconfig context. Decides how a
+/// locally built image is made available to the nodes.
+#[derive(Debug, Clone, PartialEq)]
+enum ClusterKind {
+    DockerDesktop,
+    Kind(String),
+    K3d(String),
+    Minikube(String),
+    Other(String),
+}
+
+/// ConfigMap / Secret the app container loads via `envFrom`, plus the hash
+/// of their source files so that changing them rolls the pods.
+#[derive(Debug, Clone, Default)]
+struct EnvSources {
+    config_map: Option<String>,
+    secret: Option<String>,
+    hash: String,
+}
+
+/// Image reference and pull policy the generated Deployment should use.
+#[derive(Debug, Clone)]
+struct ClusterImage {
+    reference: String,
+    pull_policy: &'static str,
+    pull_secret: Option<String>,
+}
+
+/// Everything the app's Deployment, Service and availability resources are
+/// rendered from.
+#[derive(Debug, Clone, Copy)]
+struct Workload<'a> {
+    app_name: &'a str,
+    port: &'a str,
+    replicas: i32,
+    namespace: &'a str,
+    mode: &'a str,
+    image: &'a ClusterImage,
+    env_sources: &'a EnvSources,
+    availability: &'a AvailabilityPlan,
+    /// Sizes the pod for the web server image that serves a static build,
+    /// which needs no Node runtime.
+    static_files: bool,
+    health_path: &'a str,
+}
+
+/// Context every kubectl invocation is pinned to once a target is resolved.
+static KUBE_CONTEXT: OnceLock<String> = OnceLock::new();
+
+fn kubectl() -> Command {
+    let mut command = Command::new("kubectl");
+    if let Some(context) = KUBE_CONTEXT.get() {
+        command.args(["--context", context]);
+    }
+    command
 }
 
 #[allow(dead_code)]
@@ -94,12 +652,12 @@ impl DockerManager {
         println!("🔍 Checking Docker installation...");
 
         // First check if Docker is installed
-        match Command::new("docker").arg("--version").output() {
+        match Command::new("docker").arg("--version").run() {
             Ok(_) => {
                 println!("✅ Docker is installed");
 
                 // Then check if Docker is running
-                match Command::new("docker").arg("info").output() {
+                match Command::new("docker").arg("info").run() {
                     Ok(_) => {
                         println!("✅ Docker is running");
                         Ok(())
@@ -124,7 +682,7 @@ impl DockerManager {
     fn start_docker(&self) -> io::Result<()> {
         #[cfg(target_os = "macos")]
         {
-            Command::new("open").args(["-a", "Docker"]).status()?;
+            Command::new("open").args(["-a", "Docker"]).run_status()?;
         }
 
         #[cfg(target_os = "windows")]
@@ -136,20 +694,20 @@ impl DockerManager {
                     "\"\"",
                     "\"C:\\Program Files\\Docker\\Docker\\Docker Desktop.exe\"",
                 ])
-                .status()?;
+                .run_status()?;
         }
 
         #[cfg(target_os = "linux")]
         {
             Command::new("systemctl")
                 .args(["--user", "start", "docker"])
-                .status()?;
+                .run_status()?;
         }
 
         // Wait for Docker to be ready
         println!("⏳ Waiting for Docker to start...");
         for _ in 0..30 {
-            match Command::new("docker").arg("info").output() {
+            match Command::new("docker").arg("info").run() {
                 Ok(_) => {
                     println!("✅ Docker is now running!");
                     return Ok(());
@@ -175,21 +733,21 @@ impl DockerManager {
         {
             Command::new("osascript")
                 .args(["-e", "quit app \"Docker\""])
-                .status()?;
+                .run_status()?;
         }
 
         #[cfg(target_os = "windows")]
         {
             Command::new("taskkill")
                 .args(["/IM", "Docker Desktop.exe", "/F"])
-                .status()?;
+                .run_status()?;
         }
 
         #[cfg(target_os = "linux")]
         {
             Command::new("systemctl")
                 .args(["--user", "stop", "docker"])
-                .status()?;
+                .run_status()?;
         }
 
         println!("✅ Docker stopped");
@@ -203,7 +761,7 @@ impl DockerManager {
         {
             Command::new("brew")
                 .args(["install", "--cask", "docker"])
-                .status()?;
+                .run_status()?;
         }
 
         #[cfg(target_os = "windows")]
@@ -218,30 +776,30 @@ impl DockerManager {
                         installer_url
                     ),
                 ])
-                .status()?;
+                .run_status()?;
 
             Command::new("DockerInstaller.exe")
                 .args(["install", "--quiet"])
-                .status()?;
+                .run_status()?;
 
-            fs::remove_file("DockerInstaller.exe")?;
+            effects::remove_file("DockerInstaller.exe")?;
         }
 
         #[cfg(target_os = "linux")]
         {
-            Command::new("sudo").args(["apt-get", "update"]).status()?;
+            Command::new("sudo").args(["apt-get", "update"]).run_status()?;
 
             Command::new("sudo")
                 .args(["apt-get", "install", "-y", "docker.io"])
-                .status()?;
+                .run_status()?;
 
             Command::new("sudo")
                 .args(["systemctl", "enable", "docker"])
-                .status()?;
+                .run_status()?;
 
             Command::new("sudo")
                 .args(["usermod", "-aG", "docker", &whoami::username()])
-                .status()?;
+                .run_status()?;
         }
 
         println!("✅ Docker installed successfully");
@@ -252,7 +810,7 @@ impl DockerManager {
     fn launch_docker_desktop(&self) -> io::Result<()> {
         #[cfg(target_os = "macos")]
         {
-            Command::new("open").args(["-a", "Docker"]).status()?;
+            Command::new("open").args(["-a", "Docker"]).run_status()?;
         }
 
         #[cfg(target_os = "windows")]
@@ -264,20 +822,20 @@ impl DockerManager {
                     "\"\"",
                     "\"C:\\Program Files\\Docker\\Docker\\Docker Desktop.exe\"",
                 ])
-                .status()?;
+                .run_status()?;
         }
 
         #[cfg(target_os = "linux")]
         {
             Command::new("systemctl")
                 .args(["--user", "start", "docker"])
-                .status()?;
+                .run_status()?;
         }
 
         // Wait for Docker to be ready
         println!("⏳ Waiting for Docker Desktop to start...");
         for _ in 0..30 {
-            match Command::new("docker").arg("info").output() {
+            match Command::new("docker").arg("info").run() {
                 Ok(_) => {
                     println!("✅ Docker Desktop is now running!");
                     return Ok(());
@@ -300,7 +858,7 @@ impl DockerManager {
         println!("🐳 Checking Docker setup...");
         
         // Check if Docker Desktop is installed and running
-        match Command::new("docker").arg("info").output() {
+        match Command::new("docker").arg("info").run() {
             Ok(output) => {
                 if output.status.success() {
                     println!("✅ Docker Desktop is running");
@@ -332,7 +890,7 @@ impl DockerManager {
         self.check_docker_setup()?;
 
         // Step 3: Verify Docker daemon is responsive
-        match Command::new("docker").arg("info").output() {
+        match Command::new("docker").arg("info").run() {
             Ok(output) if output.status.success() => {
                 println!("✅ Docker daemon is responsive");
             }
@@ -345,23 +903,23 @@ impl DockerManager {
         }
 
         // Step 4: Check Docker network
-        let network_check = Command::new("docker").args(["network", "ls"]).output()?;
+        let network_check = Command::new("docker").args(["network", "ls"]).run()?;
 
         if !network_check.status.success() {
             println!("⚠️ Docker network issues detected. Creating default networks...");
             Command::new("docker")
                 .args(["network", "create", "app-network"])
-                .output()?;
+                .run()?;
         }
 
         // Step 5: Clean up old containers and images
         println!("🧹 Cleaning up Docker environment...");
         Command::new("docker")
             .args(["system", "prune", "-f"])
-            .output()?;
+            .run()?;
 
         // Step 6: Verify Docker Compose
-        match Command::new("docker-compose").arg("--version").output() {
+        match Command::new("docker-compose").arg("--version").run() {
             Ok(_) => println!("✅ Docker Compose is installed"),
             Err(_) => {
                 println!("⚠️ Docker Compose not found. Please install Docker Compose.");
@@ -377,8 +935,14 @@ impl DockerManager {
     }
 }
 fn main() {
-    // Check for updates first
-    if let Ok(has_update) = check_for_updates() {
+    // Set before anything else runs, including the self-update below
+    effects::set_dry_run(std::env::args().skip(1).any(|arg| arg == "--dry-run"));
+
+    // Check for updates first. A dry run would only pretend to update and
+    // then re-exec nothing, so it keeps the current version.
+    if effects::dry_run() {
+        println!("🔸 [dry-run] skipping the automatic update");
+    } else if let Ok(has_update) = check_for_updates() {
         if has_update {
             println!("🔄 New version available. Updating automatically...");
             if let Err(e) = update_cli() {
@@ -390,7 +954,7 @@ fn main() {
                 if let Ok(current_exe) = std::env::current_exe() {
                     let _ = Command::new(current_exe)
                         .args(std::env::args().skip(1))
-                        .status();
+                        .run_status();
                     return;
                 }
             }
@@ -402,6 +966,12 @@ fn main() {
         .version(env!("CARGO_PKG_VERSION"))
         .author("Harshit Duggal")
         .about("🚀 Highly optimized deployment CLI")
+        .arg(
+            Arg::with_name("dry-run")
+                .long("dry-run")
+                .global(true)
+                .help("Print every file write and command instead of performing it"),
+        )
         .subcommand(
             SubCommand::with_name("init")
                 .about("Initialize a new project")
@@ -409,10 +979,130 @@ fn main() {
                     Arg::with_name("type")
                         .long("type")
                         .value_name("TYPE")
-                        .help("Project type (next, react, vue, svelte, astro, remix, mern)")
-                        .required(true)
-                        .possible_values(&["next", "react", "vue", "svelte", "astro", "remix", "mern"])
+                        .help("Project type")
+                        .required_unless_present("template")
+                        .conflicts_with("template")
+                        .possible_values(templates::BUILTIN)
+                )
+                .arg(
+                    Arg::with_name("template")
+                        .long("template")
+                        .value_name("PATH|GIT-URL|NAME")
+                        .help("Render a template directory, git repository or registered template"),
+                )
+                .arg(
+                    Arg::with_name("offline")
+                        .long("offline")
+                        .help("Render the built-in templates instead of running the framework's generator"),
+                )
+                .arg(
+                    Arg::with_name("name")
+                        .long("name")
+                        .value_name("NAME")
+                        .help("Package name (default: the directory name)"),
+                )
+                .arg(
+                    Arg::with_name("port")
+                        .long("port")
+                        .value_name("PORT")
+                        .help("Port the application listens on (default: 3000)"),
+                )
+                .arg(
+                    Arg::with_name("package-manager")
+                        .long("package-manager")
+                        .value_name("PM")
+                        .possible_values(templates::PACKAGE_MANAGERS)
+                        .help("Package manager used in scripts, the Dockerfile and CI (default: npm)"),
+                )
+                .arg(
+                    Arg::with_name("var")
+                        .long("var")
+                        .value_name("KEY=VALUE")
+                        .multiple_occurrences(true)
+                        .help("Set a template variable without being asked for it"),
+                )
+                .arg(
+                    Arg::with_name("yes")
+                        .long("yes")
+                        .short('y')
+                        .help("Use defaults for template variables instead of prompting"),
+                )
+                .arg(
+                    Arg::with_name("no-hooks")
+                        .long("no-hooks")
+                        .help("Skip the template's post-init hooks"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("templates")
+                .about("Manage the project template registry and dependency catalog")
+                .subcommand(SubCommand::with_name("list").about("List built-in and registered templates"))
+                .subcommand(
+                    SubCommand::with_name("add")
+                        .about("Register a template directory or git repository")
+                        .arg(Arg::with_name("name").required(true))
+                        .arg(
+                            Arg::with_name("source")
+                                .required(true)
+                                .help("Template directory or git URL (append #branch to pin one)"),
+                        )
+                        .arg(
+                            Arg::with_name("description")
+                                .long("description")
+                                .value_name("TEXT")
+                                .help("Shown by `templates list` (default: from the manifest)"),
+                        ),
                 )
+                .subcommand(
+                    SubCommand::with_name("remove")
+                        .alias("rm")
+                        .about("Remove a registered template")
+                        .arg(Arg::with_name("name").required(true)),
+                )
+                .subcommand(
+                    SubCommand::with_name("catalog")
+                        .about("Show the dependency versions generated package.json files use")
+                        .arg(
+                            Arg::with_name("framework")
+                                .possible_values(templates::BUILTIN)
+                                .help("Only show this framework's set"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("update-catalog")
+                        .about("Refresh the dependency catalog from an npm registry or a JSON file")
+                        .arg(
+                            Arg::with_name("registry")
+                                .long("registry")
+                                .value_name("URL")
+                                .required_unless_present_any(&["file", "reset"])
+                                .conflicts_with_all(&["file", "reset"])
+                                .help("npm registry or local mirror, e.g. http://localhost:4873"),
+                        )
+                        .arg(
+                            Arg::with_name("file")
+                                .long("file")
+                                .value_name("PATH")
+                                .conflicts_with("reset")
+                                .help("Catalog JSON whose entries replace the current ones"),
+                        )
+                        .arg(
+                            Arg::with_name("latest")
+                                .long("latest")
+                                .requires("registry")
+                                .help("Take each package's latest tag instead of the newest release within its current range"),
+                        )
+                        .arg(
+                            Arg::with_name("reset")
+                                .long("reset")
+                                .help("Go back to the catalog built into rustify"),
+                        )
+                        .arg(
+                            Arg::with_name("force")
+                                .long("force")
+                                .help("Save even when peer dependency checks fail"),
+                        ),
+                ),
         )
         .subcommand(
             SubCommand::with_name("update")
@@ -435,7 +1125,7 @@ fn main() {
                     Arg::with_name("port")
                         .long("port")
                         .value_name("PORT")
-                        .help("Custom port (default: 8000)"),
+                        .help("Custom port (default: 3000)"),
                 )
                 .arg(
                     Arg::with_name("rpl")
@@ -445,41 +1135,276 @@ fn main() {
                 .arg(
                     Arg::with_name("cleanup")
                         .long("cleanup")
-                        .help("Cleanup old deployments before deploying"),
+                        .help("Remove the previous container or Kubernetes resources before deploying"),
+                )
+                .arg(
+                    Arg::with_name("context")
+                        .long("context")
+                        .value_name("CONTEXT")
+                        .help("Kubeconfig context to deploy to (default: current context)"),
+                )
+                .arg(
+                    Arg::with_name("namespace")
+                        .long("namespace")
+                        .short('n')
+                        .value_name("NAMESPACE")
+                        .help("Kubernetes namespace to deploy into"),
+                )
+                .arg(
+                    Arg::with_name("yes")
+                        .long("yes")
+                        .short('y')
+                        .help("Skip the confirmation prompt for production contexts"),
+                )
+                .arg(
+                    Arg::with_name("skip-health-check")
+                        .long("skip-health-check")
+                        .help("Deploy without checking the health path on the built image first"),
                 ),
         )
-        .get_matches();
-
-    match app.subcommand() {
-        Some(("init", sub_m)) => {
-            let project_type = sub_m.value_of("type").unwrap();
-            println!("🚀 Initializing new {} project...", project_type);
-            
-            match initialize_project(project_type) {
-                Ok(_) => println!("✅ Project initialized successfully!"),
-                Err(e) => {
-                    eprintln!("❌ Error initializing project: {}", e);
-                    std::process::exit(1);
-                }
-            }
-        }
-        Some(("deploy", sub_m)) => {
-            // First check for updates
-            if let Ok(has_update) = check_for_updates() {
-                if has_update {
-                    println!("📦 Update available! Run 'rustify update --init' to update");
-                }
-            }
-
-            let is_prod = sub_m.is_present("prod");
-            let port = sub_m.value_of("port").unwrap_or("3000");
-            let auto_scale = sub_m.is_present("rpl");
-            let mut metadata = AppMetadata {
-                app_name: detect_app_name().unwrap_or_else(|_| "unknown".to_string()),
-                app_type: detect_project_type().unwrap_or_else(|_| "unknown".to_string()),
-                port: port.to_string(),
-                created_at: Local::now().to_rfc3339(),
-                container_id: None,
+        .subcommand(
+            SubCommand::with_name("plan")
+                .alias("diff")
+                .about("Show what a deploy would change; exits 2 when there are changes")
+                .arg(
+                    Arg::with_name("prod")
+                        .long("prod")
+                        .help("Plan a production (Kubernetes) deployment"),
+                )
+                .arg(
+                    Arg::with_name("port")
+                        .long("port")
+                        .value_name("PORT")
+                        .help("Custom port (default: 3000)"),
+                )
+                .arg(
+                    Arg::with_name("rpl")
+                        .long("rpl")
+                        .help("Include the autoscaler"),
+                )
+                .arg(
+                    Arg::with_name("context")
+                        .long("context")
+                        .value_name("CONTEXT")
+                        .help("Kubeconfig context to compare against (default: current context)"),
+                )
+                .arg(
+                    Arg::with_name("namespace")
+                        .long("namespace")
+                        .short('n')
+                        .value_name("NAMESPACE")
+                        .help("Kubernetes namespace to compare against"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("destroy")
+                .about("Remove the deployed application and its generated files")
+                .arg(
+                    Arg::with_name("prod")
+                        .long("prod")
+                        .help("Remove the production (Kubernetes) deployment"),
+                )
+                .arg(
+                    Arg::with_name("context")
+                        .long("context")
+                        .value_name("CONTEXT")
+                        .help("Kubeconfig context (default: current context)"),
+                )
+                .arg(
+                    Arg::with_name("namespace")
+                        .long("namespace")
+                        .short('n')
+                        .value_name("NAMESPACE")
+                        .help("Namespace the application runs in"),
+                )
+                .arg(
+                    Arg::with_name("yes")
+                        .long("yes")
+                        .short('y')
+                        .help("Skip the confirmation prompt for production contexts"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("detect")
+                .about("Show the framework detected in the current directory")
+                .arg(
+                    Arg::with_name("explain")
+                        .long("explain")
+                        .help("Show rendering mode, build output, start command, port and the signals behind the score"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("optimize")
+                .about("Propose framework optimizations, apply them after confirmation")
+                .arg(
+                    Arg::with_name("type")
+                        .long("type")
+                        .value_name("TYPE")
+                        .possible_values(&[
+                            "next", "react", "vue", "nuxt", "svelte", "angular", "astro", "remix", "mern",
+                        ])
+                        .help("Framework to optimize for (default: detected)"),
+                )
+                .arg(
+                    Arg::with_name("yes")
+                        .long("yes")
+                        .short('y')
+                        .help("Apply without asking for confirmation"),
+                )
+                .arg(
+                    Arg::with_name("undo")
+                        .long("undo")
+                        .conflicts_with_all(&["type", "yes"])
+                        .help("Restore the files changed by the last optimize run"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("clean")
+                .about("Remove generated files from the build directory")
+                .arg(
+                    Arg::with_name("env")
+                        .long("env")
+                        .value_name("ENV")
+                        .possible_values(&["dev", "prod"])
+                        .help("Only clean one environment (default: all)"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("export")
+                .about("Export the generated Kubernetes resources")
+                .subcommand(
+                    SubCommand::with_name("helm")
+                        .about("Write a Helm chart")
+                        .arg(
+                            Arg::with_name("dir")
+                                .required(true)
+                                .help("Directory to write the chart to"),
+                        )
+                        .arg(
+                            Arg::with_name("prod")
+                                .long("prod")
+                                .help("Use production defaults"),
+                        )
+                        .arg(
+                            Arg::with_name("port")
+                                .long("port")
+                                .value_name("PORT")
+                                .help("Application port (default: 3000)"),
+                        )
+                        .arg(
+                            Arg::with_name("force")
+                                .long("force")
+                                .help("Overwrite a non-empty directory"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("kustomize")
+                        .about("Write a kustomize base with dev and prod overlays")
+                        .arg(
+                            Arg::with_name("dir")
+                                .required(true)
+                                .help("Directory to write base/ and overlays/ to"),
+                        )
+                        .arg(
+                            Arg::with_name("port")
+                                .long("port")
+                                .value_name("PORT")
+                                .help("Application port (default: 3000)"),
+                        )
+                        .arg(
+                            Arg::with_name("force")
+                                .long("force")
+                                .help("Overwrite a non-empty directory"),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("scale")
+                .about("Inspect the application's autoscaler")
+                .arg(
+                    Arg::with_name("show")
+                        .long("show")
+                        .help("Explain the current autoscaling decisions"),
+                )
+                .arg(
+                    Arg::with_name("context")
+                        .long("context")
+                        .value_name("CONTEXT")
+                        .help("Kubeconfig context (default: current context)"),
+                )
+                .arg(
+                    Arg::with_name("namespace")
+                        .long("namespace")
+                        .short('n')
+                        .value_name("NAMESPACE")
+                        .help("Namespace the application runs in"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("secrets")
+                .about("Manage the encrypted secrets store")
+                .subcommand(
+                    SubCommand::with_name("set")
+                        .about("Encrypt and store a secret")
+                        .arg(Arg::with_name("name").required(true))
+                        .arg(
+                            Arg::with_name("value")
+                                .help("Secret value (read from stdin when omitted)"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("get")
+                        .about("Print a decrypted secret")
+                        .arg(Arg::with_name("name").required(true)),
+                )
+                .subcommand(SubCommand::with_name("list").about("List stored secret names"))
+                .subcommand(
+                    SubCommand::with_name("rm")
+                        .about("Remove a secret")
+                        .arg(Arg::with_name("name").required(true)),
+                ),
+        )
+        .get_matches();
+
+    match app.subcommand() {
+        Some(("init", sub_m)) => {
+            let project_type = sub_m
+                .value_of("type")
+                .or_else(|| sub_m.value_of("template"))
+                .unwrap();
+            println!("🚀 Initializing new {} project...", project_type);
+            
+            match initialize_project(sub_m) {
+                Ok(_) => println!("✅ Project initialized successfully!"),
+                Err(e) => {
+                    eprintln!("❌ Error initializing project: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(("deploy", sub_m)) => {
+            // First check for updates
+            if let Ok(has_update) = check_for_updates() {
+                if has_update {
+                    println!("📦 Update available! Run 'rustify update --init' to update");
+                }
+            }
+
+            let is_prod = sub_m.is_present("prod");
+            let port = sub_m.value_of("port").unwrap_or("3000");
+            let auto_scale = sub_m.is_present("rpl");
+            let mut metadata = AppMetadata {
+                app_name: detect_app_name().unwrap_or_else(|_| "unknown".to_string()),
+                app_type: match detect_project_type() {
+                    Ok(app_type) => app_type,
+                    Err(e) => {
+                        eprintln!("❌ {}", e);
+                        std::process::exit(1);
+                    }
+                },
+                port: port.to_string(),
+                created_at: Local::now().to_rfc3339(),
+                container_id: None,
                 _status: "pending".to_string(),
                 kubernetes_enabled: is_prod,
                 kubernetes_metadata: KubernetesMetadata::default(),
@@ -487,43 +1412,150 @@ fn main() {
                 scaling_config: ScalingConfig::default(),
             };
 
-            if let Err(e) = deploy_application(&mut metadata, is_prod, auto_scale) {
+            let mut config = match load_project_config() {
+                Ok(config) => config,
+                Err(e) => {
+                    eprintln!("❌ {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            // Command line flags take precedence over rustify.json
+            if let Some(context) = sub_m.value_of("context") {
+                config.kubernetes.context = Some(context.to_string());
+            }
+            if let Some(namespace) = sub_m.value_of("namespace") {
+                config.kubernetes.namespace = Some(namespace.to_string());
+            }
+            let assume_yes = sub_m.is_present("yes");
+            let health_check = !sub_m.is_present("skip-health-check");
+            let cleanup = sub_m.is_present("cleanup");
+            metadata.scaling_config = config.scaling.clone();
+
+            if let Err(e) = deploy_application(
+                &mut metadata,
+                &config,
+                is_prod,
+                auto_scale,
+                assume_yes,
+                health_check,
+                cleanup,
+            ) {
                 eprintln!("❌ Deployment failed: {}", e);
                 std::process::exit(1);
             }
         }
+        Some(("plan", sub_m)) => match handle_plan_command(sub_m) {
+            Ok(0) => {}
+            Ok(code) => std::process::exit(code),
+            Err(e) => {
+                eprintln!("❌ {}", e);
+                std::process::exit(1);
+            }
+        },
+        Some(("destroy", sub_m)) => {
+            if let Err(e) = handle_destroy_command(sub_m) {
+                eprintln!("❌ {}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(("clean", sub_m)) => {
+            if let Err(e) = handle_clean_command(sub_m) {
+                eprintln!("❌ {}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(("export", sub_m)) => {
+            if let Err(e) = handle_export_command(sub_m) {
+                eprintln!("❌ {}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(("scale", sub_m)) => {
+            if let Err(e) = handle_scale_command(sub_m) {
+                eprintln!("❌ {}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(("secrets", sub_m)) => {
+            if let Err(e) = handle_secrets_command(sub_m) {
+                eprintln!("❌ {}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(("detect", sub_m)) => {
+            if let Err(e) = handle_detect_command(sub_m) {
+                eprintln!("❌ {}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(("optimize", sub_m)) => {
+            if let Err(e) = handle_optimize_command(sub_m) {
+                eprintln!("❌ {}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(("templates", sub_m)) => {
+            if let Err(e) = handle_templates_command(sub_m) {
+                eprintln!("❌ {}", e);
+                std::process::exit(1);
+            }
+        }
         _ => {
             println!("No command specified. Use --help for usage information.");
             std::process::exit(1);
         }
     }
 }
-fn deploy_application(metadata: &mut AppMetadata, is_prod: bool, auto_scale: bool) -> io::Result<()> {
+fn deploy_application(
+    metadata: &mut AppMetadata,
+    config: &RustifyConfig,
+    is_prod: bool,
+    auto_scale: bool,
+    assume_yes: bool,
+    health_check: bool,
+    cleanup: bool,
+) -> io::Result<()> {
     println!("🚀 Starting deployment process...");
+    artifacts::init(config.build_dir(), if is_prod { "prod" } else { "dev" });
     println!("🔍 Verifying infrastructure...");
 
     // Verify Docker installation
     verify_docker_installation()?;
 
     // Handle environment files
-    handle_env_files()?;
-
-    // Check if package.json exists and is valid
-    if !Path::new("package.json").exists() {
-        return Err(io::Error::new(
-            io::ErrorKind::NotFound,
-            "package.json not found. Please ensure you're in the correct directory."
-        ));
-    }
+    let project_env = handle_env_files(is_prod, &config.env)?;
 
     if metadata.kubernetes_enabled {
+        // Pin the target context for this run instead of switching globally
+        let target = resolve_kube_target(&config.kubernetes)?;
+        confirm_production_context(&target, &config.kubernetes, assume_yes)?;
+        let _ = KUBE_CONTEXT.set(target.context.clone());
+        metadata.kubernetes_metadata.namespace = target.namespace.clone();
+
         // Verify Kubernetes setup before proceeding
-        verify_kubernetes_setup()?;
-        
-        // Rest of your Kubernetes deployment code...
+        let (cluster, server) = verify_kubernetes_setup(&target)?;
+        build_docker_image(metadata, &project_env)?;
+        if health_check {
+            preflight_health_check(metadata, &project_env, &config.kubernetes.health_path)?;
+        }
+        if cleanup {
+            println!("🧹 Removing the previous deployment of {}", metadata.app_name);
+            let root = Path::new(config.build_dir());
+            remove_deployment(&metadata.app_name, root, Some(&target.namespace), true)?;
+        }
+        deploy_to_kubernetes(metadata, &cluster, server, config, &project_env, is_prod, auto_scale)?;
     } else {
+        build_docker_image(metadata, &project_env)?;
+        if health_check {
+            preflight_health_check(metadata, &project_env, &config.kubernetes.health_path)?;
+        }
+        if cleanup {
+            println!("🧹 Removing the previous container of {}", metadata.app_name);
+            remove_deployment(&metadata.app_name, Path::new(config.build_dir()), None, true)?;
+        }
         println!("🐳 Deploying with Docker...");
-        let container_id = deploy_to_docker(metadata)?;
+        let container_id = deploy_to_docker(metadata, &project_env)?;
         metadata.container_id = Some(container_id);
         
         // Verify container is running
@@ -531,42 +1563,69 @@ fn deploy_application(metadata: &mut AppMetadata, is_prod: bool, auto_scale: boo
     }
 
     save_metadata(metadata)?;
-    println!("✅ Deployment completed successfully!");
+    if effects::dry_run() {
+        println!("✅ Dry run finished, nothing was changed");
+    } else {
+        println!("✅ Deployment completed successfully!");
+    }
     Ok(())
 }
 
-fn deploy_to_docker(metadata: &AppMetadata) -> io::Result<String> {
-    println!("🐳 Deploying to Docker...");
-
-    // Check if package.json exists
-    if !Path::new("package.json").exists() {
-        return Err(io::Error::new(
-            io::ErrorKind::NotFound,
-            "package.json not found in current directory"
-        ));
+/// Output directory of a JS project that builds to plain files, and whether
+/// it routes on the client. Those are served by Caddy instead of Node.
+fn static_build(app_type: &str) -> Option<(String, bool)> {
+    if stacks::STACKS.contains(&app_type) {
+        return None;
     }
+    let detection = detect::detect().ok().filter(|d| d.framework == app_type)?;
+    let output = detection.static_output()?.to_string();
+    Some((output, detection.rendering == detect::Rendering::Spa))
+}
+
+/// Whether the image is a web server for plain files, with no Node runtime.
+fn serves_static_files(app_type: &str) -> bool {
+    app_type == "static" || static_build(app_type).is_some()
+}
 
+fn build_docker_image(metadata: &AppMetadata, project_env: &envfile::ProjectEnv) -> io::Result<()> {
+    let static_site = static_build(&metadata.app_type);
+    // .env files are not in the build context, but frameworks inline public
+    // variables into the client bundle while building
+    let build_args: Vec<(&String, &String)> = project_env.build_args().collect();
+    let names: Vec<&String> = build_args.iter().map(|(key, _)| *key).collect();
+    let args = stacks::arg_lines(&names);
+    for key in project_env.secret.keys().filter(|key| envfile::is_public(key)) {
+        println!(
+            "⚠️  {} matches env.secretKeys, so it is not passed to the build and stays out of the client bundle",
+            key
+        );
+    }
     // Generate Dockerfile based on project type
     let dockerfile_content = match metadata.app_type.as_str() {
+        stack if stacks::STACKS.contains(&stack) => stacks::dockerfile(stack, &metadata.port)?,
+        _ if static_site.is_some() => {
+            let (output, spa) = static_site.as_ref().unwrap();
+            stacks::static_build(output, &metadata.port, *spa, &names)
+        }
         "next" => format!(
             r#"FROM node:latest
 WORKDIR /app
 COPY . .
 RUN npm install
-RUN npm run build
+{}RUN npm run build
 EXPOSE {}
 CMD ["npm", "start"]"#,
-            metadata.port
+            args, metadata.port
         ),
         "react" | "vue" | "svelte" | "astro" => format!(
             r#"FROM node:latest
 WORKDIR /app
 COPY . .
 RUN npm install
-RUN npm run build
+{}RUN npm run build
 EXPOSE {}
 CMD ["npm", "start"]"#,
-            metadata.port
+            args, metadata.port
         ),
         "bun" => format!(
             r#"FROM oven/bun:latest
@@ -588,24 +1647,48 @@ CMD ["npm", "start"]"#,
         ),
     };
 
-    // Create .dockerignore
-    let dockerignore = r#"node_modules
+    // Create .dockerignore; env files and secrets are injected at runtime, never baked in
+    let mut dockerignore = format!(
+        r#"node_modules
 .git
-.env.local
-.env.*.local
+.env
+.env.*
+rustify.secrets.json
+.rustify-secrets.key
+{}
 npm-debug.log*
 yarn-debug.log*
 yarn-error.log*
-.DS_Store"#;
+.DS_Store"#,
+        artifacts::root().display()
+    );
+    for entry in stacks::dockerignore(&metadata.app_type) {
+        dockerignore.push('\n');
+        dockerignore.push_str(entry);
+    }
+    // The image builds its own output
+    if let Some((output, _)) = &static_site {
+        dockerignore.push('\n');
+        dockerignore.push_str(output);
+    }
 
-    fs::write("Dockerfile", dockerfile_content)?;
-    fs::write(".dockerignore", dockerignore)?;
+    effects::write("Dockerfile", dockerfile_content)?;
+    effects::write(".dockerignore", dockerignore)?;
 
-    // Build and run Docker container
     println!("🏗️  Building Docker image...");
-    let build_output = Command::new("docker")
-        .args(["build", "-t", &metadata.app_name, "."])
-        .output()?;
+    let mut build = Command::new("docker");
+    build.args(["build", "-t", &metadata.app_name]);
+    if !names.is_empty() {
+        println!(
+            "🔓 Passing public variables to the build: {}",
+            names.iter().map(|name| name.as_str()).collect::<Vec<_>>().join(", ")
+        );
+    }
+    // Values come from the environment, so they never show up in argv
+    for (key, value) in &build_args {
+        build.args(["--build-arg", key.as_str()]).env(key, value);
+    }
+    let build_output = build.arg(".").run()?;
 
     if !build_output.status.success() {
         return Err(io::Error::new(
@@ -614,18 +1697,185 @@ yarn-error.log*
         ));
     }
 
+    Ok(())
+}
+
+/// Starts the built image on a free local port and requests `health_path`,
+/// so an app without the endpoint fails here instead of as pods that the
+/// probes keep restarting.
+fn preflight_health_check(
+    metadata: &AppMetadata,
+    project_env: &envfile::ProjectEnv,
+    health_path: &str,
+) -> io::Result<()> {
+    if effects::dry_run() {
+        println!("🔸 [dry-run] would check GET {} on the built image", health_path);
+        return Ok(());
+    }
+    println!("🩺 Checking GET {} on the built image...", health_path);
+
+    let host_port = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+    let container = format!("{}-preflight", metadata.app_name);
+    let _ = Command::new("docker").args(["rm", "-f", &container]).run();
+
+    let mut run = Command::new("docker");
+    run.args([
+        "run",
+        "-d",
+        "--name",
+        &container,
+        "-p",
+        &format!("127.0.0.1:{}:{}", host_port, metadata.port),
+        "-e",
+        &format!("PORT={}", metadata.port),
+    ]);
+    for (key, value) in project_env.all() {
+        run.args(["-e", key]).env(key, value);
+    }
+    let run_output = run.arg(&metadata.app_name).run()?;
+    if !run_output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("Docker run failed: {}", String::from_utf8_lossy(&run_output.stderr)),
+        ));
+    }
+
+    // Give the app 30 seconds to start listening; stop early if it exits
+    let url = format!("http://127.0.0.1:{}{}", host_port, health_path);
+    let mut code = 0u16;
+    for _ in 0..30 {
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let output = Command::new("curl")
+            .args(["-s", "-o", "/dev/null", "-w", "%{http_code}", "--max-time", "2", &url])
+            .run()?;
+        code = String::from_utf8_lossy(&output.stdout).trim().parse().unwrap_or(0);
+        if code != 0 {
+            break;
+        }
+        let running = Command::new("docker")
+            .args(["inspect", "-f", "{{.State.Running}}", &container])
+            .run()?;
+        if String::from_utf8_lossy(&running.stdout).trim() != "true" {
+            break;
+        }
+    }
+    let logs = Command::new("docker").args(["logs", "--tail", "20", &container]).run()?;
+    let _ = Command::new("docker").args(["rm", "-f", &container]).run();
+
+    if (200..300).contains(&code) {
+        println!("✅ {} answered HTTP {}", health_path, code);
+        return Ok(());
+    }
+    let problem = if code == 0 {
+        format!(
+            "The built image did not answer on port {} within 30 seconds. Last log lines:\n{}{}",
+            metadata.port,
+            String::from_utf8_lossy(&logs.stdout),
+            String::from_utf8_lossy(&logs.stderr).trim_end()
+        )
+    } else {
+        format!("GET {} on the built image answered HTTP {}.", health_path, code)
+    };
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        format!(
+            "{}\n   The Kubernetes probes request {} and restart pods that do not answer 2xx.\n   \
+             Add the endpoint, e.g. for Express: app.get('{}', (req, res) => res.send('ok'))\n   \
+             or point the probes at a path the app serves: \"kubernetes\": {{ \"healthPath\": \"/api/status\" }} in rustify.json\n   \
+             or deploy anyway with --skip-health-check",
+            problem, health_path, health_path
+        ),
+    ))
+}
+
+fn deploy_to_kubernetes(
+    metadata: &mut AppMetadata,
+    cluster: &ClusterKind,
+    server: Option<ServerVersion>,
+    config: &RustifyConfig,
+    project_env: &envfile::ProjectEnv,
+    is_prod: bool,
+    auto_scale: bool,
+) -> io::Result<()> {
+    println!("☸️  Deploying to Kubernetes...");
+    let mode = if is_prod { "prod" } else { "dev" };
+
+    if metadata.kubernetes_metadata.namespace.is_empty() {
+        metadata.kubernetes_metadata.namespace = default_namespace();
+    }
+    if metadata.kubernetes_metadata.replicas == 0 {
+        metadata.kubernetes_metadata.replicas = metadata.scaling_config.min_instances.max(1) as i32;
+    }
+    let namespace = metadata.kubernetes_metadata.namespace.clone();
+    metadata.kubernetes_metadata.deployment_name = format!("{}-deployment", metadata.app_name);
+    metadata.kubernetes_metadata.service_name = format!("{}-service", metadata.app_name);
+
+    create_namespace_with_quotas(&namespace, mode, config, serves_static_files(&metadata.app_type))?;
+
+    let image = prepare_kubernetes_deployment(&metadata.app_name, &namespace, cluster, config)?;
+    let env_sources = apply_env_resources(&metadata.app_name, &namespace, project_env)?;
+    let tls_hosts = config.tls.hosts_for(&config.ingress.hosts_for(&metadata.app_name));
+    // Secrets cert-manager fills are left alone
+    let mut tls_secrets = vec!["nginx-ssl-certs".to_string()];
+    if config.ingress.cluster_issuer.is_none() {
+        tls_secrets.extend(config.ingress.tls_secret.clone());
+    }
+    let tls_secrets: Vec<&str> = tls_secrets.iter().map(String::as_str).collect();
+    install_tls_secrets(&namespace, &tls_secrets, &tls_hosts, &config.tls)?;
+
+    let mut availability = config
+        .kubernetes
+        .availability
+        .resolve(mode, metadata.kubernetes_metadata.replicas)?;
+    availability.sleep_action = pre_stop_sleep_action(server, &availability);
+    let manifests = generate_kubernetes_manifests(&Workload {
+        app_name: &metadata.app_name,
+        port: &metadata.port,
+        replicas: metadata.kubernetes_metadata.replicas,
+        namespace: &namespace,
+        mode,
+        image: &image,
+        env_sources: &env_sources,
+        availability: &availability,
+        static_files: serves_static_files(&metadata.app_type),
+        health_path: &config.kubernetes.health_path,
+    })?;
+    apply_kubernetes_manifests(&metadata.app_name, &namespace, &manifests)?;
+    setup_network_policies(&metadata.app_name, &namespace, &metadata.port, &config.kubernetes)?;
+    create_kubernetes_ingress(&metadata.app_name, &namespace, &metadata.port, &config.ingress)?;
+
+    if auto_scale {
+        setup_autoscaling(&metadata.app_name, &namespace, &metadata.scaling_config)?;
+    }
+
+    wait_for_kubernetes_deployment(&metadata.kubernetes_metadata.deployment_name, &namespace)?;
+    metadata.kubernetes_metadata.ingress_host =
+        verify_ingress(&metadata.app_name, &namespace, &metadata.port, &config.ingress)?;
+    update_pod_status(metadata, &namespace)?;
+    print_kubernetes_status(metadata);
+    Ok(())
+}
+
+fn deploy_to_docker(metadata: &AppMetadata, project_env: &envfile::ProjectEnv) -> io::Result<String> {
+    println!("🐳 Deploying to Docker...");
+
     println!("🚀 Starting Docker container...");
-    let run_output = Command::new("docker")
-        .args([
-            "run",
-            "-d",
-            "-p",
-            &format!("{}:{}", metadata.port, metadata.port),
-            "--name",
-            &format!("{}-container", metadata.app_name),
-            &metadata.app_name,
-        ])
-        .output()?;
+    let mut run = Command::new("docker");
+    run.args([
+        "run",
+        "-d",
+        "-p",
+        &format!("{}:{}", metadata.port, metadata.port),
+        "--name",
+        &format!("{}-container", metadata.app_name),
+    ]);
+
+    // `-e KEY` without a value makes docker read it from our environment, so
+    // parsed values (quotes, multi-line) arrive intact and never hit the disk
+    for (key, value) in project_env.all() {
+        run.args(["-e", key]).env(key, value);
+    }
+    let run_output = run.arg(&metadata.app_name).run()?;
 
     if !run_output.status.success() {
         return Err(io::Error::new(
@@ -642,12 +1892,12 @@ fn verify_docker_installation() -> io::Result<()> {
         "{}",
         GradientText::cyber("🔍 Verifying Docker installation...")
     );
-    match Command::new("docker").arg("--version").output() {
+    match Command::new("docker").arg("--version").run() {
         Ok(output) => {
             let version = String::from_utf8_lossy(&output.stdout);
             println!("{}", GradientText::success(&format!("✅ Docker installed: {}", version.trim())));
             // Check if Docker Desktop is running
-            match Command::new("docker").arg("info").output() {
+            match Command::new("docker").arg("info").run() {
                 Ok(output) if output.status.success() => {
                     println!("{}", GradientText::success("✅ Docker Desktop is running"));
                 }
@@ -667,9 +1917,12 @@ fn verify_docker_installation() -> io::Result<()> {
 }
 
 fn verify_container_status(container_id: &str) -> io::Result<()> {
+    if effects::dry_run() {
+        return Ok(());
+    }
     let output = Command::new("docker")
         .args(["inspect", "-f", "{{.State.Running}}", container_id])
-        .output()?;
+        .run()?;
 
     if String::from_utf8_lossy(&output.stdout).trim() != "true" {
         return Err(io::Error::new(
@@ -686,7 +1939,7 @@ fn verify_container_status(container_id: &str) -> io::Result<()> {
 
     let health_output = Command::new("docker")
         .args(["inspect", "-f", "{{.State.Health.Status}}", container_id])
-        .output()?;
+        .run()?;
 
     let health_status = String::from_utf8_lossy(&health_output.stdout)
         .trim()
@@ -706,37 +1959,192 @@ fn verify_container_status(container_id: &str) -> io::Result<()> {
 fn save_metadata(metadata: &AppMetadata) -> io::Result<()> {
     let json = serde_json::to_string_pretty(metadata)
         .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-    fs::write(".container-metadata.json", json)?;
+    artifacts::write("metadata.json", json)?;
     Ok(())
 }
 
-fn generate_kubernetes_manifests(
-    app_name: &str,
-    _app_type: &str,
-    port: &str,
-    replicas: i32,
-    namespace: &str,
-    mode: &str,
-) -> io::Result<()> {
-    let resources = if mode == "prod" {
-        r#"
-        resources:
-          requests:
-            cpu: "1"
-            memory: "2Gi"
-          limits:
-            cpu: "2"
-            memory: "4Gi""#
+fn load_project_config() -> io::Result<RustifyConfig> {
+    if !Path::new("rustify.json").exists() {
+        return Ok(RustifyConfig::default());
+    }
+
+    let content = fs::read_to_string("rustify.json")?;
+    serde_json::from_str(&content).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid rustify.json: {}", e),
+        )
+    })
+}
+
+fn generate_kubernetes_manifests(workload: &Workload) -> io::Result<Vec<&'static str>> {
+    let manifests = render_kubernetes_manifests(workload);
+    for (file, content) in &manifests {
+        artifacts::write(file, content)?;
+    }
+    Ok(manifests.into_iter().map(|(file, _)| file).collect())
+}
+
+/// Lifecycle hook holding a terminating pod for `seconds` before SIGTERM.
+/// With `sleep_action` the kubelet does the sleeping, so distroless images
+/// without a `sleep` binary work too; that needs Kubernetes 1.30 or newer.
+fn render_pre_stop(seconds: u32, sleep_action: bool) -> String {
+    if seconds == 0 {
+        return String::new();
+    }
+    if sleep_action {
+        format!(
+            r#"
+        lifecycle:
+          preStop:
+            sleep:
+              seconds: {}"#,
+            seconds
+        )
     } else {
+        format!(
+            r#"
+        lifecycle:
+          preStop:
+            exec:
+              command: ["sleep", "{}"]"#,
+            seconds
+        )
+    }
+}
+
+/// Whether the preStop hook can be the kubelet's `sleep` action on `server`.
+/// Versions rustify cannot read get `exec sleep`, which every cluster
+/// accepts.
+fn pre_stop_sleep_action(server: Option<ServerVersion>, availability: &AvailabilityPlan) -> bool {
+    let supported = server.is_some_and(|version| version.has_sleep_action());
+    if !supported && availability.pre_stop_sleep_seconds > 0 {
+        println!("ℹ️  The cluster predates Kubernetes 1.30, so preStop runs `sleep` inside the container");
+        println!("   Images without a sleep binary (distroless) stop without draining; set kubernetes.availability.preStopSleepSeconds to 0 for them");
+    }
+    supported
+}
+
+/// Requests and limits of the app container.
+fn container_resources(mode: &str, static_files: bool) -> (ResourceAmounts, ResourceAmounts) {
+    if static_files {
+        (ResourceAmounts::new("50m", "32Mi"), ResourceAmounts::new("500m", "128Mi"))
+    } else if mode == "prod" {
+        (ResourceAmounts::new("1", "2Gi"), ResourceAmounts::new("2", "4Gi"))
+    } else {
+        (ResourceAmounts::new("500m", "512Mi"), ResourceAmounts::new("1", "1Gi"))
+    }
+}
+
+/// The container's `resources` block, starting on a new line.
+fn render_resources(requests: &ResourceAmounts, limits: &ResourceAmounts) -> String {
+    format!(
         r#"
         resources:
           requests:
-            cpu: "500m"
-            memory: "512Mi"
+            cpu: "{}"
+            memory: "{}"
           limits:
-            cpu: "1"
-            memory: "1Gi""#
+            cpu: "{}"
+            memory: "{}""#,
+        requests.cpu, requests.memory, limits.cpu, limits.memory
+    )
+}
+
+/// Deployment, Service and the optional PriorityClass / PodDisruptionBudget,
+/// in apply order, keyed by the file name they are written to.
+fn render_kubernetes_manifests(workload: &Workload) -> Vec<(&'static str, String)> {
+    let Workload {
+        app_name,
+        port,
+        replicas,
+        namespace,
+        mode,
+        image,
+        env_sources,
+        availability,
+        static_files,
+        health_path,
+    } = *workload;
+    let image_ref = &image.reference;
+    let pull_policy = image.pull_policy;
+    let pull_secrets = match &image.pull_secret {
+        Some(secret) => format!(
+            r#"
+      imagePullSecrets:
+      - name: {secret}"#
+        ),
+        None => String::new(),
     };
+    let mut env_from = String::new();
+    if let Some(config_map) = &env_sources.config_map {
+        env_from.push_str(&format!(
+            r#"
+        - configMapRef:
+            name: {config_map}"#
+        ));
+    }
+    if let Some(secret) = &env_sources.secret {
+        env_from.push_str(&format!(
+            r#"
+        - secretRef:
+            name: {secret}"#
+        ));
+    }
+    if !env_from.is_empty() {
+        env_from = format!("\n        envFrom:{}", env_from);
+    }
+    let env_hash = &env_sources.hash;
+    let (requests, limits) = container_resources(mode, static_files);
+    let resources = render_resources(&requests, &limits);
+
+    let mut pod_extras = String::new();
+    if let Some(priority_class) = &availability.priority_class {
+        pod_extras.push_str(&format!("\n      priorityClassName: {}", priority_class));
+    }
+    if availability.anti_affinity {
+        pod_extras.push_str(&format!(
+            r#"
+      affinity:
+        podAntiAffinity:
+          preferredDuringSchedulingIgnoredDuringExecution:
+          - weight: 100
+            podAffinityTerm:
+              topologyKey: kubernetes.io/hostname
+              labelSelector:
+                matchLabels:
+                  app: {app_name}"#
+        ));
+    }
+    // The web server only needs a writable /tmp; Node apps get NODE_ENV
+    let (node_env, container_security, volumes) = if static_files {
+        (
+            String::new(),
+            r#"
+        securityContext:
+          readOnlyRootFilesystem: true
+          allowPrivilegeEscalation: false
+        volumeMounts:
+        - name: tmp
+          mountPath: /tmp"#,
+            r#"
+      volumes:
+      - name: tmp
+        emptyDir: {}"#,
+        )
+    } else {
+        (
+            format!(
+                r#"
+        - name: NODE_ENV
+          value: "{mode}""#
+            ),
+            "",
+            "",
+        )
+    };
+    let grace_period = availability.termination_grace_period_seconds;
+    let lifecycle = render_pre_stop(availability.pre_stop_sleep_seconds, availability.sleep_action);
 
     let deployment = format!(
         r#"apiVersion: apps/v1
@@ -761,23 +2169,23 @@ spec:
       annotations:
         prometheus.io/scrape: "true"
         prometheus.io/port: "{port}"
-    spec:
+        rustify.dev/env-hash: "{env_hash}"
+    spec:{pull_secrets}
+      terminationGracePeriodSeconds: {grace_period}{pod_extras}
       containers:
       - name: {app_name}
-        image: {app_name}:latest
-        imagePullPolicy: Never
+        image: {image_ref}
+        imagePullPolicy: {pull_policy}
         ports:
         - containerPort: {port}
           protocol: TCP
         env:
         - name: PORT
-          value: "{port}"
-        - name: NODE_ENV
-          value: "{mode}"
-        {resources}
+          value: "{port}"{node_env}{env_from}
+        {resources}{container_security}
         livenessProbe:
           httpGet:
-            path: /health
+            path: {health_path}
             port: {port}
           initialDelaySeconds: 15
           periodSeconds: 20
@@ -785,7 +2193,7 @@ spec:
           failureThreshold: 3
         readinessProbe:
           httpGet:
-            path: /health
+            path: {health_path}
             port: {port}
           initialDelaySeconds: 5
           periodSeconds: 10
@@ -794,10 +2202,10 @@ spec:
           failureThreshold: 3
         startupProbe:
           httpGet:
-            path: /health
+            path: {health_path}
             port: {port}
           failureThreshold: 30
-          periodSeconds: 10
+          periodSeconds: 10{lifecycle}{volumes}
       securityContext:
         runAsNonRoot: true
         runAsUser: 1000
@@ -810,8 +2218,6 @@ spec:
             app: {app_name}"#
     );
 
-    fs::write("k8s-deployment.yaml", deployment)?;
-
     // Generate service with session affinity
     let service = format!(
         r#"apiVersion: v1
@@ -835,38 +2241,145 @@ spec:
   type: ClusterIP"#
     );
 
-    fs::write("k8s-service.yaml", service)?;
+    // PriorityClass is applied first so the pods can reference it
+    let mut manifests = Vec::new();
+    if let (Some(name), Some(value)) = (&availability.priority_class, availability.priority_value) {
+        manifests.push((
+            "k8s-priorityclass.yaml",
+            format!(
+                r#"apiVersion: scheduling.k8s.io/v1
+kind: PriorityClass
+metadata:
+  name: {name}
+  labels:
+    app.kubernetes.io/managed-by: rustify
+value: {value}
+globalDefault: false
+description: "Priority for {app_name}""#
+            ),
+        ));
+    }
+    manifests.push(("k8s-deployment.yaml", deployment));
+    manifests.push(("k8s-service.yaml", service));
+
+    if let Some(min_available) = &availability.min_available {
+        let min_available = match min_available.parse::<u32>() {
+            Ok(count) => count.to_string(),
+            Err(_) => format!("\"{}\"", min_available),
+        };
+        manifests.push((
+            "k8s-pdb.yaml",
+            format!(
+                r#"apiVersion: policy/v1
+kind: PodDisruptionBudget
+metadata:
+  name: {app_name}-pdb
+  namespace: {namespace}
+  labels:
+    app.kubernetes.io/managed-by: rustify
+spec:
+  minAvailable: {min_available}
+  selector:
+    matchLabels:
+      app: {app_name}"#
+            ),
+        ));
+    }
+
+    manifests
+}
+
+fn apply_kubernetes_manifests(
+    app_name: &str,
+    namespace: &str,
+    manifests: &[&str],
+) -> io::Result<()> {
+    ensure_namespace(namespace)?;
+
+    // A budget left over from an earlier deploy would keep blocking drains
+    if !manifests.contains(&"k8s-pdb.yaml") {
+        kubectl()
+            .args([
+                "delete",
+                "pdb",
+                &format!("{}-pdb", app_name),
+                "-n",
+                namespace,
+                "--ignore-not-found",
+            ])
+            .run()?;
+    }
+
+    for manifest in manifests {
+        let output = kubectl()
+            .args(["apply", "-f"])
+            .arg(artifacts::path(manifest))
+            .args(["-n", namespace])
+            .run()?;
+
+        if !output.status.success() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!(
+                    "Failed to apply {}: {}",
+                    manifest,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ));
+        }
+    }
+
     Ok(())
 }
 
-fn apply_kubernetes_manifests(namespace: &str) -> io::Result<()> {
-    // Create namespace if it doesn't exist
-    Command::new("kubectl")
+/// Whether rustify owns `namespace`: it carries the managed-by label, or
+/// it does not exist yet and deploy will create it.
+fn owns_namespace(namespace: &str) -> io::Result<bool> {
+    let output = kubectl()
         .args([
-            "create",
+            "get",
             "namespace",
             namespace,
-            "--dry-run=client",
             "-o",
-            "yaml",
+            r"jsonpath={.metadata.labels.app\.kubernetes\.io/managed-by}",
         ])
-        .output()?;
-
-    // Apply manifests
-    Command::new("kubectl")
-        .args(["apply", "-f", "k8s-deployment.yaml"])
-        .output()?;
+        .run()?;
+    if !output.status.success() {
+        // Anything but a missing namespace (say, no RBAC to read it) is
+        // not proof of ownership
+        return Ok(String::from_utf8_lossy(&output.stderr).contains("NotFound"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim() == "rustify")
+}
 
-    Command::new("kubectl")
-        .args(["apply", "-f", "k8s-service.yaml"])
-        .output()?;
+// Creates the namespace when missing. Existing namespaces are left untouched
+// so rustify never claims ownership of one it didn't create.
+fn ensure_namespace(namespace: &str) -> io::Result<()> {
+    let existing = kubectl()
+        .args(["get", "namespace", namespace, "-o", "name"])
+        .run()?;
+    if existing.status.success() {
+        return Ok(());
+    }
 
-    Ok(())
-     }
+    println!("📦 Creating namespace: {}", namespace);
+    apply_manifest_from_stdin(&format!(
+        r#"apiVersion: v1
+kind: Namespace
+metadata:
+  name: {namespace}
+  labels:
+    app.kubernetes.io/managed-by: rustify
+"#
+    ))
+}
 fn wait_for_kubernetes_deployment(deployment_name: &str, namespace: &str) -> io::Result<()> {
+    if effects::dry_run() {
+        return Ok(());
+    }
     println!("⏳ Waiting for deployment to be ready...");
 
-    let status = Command::new("kubectl")
+    let status = kubectl()
         .args([
             "rollout",
             "status",
@@ -876,7 +2389,7 @@ fn wait_for_kubernetes_deployment(deployment_name: &str, namespace: &str) -> io:
             namespace,
             "--timeout=300s",
         ])
-        .status()?;
+        .run_status()?;
 
     if !status.success() {
         return Err(io::Error::new(
@@ -889,7 +2402,7 @@ fn wait_for_kubernetes_deployment(deployment_name: &str, namespace: &str) -> io:
 }
 
 fn update_pod_status(metadata: &mut AppMetadata, namespace: &str) -> io::Result<()> {
-    let output = Command::new("kubectl")
+    let output = kubectl()
         .args([
             "get",
             "pods",
@@ -900,7 +2413,7 @@ fn update_pod_status(metadata: &mut AppMetadata, namespace: &str) -> io::Result<
             "-o",
             "jsonpath={.items[*].status.phase}",
         ])
-        .output()?;
+        .run()?;
 
     metadata.kubernetes_metadata.pod_status = String::from_utf8_lossy(&output.stdout)
         .split_whitespace()
@@ -914,81 +2427,22 @@ fn create_kubernetes_ingress(
     app_name: &str,
     namespace: &str,
     port: &str,
-    mode: &str,
-) -> io::Result<String> {
+    config: &IngressConfig,
+) -> io::Result<()> {
     println!("🔧 Creating Kubernetes ingress...");
 
-    // Define TLS and annotations based on mode
-    let (tls_config, annotations) = if mode == "prod" {
-        (
-            r#"
-  tls:
-  - hosts:
-    - app.example.com
-    secretName: tls-secret"#,
-            r#"
-    kubernetes.io/ingress.class: nginx
-    cert-manager.io/cluster-issuer: letsencrypt-prod
-    nginx.ingress.kubernetes.io/ssl-redirect: "true"
-    nginx.ingress.kubernetes.io/force-ssl-redirect: "true"
-    nginx.ingress.kubernetes.io/proxy-body-size: "50m"
-    nginx.ingress.kubernetes.io/proxy-buffer-size: "128k""#,
-        )
-    } else {
-        (
-            "",
-            r#"
-    kubernetes.io/ingress.class: nginx
-    nginx.ingress.kubernetes.io/ssl-redirect: "false""#,
-        )
-    };
-
-    // Create ingress manifest
-    let ingress_manifest = format!(
-        r#"apiVersion: networking.k8s.io/v1
-kind: Ingress
-metadata:
-  name: {}-ingress
-  namespace: {}
-  annotations:{} 
-spec:{}
-  rules:
-  - host: {}
-    http:
-      paths:
-      - path: /
-        pathType: Prefix
-        backend:
-          service:
-            name: {}-service
-            port:
-              number: {}"#,
-        app_name,
-        namespace,
-        annotations,
-        tls_config,
-        if mode == "prod" {
-            "app.example.com"
-        } else {
-            app_name
-        },
-        app_name,
-        port
-    );
-
     // Write manifest to file
-    fs::write("k8s-ingress.yaml", ingress_manifest)?;
+    let path = artifacts::write(
+        "k8s-ingress.yaml",
+        render_ingress(app_name, namespace, port, config),
+    )?;
 
     // Apply ingress manifest
-    let output = Command::new("kubectl")
-        .args([
-            "apply",
-            "-f",
-            "k8s-ingress.yaml",
-            "-n",
-            namespace,
-        ])
-        .output()?;
+    let output = kubectl()
+        .args(["apply", "-f"])
+        .arg(&path)
+        .args(["-n", namespace])
+        .run()?;
 
     if !output.status.success() {
         let error = String::from_utf8_lossy(&output.stderr);
@@ -998,21 +2452,172 @@ spec:{}
         ));
     }
 
-    // Wait for ingress to be ready
-    println!("⏳ Waiting for ingress to be ready...");
-    thread::sleep(Duration::from_secs(5));
+    println!("✅ Ingress created for {}", config.hosts_for(app_name).join(", "));
+    Ok(())
+}
+
+fn render_ingress(app_name: &str, namespace: &str, port: &str, config: &IngressConfig) -> String {
+    let hosts = config.hosts_for(app_name);
+    let tls_secret = config.tls_secret_name(app_name);
+
+    let mut annotations = format!(
+        r#"
+    nginx.ingress.kubernetes.io/proxy-body-size: "{body}"
+    nginx.ingress.kubernetes.io/proxy-read-timeout: "{timeout}"
+    nginx.ingress.kubernetes.io/proxy-send-timeout: "{timeout}"
+    nginx.ingress.kubernetes.io/ssl-redirect: "{redirect}""#,
+        body = config.max_body_size,
+        timeout = config.timeout_seconds,
+        redirect = tls_secret.is_some(),
+    );
+    if let Some(issuer) = &config.cluster_issuer {
+        annotations.push_str(&format!("\n    cert-manager.io/cluster-issuer: {}", issuer));
+    }
+
+    let tls_config = match &tls_secret {
+        Some(secret) => {
+            let mut tls = String::from("\n  tls:\n  - hosts:");
+            for host in &hosts {
+                tls.push_str(&format!("\n    - \"{}\"", host));
+            }
+            tls.push_str(&format!("\n    secretName: {}", secret));
+            tls
+        }
+        None => String::new(),
+    };
+
+    let mut paths = String::new();
+    for path in config.paths_for(app_name, port) {
+        paths.push_str(&format!(
+            r#"
+      - path: {}
+        pathType: Prefix
+        backend:
+          service:
+            name: {}
+            port:
+              number: {}"#,
+            path.path,
+            path.service.as_deref().unwrap_or_default(),
+            path.port.as_deref().unwrap_or_default(),
+        ));
+    }
+
+    let mut rules = String::new();
+    for host in &hosts {
+        rules.push_str(&format!(
+            "\n  - host: \"{}\"\n    http:\n      paths:{}",
+            host, paths
+        ));
+    }
+
+    format!(
+        r#"apiVersion: networking.k8s.io/v1
+kind: Ingress
+metadata:
+  name: {app_name}-ingress
+  namespace: {namespace}
+  labels:
+    app.kubernetes.io/managed-by: rustify
+  annotations:{annotations}
+spec:
+  ingressClassName: {class}{tls_config}
+  rules:{rules}
+"#,
+        class = config.class_name,
+    )
+}
+
+/// Requests the app through the ingress controller and returns the URL that
+/// answered. The controller address comes from the ingress status, falling
+/// back to localhost for local clusters that publish ports 80/443 there.
+fn verify_ingress(
+    app_name: &str,
+    namespace: &str,
+    port: &str,
+    config: &IngressConfig,
+) -> io::Result<Option<String>> {
+    if effects::dry_run() {
+        return Ok(None);
+    }
+    println!("⏳ Verifying ingress...");
 
-    // Get ingress host
-    let host = if mode == "prod" {
-        "app.example.com".to_string()
+    let host = config.hosts_for(app_name).remove(0);
+    // Wildcard hosts are checked through a concrete subdomain
+    let request_host = host.replacen("*.", "www.", 1);
+    let (scheme, controller_port) = if config.tls_secret_name(app_name).is_some() {
+        ("https", "443")
     } else {
-        format!("{}.local", app_name)
+        ("http", "80")
     };
+    let path = config
+        .paths_for(app_name, port)
+        .into_iter()
+        .find(|p| p.service.as_deref() == Some(&format!("{}-service", app_name)))
+        .map(|p| p.path)
+        .unwrap_or_else(|| "/".to_string());
+    let url = format!("{}://{}{}", scheme, request_host, path);
+
+    for _ in 0..12 {
+        let status = kubectl()
+            .args([
+                "get",
+                "ingress",
+                &format!("{}-ingress", app_name),
+                "-n",
+                namespace,
+                "-o",
+                "jsonpath={.status.loadBalancer.ingress[0].ip}{.status.loadBalancer.ingress[0].hostname}",
+            ])
+            .run()?;
+        let address = match String::from_utf8_lossy(&status.stdout).trim() {
+            "" | "localhost" => "127.0.0.1".to_string(),
+            address => address.to_string(),
+        };
 
-    println!("✅ Ingress created successfully");
-    println!("🌐 Application will be available at: https://{}", host);
+        // --resolve sends the right Host header and SNI without touching DNS;
+        // -k because the certificate may come from the local development CA
+        let output = Command::new("curl")
+            .args([
+                "-sk",
+                "-o",
+                "/dev/null",
+                "-w",
+                "%{http_code}",
+                "--max-time",
+                "5",
+                "--resolve",
+                &format!("{}:{}:{}", request_host, controller_port, address),
+                &url,
+            ])
+            .run();
+
+        let code: u16 = match output {
+            Ok(output) => String::from_utf8_lossy(&output.stdout).trim().parse().unwrap_or(0),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                println!("⚠️  curl not found, skipping ingress verification");
+                return Ok(None);
+            }
+            Err(e) => return Err(e),
+        };
+
+        // The controller answers 404/502/503 until the route and endpoints exist
+        if (200..400).contains(&code) || code == 401 || code == 403 {
+            println!("✅ Ingress answered {} with HTTP {}", url, code);
+            if request_host.ends_with(".local") {
+                println!("   Add '{} {}' to /etc/hosts to open it in a browser", address, request_host);
+            }
+            return Ok(Some(url));
+        }
+        thread::sleep(Duration::from_secs(5));
+    }
 
-    Ok(host)
+    println!("⚠️  Could not reach {} through the ingress controller", url);
+    println!(
+        "   Use port forwarding instead: kubectl port-forward svc/{}-service {}:{} -n {}",
+        app_name, port, port, namespace
+    );
+    Ok(None)
 }
 
 fn print_kubernetes_status(metadata: &AppMetadata) {
@@ -1055,7 +2660,7 @@ fn print_kubernetes_status(metadata: &AppMetadata) {
     if let Some(host) = &metadata.kubernetes_metadata.ingress_host {
         println!(
             "{}",
-            GradientText::status(&format!("   • Ingress Host: {}", host))
+            GradientText::status(&format!("   • URL: {}", host))
         );
     }
 }
@@ -1065,7 +2670,7 @@ fn verify_infrastructure() -> io::Result<()> {
 
     // Check Docker
     println!("\n📦 Checking Docker...");
-    match Command::new("docker").arg("--version").output() {
+    match Command::new("docker").arg("--version").run() {
         Ok(output) => {
             let version = String::from_utf8_lossy(&output.stdout);
             println!(
@@ -1074,7 +2679,7 @@ fn verify_infrastructure() -> io::Result<()> {
             );
 
             // Check if Docker daemon is running
-            match Command::new("docker").args(["ps"]).output() {
+            match Command::new("docker").args(["ps"]).run() {
                 Ok(_) => println!(" Docker daemon is running"),
                 Err(_) => return Err(io::Error::new(
                     io::ErrorKind::Other,
@@ -1093,31 +2698,26 @@ fn verify_infrastructure() -> io::Result<()> {
     // Check Kubernetes context
     println!("\n☸️  Checking Kubernetes...");
 
-    // Ensure we're using docker-desktop context
-    Command::new("kubectl")
-        .args(["config", "use-context", "docker-desktop"])
-        .output()?;
-
     // Check kubectl installation and connection
-    match Command::new("kubectl")
+    match kubectl()
         .args(["cluster-info", "dump"])
-        .output()
+        .run()
     {
         Ok(output) => {
             if output.status.success() {
-                println!(" Connected to Kubernetes cluster (docker-desktop)");
+                println!(" Connected to Kubernetes cluster");
 
                 // Verify core components
-                let core_namespaces = Command::new("kubectl")
+                let core_namespaces = kubectl()
                     .args(["get", "namespaces"])
-                    .output()?;
+                    .run()?;
                 println!("\n📊 Available Namespaces:");
                 println!("{}", String::from_utf8_lossy(&core_namespaces.stdout));
 
                 // Check if nginx ingress controller is installed
-                let ingress_pods = Command::new("kubectl")
+                let ingress_pods = kubectl()
                     .args(["get", "pods", "-n", "ingress-nginx"])
-                    .output();
+                    .run();
 
                 if ingress_pods.is_err() {
                     println!("\n⚠️  Nginx Ingress Controller not found. Installing...");
@@ -1143,17 +2743,17 @@ fn verify_infrastructure() -> io::Result<()> {
 
 fn install_nginx_ingress() -> io::Result<()> {
     // Add Nginx Ingress Controller repository
-    Command::new("kubectl")
+    kubectl()
         .args([
             "apply",
             "-f",
             "https://raw.githubusercontent.com/kubernetes/ingress-nginx/controller-v1.8.2/deploy/static/provider/cloud/deploy.yaml"
         ])
-        .output()?;
+        .run()?;
 
     // Wait for the ingress controller to be ready
     println!("⏳ Waiting for Nginx Ingress Controller to be ready...");
-    Command::new("kubectl")
+    kubectl()
         .args([
             "wait",
             "--namespace",
@@ -1163,73 +2763,346 @@ fn install_nginx_ingress() -> io::Result<()> {
             "--selector=app.kubernetes.io/component=controller",
             "--timeout=300s",
         ])
-        .output()?;
+        .run()?;
 
     println!("✅ Nginx Ingress Controller installed successfully");
     Ok(())
 }
 
-fn prepare_kubernetes_deployment(app_name: &str, _mode: &str) -> io::Result<()> {
-    // Tag the image for Kubernetes
-    Command::new("docker")
-        .args([
-            "tag",
-            &format!("rust-dockerize-{}", app_name),
-            &format!("{}:latest", app_name),
-        ])
-        .output()?;
+fn prepare_kubernetes_deployment(
+    app_name: &str,
+    namespace: &str,
+    cluster: &ClusterKind,
+    config: &RustifyConfig,
+) -> io::Result<ClusterImage> {
+    let local_image = format!("{}:latest", app_name);
+
+    if let Some(registry) = &config.registry {
+        return push_to_registry(app_name, namespace, &local_image, registry);
+    }
+
+    // An explicitly configured registry wins over loading into the nodes
+    if let Some(registry) = &config.kubernetes.local_registry {
+        let reference = format!("{}/{}", registry.trim_end_matches('/'), local_image);
+        println!("📤 Pushing {} to local registry {}...", local_image, registry);
+        run_image_command("docker", &["tag", &local_image, &reference])?;
+        run_image_command("docker", &["push", &reference])?;
+        println!("✅ Image pushed as {}", reference);
+        return Ok(ClusterImage {
+            reference,
+            pull_policy: "IfNotPresent",
+            pull_secret: None,
+        });
+    }
+
+    match cluster {
+        ClusterKind::DockerDesktop => {
+            // Docker Desktop's cluster shares the local image store
+        }
+        ClusterKind::Kind(name) => {
+            println!("📦 Loading {} into kind cluster '{}'...", local_image, name);
+            run_image_command("kind", &["load", "docker-image", &local_image, "--name", name])?;
+        }
+        ClusterKind::K3d(name) => {
+            println!("📦 Importing {} into k3d cluster '{}'...", local_image, name);
+            run_image_command("k3d", &["image", "import", &local_image, "-c", name])?;
+        }
+        ClusterKind::Minikube(profile) => {
+            println!("📦 Loading {} into minikube profile '{}'...", local_image, profile);
+            run_image_command("minikube", &["image", "load", &local_image, "-p", profile])?;
+        }
+        ClusterKind::Other(context) => {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!(
+                    "Context '{}' is not a local cluster, so it cannot use locally built images. \
+                     Set kubernetes.localRegistry in rustify.json to a registry the cluster can pull from.",
+                    context
+                ),
+            ));
+        }
+    }
+
+    println!("✅ Docker image available to the cluster");
+    Ok(ClusterImage {
+        reference: local_image,
+        pull_policy: "Never",
+        pull_secret: None,
+    })
+}
+
+/// The image `prepare_kubernetes_deployment` would hand the Deployment,
+/// worked out without building, pushing or creating anything.
+fn planned_cluster_image(app_name: &str, config: &RustifyConfig) -> io::Result<ClusterImage> {
+    let local_image = format!("{}:latest", app_name);
+
+    if let Some(registry) = &config.registry {
+        let pull_secret = registry::lookup_credentials(&registry.url)?
+            .map(|_| format!("{}-registry-credentials", app_name));
+        return Ok(ClusterImage {
+            reference: format!(
+                "{}/{}:{}",
+                registry.url.trim_end_matches('/'),
+                app_name,
+                registry.tag
+            ),
+            pull_policy: "IfNotPresent",
+            pull_secret,
+        });
+    }
+
+    if let Some(registry) = &config.kubernetes.local_registry {
+        return Ok(ClusterImage {
+            reference: format!("{}/{}", registry.trim_end_matches('/'), local_image),
+            pull_policy: "IfNotPresent",
+            pull_secret: None,
+        });
+    }
+
+    Ok(ClusterImage {
+        reference: local_image,
+        pull_policy: "Never",
+        pull_secret: None,
+    })
+}
+
+fn push_to_registry(
+    app_name: &str,
+    namespace: &str,
+    local_image: &str,
+    registry: &RegistryConfig,
+) -> io::Result<ClusterImage> {
+    let reference = format!(
+        "{}/{}:{}",
+        registry.url.trim_end_matches('/'),
+        app_name,
+        registry.tag
+    );
+    let host = registry::registry_host(&registry.url);
+
+    println!("📤 Pushing {} to {}...", reference, host);
+    run_image_command("docker", &["tag", local_image, &reference])?;
+    run_image_command("docker", &["push", &reference]).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("{}\n💡 Run 'docker login {}' and try again", e, host),
+        )
+    })?;
+    println!("✅ Image pushed as {}", reference);
+
+    // Registries without auth (e.g. a local registry:2) need no pull secret
+    let pull_secret = match registry::lookup_credentials(&registry.url)? {
+        Some(credentials) => {
+            let name = format!("{}-registry-credentials", app_name);
+            let manifest =
+                registry::pull_secret_manifest(&name, namespace, &registry.url, &credentials);
+            apply_manifest_from_stdin(&manifest)?;
+            println!("🔑 Image pull secret '{}' created", name);
+            Some(name)
+        }
+        None => {
+            println!("ℹ️  No credentials for {} in Docker config, skipping pull secret", host);
+            None
+        }
+    };
+
+    Ok(ClusterImage {
+        reference,
+        pull_policy: "IfNotPresent",
+        pull_secret,
+    })
+}
+
+// Pipes a manifest straight into kubectl so credentials never touch the disk
+fn apply_manifest_from_stdin(manifest: &str) -> io::Result<()> {
+    let output = kubectl()
+        .args(["apply", "-f", "-"])
+        .run_with_input(manifest.as_bytes())?;
+
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "kubectl apply failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ));
+    }
 
-    println!("✅ Docker image tagged for Kubernetes");
     Ok(())
 }
 
-fn create_namespace_with_quotas(namespace: &str, mode: &str) -> io::Result<()> {
-    // Create namespace with resource quotas based on mode
-    let quota_spec = if mode == "prod" {
-        r#"
-apiVersion: v1
+/// Makes sure each named `kubernetes.io/tls` Secret holds a certificate from
+/// the local CA covering `hosts`, reissuing it when missing or near expiry.
+fn install_tls_secrets(
+    namespace: &str,
+    secret_names: &[&str],
+    hosts: &[String],
+    config: &TlsConfig,
+) -> io::Result<()> {
+    let ca = certs::CertificateAuthority::load_or_create(certs::ca_root()?)?;
+    let ca_pem = ca.cert_pem()?;
+
+    for name in secret_names {
+        let existing = kubectl()
+            .args([
+                "get",
+                "secret",
+                name,
+                "-n",
+                namespace,
+                "-o",
+                "jsonpath={.data.tls\\.crt}",
+            ])
+            .run()?;
+        let current = if existing.status.success() {
+            openssl::base64::decode_block(String::from_utf8_lossy(&existing.stdout).trim())
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+
+        if !current.is_empty() && !ca.needs_rotation(&current, hosts, config.renew_before_days)? {
+            println!("🔒 TLS secret {} is up to date", name);
+            continue;
+        }
+
+        let cert = ca.issue(hosts, config.valid_days)?;
+        apply_manifest_from_stdin(&certs::tls_secret_manifest(name, namespace, &cert, &ca_pem))?;
+        println!(
+            "🔒 Issued TLS secret {} for {} (valid until {})",
+            name,
+            hosts.join(", "),
+            cert.not_after
+        );
+    }
+
+    Ok(())
+}
+
+fn run_image_command(program: &str, args: &[&str]) -> io::Result<()> {
+    let output = Command::new(program).args(args).run().map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Failed to run {}: {}. Is it installed and in PATH?", program, e),
+        )
+    })?;
+
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "{} {} failed: {}",
+                program,
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+fn create_namespace_with_quotas(
+    namespace: &str,
+    mode: &str,
+    config: &RustifyConfig,
+    static_files: bool,
+) -> io::Result<()> {
+    // A quota in a shared namespace such as `default` would throttle
+    // workloads rustify knows nothing about
+    if !owns_namespace(namespace)? {
+        println!(
+            "ℹ️  Namespace {} is not managed by rustify; leaving its quota and limits alone",
+            namespace
+        );
+        return ensure_namespace(namespace);
+    }
+    let path = artifacts::write("quota.yaml", render_quota(namespace, mode, config, static_files))?;
+
+    ensure_namespace(namespace)?;
+
+    let output = kubectl()
+        .args(["apply", "-f"])
+        .arg(&path)
+        .args(["-n", namespace])
+        .run()?;
+
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "Failed to apply quota: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+/// ResourceQuota and LimitRange for the app namespace. The default quota
+/// fits `peak_pods` of the app, so neither the autoscaler nor a rollout is
+/// blocked by it.
+fn render_quota(namespace: &str, mode: &str, config: &RustifyConfig, static_files: bool) -> String {
+    let quota = config
+        .kubernetes
+        .quota
+        .clone()
+        .unwrap_or_else(|| QuotaConfig::for_pods(mode, static_files, peak_pods(&config.scaling)));
+    let limits = config
+        .kubernetes
+        .limit_range
+        .clone()
+        .unwrap_or_else(|| LimitRangeConfig::for_mode(mode));
+
+    // The LimitRange gives containers without explicit resources a default,
+    // which the quota requires once it constrains limits
+    format!(
+        r#"apiVersion: v1
 kind: ResourceQuota
 metadata:
   name: compute-quota
+  namespace: {namespace}
+  labels:
+    app.kubernetes.io/managed-by: rustify
 spec:
   hard:
-    requests.cpu: "4"
-    requests.memory: 8Gi
-    limits.cpu: "8"
-    limits.memory: 16Gi""#
-    } else {
-        r#"
+    requests.cpu: "{}"
+    requests.memory: {}
+    limits.cpu: "{}"
+    limits.memory: {}
+---
 apiVersion: v1
-kind: ResourceQuota
+kind: LimitRange
 metadata:
-  name: compute-quota
+  name: compute-limits
+  namespace: {namespace}
+  labels:
+    app.kubernetes.io/managed-by: rustify
 spec:
-  hard:
-    requests.cpu: "1"
-    requests.memory: 2Gi
-    limits.cpu: "2"
-    limits.memory: 4Gi""#
-    };
-
-    fs::write("quota.yaml", quota_spec)?;
-
-    // Create namespace and apply quota
-    Command::new("kubectl")
-        .args([
-            "create",
-            "namespace",
-            namespace,
-            "--dry-run=client",
-            "-o",
-            "yaml",
-        ])
-        .output()?;
-
-    Command::new("kubectl")
-        .args(["apply", "-f", "quota.yaml", "-n", namespace])
-        .output()?;
-
-        Ok(())
+  limits:
+  - type: Container
+    defaultRequest:
+      cpu: "{}"
+      memory: {}
+    default:
+      cpu: "{}"
+      memory: {}
+    max:
+      cpu: "{}"
+      memory: {}"#,
+        quota.requests.cpu,
+        quota.requests.memory,
+        quota.limits.cpu,
+        quota.limits.memory,
+        limits.default_request.cpu,
+        limits.default_request.memory,
+        limits.default_limit.cpu,
+        limits.default_limit.memory,
+        limits.max.cpu,
+        limits.max.memory,
+    )
 }
 
 async fn setup_monitoring(app_name: &str, namespace: &str, mode: &str) -> io::Result<()> {
@@ -1263,39 +3136,30 @@ async fn setup_monitoring(app_name: &str, namespace: &str, mode: &str) -> io::Re
       }
     }"#;
 
-    fs::write("prometheus.yml", prometheus_config)?;
-    fs::write("grafana-dashboard.json", grafana_dashboard)?;
+    artifacts::write("prometheus.yml", prometheus_config)?;
+    artifacts::write("grafana-dashboard.json", grafana_dashboard)?;
     Ok(())
 }
 
-fn setup_autoscaling(app_name: &str, namespace: &str, min_replicas: i32) -> io::Result<()> {
-    let hpa_config = format!(
-        r#"apiVersion: autoscaling/v2
-kind: HorizontalPodAutoscaler
-metadata:
-  name: {app_name}-hpa
-  namespace: {namespace}
-spec:
-  scaleTargetRef:
-    apiVersion: apps/v1
-    kind: Deployment
-    name: {app_name}-deployment
-  minReplicas: {min_replicas}
-  maxReplicas: 10
-  metrics:
-  - type: Resource
-    resource:
-      name: cpu
-      target:
-        type: Utilization
-        averageUtilization: 70"#
-    );
+fn setup_autoscaling(app_name: &str, namespace: &str, scaling: &ScalingConfig) -> io::Result<()> {
+    autoscale::validate(scaling)?;
+    let path = artifacts::write("hpa.yaml", autoscale::manifest(app_name, namespace, scaling))?;
 
-    fs::write("hpa.yaml", hpa_config)?;
-    Command::new("kubectl")
-        .args(["apply", "-f", "hpa.yaml"])
-        .output()?;
+    let output = kubectl().args(["apply", "-f"]).arg(&path).run()?;
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "Failed to apply autoscaler: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ));
+    }
 
+    println!(
+        "📈 Autoscaling {} between {} and {} replicas",
+        app_name, scaling.min_instances, scaling.max_instances
+    );
     Ok(())
 }
 
@@ -1303,7 +3167,7 @@ fn cleanup_deployment(app_name: &str, namespace: &str) -> io::Result<()> {
     println!("🧹 Cleaning up old deployments...");
 
     // Delete old pods
-    Command::new("kubectl")
+    kubectl()
         .args([
             "delete",
             "pods",
@@ -1314,10 +3178,10 @@ fn cleanup_deployment(app_name: &str, namespace: &str) -> io::Result<()> {
             "--field-selector",
             "status.phase=Succeeded",
         ])
-        .output()?;
+        .run()?;
 
     // Delete failed pods
-    Command::new("kubectl")
+    kubectl()
         .args([
             "delete",
             "pods",
@@ -1328,7 +3192,7 @@ fn cleanup_deployment(app_name: &str, namespace: &str) -> io::Result<()> {
             "--field-selector",
             "status.phase=Failed",
         ])
-        .output()?;
+        .run()?;
 
     println!("✅ Cleanup completed");
     Ok(())
@@ -1338,39 +3202,33 @@ fn check_kubernetes_connection() -> io::Result<()> {
     println!("🔍 Verifying Kubernetes connection...");
 
     // First, check if Docker Desktop is running
-    if let Err(_) = Command::new("docker").arg("info").output() {
+    if let Err(_) = Command::new("docker").arg("info").run() {
         return Err(io::Error::new(
             io::ErrorKind::Other,
             "Docker Desktop is not running. Please start Docker Desktop first.",
         ));
     }
 
-    // Try to get the current context
-    let context_output = Command::new("kubectl")
-        .args(["config", "current-context"])
-        .output()?;
-
-    if !context_output.status.success() {
-        // If no context is set, try to set docker-desktop context
-        println!("⚠️  No Kubernetes context set. Attempting to set docker-desktop context...");
+    // Never switch the global context; just report what could be selected
+    let has_context = KUBE_CONTEXT.get().is_some()
+        || kubectl()
+            .args(["config", "current-context"])
+            .run()?
+            .status
+            .success();
 
-        // List available contexts
-        let contexts_output = Command::new("kubectl")
+    if !has_context {
+        let contexts_output = kubectl()
             .args(["config", "get-contexts", "-o", "name"])
-            .output()?;
+            .run()?;
 
-        let contexts = String::from_utf8_lossy(&contexts_output.stdout);
-
-        if contexts.contains("docker-desktop") {
-            Command::new("kubectl")
-                .args(["config", "use-context", "docker-desktop"])
-                .output()?;
-        } else {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "Docker Desktop Kubernetes is not enabled. Please enable it in Docker Desktop settings."
-            ));
-        }
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "No Kubernetes context set. Select one with --context. Available contexts:\n{}",
+                String::from_utf8_lossy(&contexts_output.stdout).trim()
+            ),
+        ));
     }
 
     // Verify cluster connectivity with retry
@@ -1380,7 +3238,7 @@ fn check_kubernetes_connection() -> io::Result<()> {
             std::thread::sleep(std::time::Duration::from_secs(5));
         }
 
-        match Command::new("kubectl").args(["cluster-info"]).output() {
+        match kubectl().args(["cluster-info"]).run() {
             Ok(output) if output.status.success() => {
                 println!("✅ Successfully connected to Kubernetes cluster");
                 return Ok(());
@@ -1406,7 +3264,7 @@ fn check_kubernetes_status() -> io::Result<()> {
 
     // Check Docker Desktop status
     println!("\n🐳 Docker Desktop status:");
-    match Command::new("docker").arg("info").output() {
+    match Command::new("docker").arg("info").run() {
         Ok(output) => {
             if output.status.success() {
                 println!("✅ Docker Desktop is running");
@@ -1419,15 +3277,15 @@ fn check_kubernetes_status() -> io::Result<()> {
 
     // Check Kubernetes status
     println!("\n☸️  Kubernetes status:");
-    match Command::new("kubectl").args(["cluster-info"]).output() {
+    match kubectl().args(["cluster-info"]).run() {
         Ok(output) => {
             if output.status.success() {
                 println!("✅ Kubernetes is running");
 
                 // Show component status
-                if let Ok(components) = Command::new("kubectl")
+                if let Ok(components) = kubectl()
                     .args(["get", "componentstatuses", "-o", "wide"])
-                    .output()
+                    .run()
                  {
                     println!("\nComponent Status:");
                     println!("{}", String::from_utf8_lossy(&components.stdout));
@@ -1463,12 +3321,107 @@ fn generate_haproxy_config(_mode: &str) -> io::Result<()> {
     "#
     );
 
-    fs::write("haproxy.cfg", config)?;
+    artifacts::write("haproxy.cfg", config)?;
+    Ok(())
+}
+
+fn setup_network_policies(
+    app_name: &str,
+    namespace: &str,
+    port: &str,
+    config: &KubernetesConfig,
+) -> io::Result<()> {
+    let path = artifacts::write(
+        "network-policy.yaml",
+        render_network_policy(
+            app_name,
+            namespace,
+            port,
+            &config.ingress_namespace,
+            config.egress.as_deref(),
+        ),
+    )?;
+
+    let output = kubectl()
+        .args(["apply", "-f"])
+        .arg(&path)
+        .args(["-n", namespace])
+        .run()?;
+
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "Failed to apply network policy: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ));
+    }
+
     Ok(())
 }
 
-fn setup_network_policies(app_name: &str, namespace: &str) -> io::Result<()> {
-    let network_policy = format!(
+/// Admits traffic on the app port from its namespace and the ingress
+/// controller. Egress always allows DNS; the rest is open unless `egress`
+/// lists the allowed destinations.
+fn render_network_policy(
+    app_name: &str,
+    namespace: &str,
+    port: &str,
+    ingress_namespace: &str,
+    egress: Option<&[EgressRule]>,
+) -> String {
+    let mut egress_rules = String::from(
+        r#"
+  - to:
+    - namespaceSelector:
+        matchLabels:
+          kubernetes.io/metadata.name: kube-system
+    ports:
+    - protocol: UDP
+      port: 53
+    - protocol: TCP
+      port: 53"#,
+    );
+    match egress {
+        None => egress_rules.push_str("\n  - {}"),
+        Some(rules) => {
+            for rule in rules {
+                let mut to = String::new();
+                if let Some(namespace) = &rule.namespace {
+                    to.push_str(&format!(
+                        r#"
+    - namespaceSelector:
+        matchLabels:
+          kubernetes.io/metadata.name: {namespace}"#
+                    ));
+                }
+                if let Some(cidr) = &rule.cidr {
+                    to.push_str(&format!(
+                        r#"
+    - ipBlock:
+        cidr: {cidr}"#
+                    ));
+                }
+                let mut ports = String::new();
+                for port in &rule.ports {
+                    ports.push_str(&format!(
+                        r#"
+    - protocol: TCP
+      port: {port}"#
+                    ));
+                }
+                egress_rules.push_str(&match (to.is_empty(), ports.is_empty()) {
+                    (true, true) => "\n  - {}".to_string(),
+                    (false, true) => format!("\n  - to:{}", to),
+                    (true, false) => format!("\n  - ports:{}", ports),
+                    (false, false) => format!("\n  - to:{}\n    ports:{}", to, ports),
+                });
+            }
+        }
+    }
+
+    format!(
         r#"apiVersion: networking.k8s.io/v1
 kind: NetworkPolicy
 metadata:
@@ -1485,33 +3438,22 @@ spec:
   - from:
     - namespaceSelector:
         matchLabels:
-          name: {namespace}
-    ports:
-    - protocol: TCP
-      port: 80
-  egress:
-  - to:
+          kubernetes.io/metadata.name: {namespace}
     - namespaceSelector:
         matchLabels:
-          name: kube-system
+          kubernetes.io/metadata.name: {ingress_namespace}
     ports:
-    - protocol: TCP"#
-    );
-
-    fs::write("network-policy.yaml", network_policy)?;
-
-    Command::new("kubectl")
-        .args(["apply", "-f", "network-policy.yaml"])
-        .output()?;
-
-    Ok(())
+    - protocol: TCP
+      port: {port}
+  egress:{egress_rules}"#
+    )
 }
 
-fn verify_kubernetes_setup() -> io::Result<()> {
+fn verify_kubernetes_setup(target: &KubeTarget) -> io::Result<(ClusterKind, Option<ServerVersion>)> {
     println!("🔍 Verifying Kubernetes setup...");
 
     // Step 1: Check if kubectl is installed
-    match Command::new("kubectl").arg("version").output() {
+    match kubectl().arg("version").run() {
         Ok(_) => println!("✅ kubectl is installed"),
         Err(_) => {
             return Err(io::Error::new(
@@ -1521,49 +3463,34 @@ fn verify_kubernetes_setup() -> io::Result<()> {
         }
     }
 
-    // Step 2: Ensure Docker Desktop is running with Kubernetes
-    let docker_status = Command::new("docker").arg("info").output()?;
+    // Step 2: Ensure the Docker daemon is running, images are built locally
+    let docker_status = Command::new("docker").arg("info").run()?;
     if !docker_status.status.success() {
         return Err(io::Error::new(
             io::ErrorKind::Other,
-            "Docker Desktop is not running. Please start Docker Desktop first.",
-        ));
-    }
-
-    // Step 3: Enable Kubernetes if not already enabled
-    println!("⏳ Checking Kubernetes status in Docker Desktop...");
-    let k8s_context = Command::new("kubectl")
-        .args(["config", "get-contexts"])
-        .output()?;
-
-    if !String::from_utf8_lossy(&k8s_context.stdout).contains("docker-desktop") {
-        println!("⚠️ Kubernetes is not enabled in Docker Desktop");
-        println!("🔄 Please enable Kubernetes in Docker Desktop:");
-        println!("1. Open Docker Desktop");
-        println!("2. Go to Settings/Preferences");
-        println!("3. Select 'Kubernetes'");
-        println!("4. Check 'Enable Kubernetes'");
-        println!("5. Click 'Apply & Restart'");
-        return Err(io::Error::new(
-            io::ErrorKind::Other,
-            "Kubernetes is not enabled in Docker Desktop",
+            "Docker is not running. Please start Docker first.",
         ));
     }
 
-    // Step 4: Switch to docker-desktop context
-    Command::new("kubectl")
-        .args(["config", "use-context", "docker-desktop"])
-        .output()?;
+    // Step 3: Make sure the selected context answers and detect its cluster kind
+    let server = verify_cluster_api(target)?;
+    let cluster = detect_cluster_kind(&target.context)?;
+    println!(
+        "☸️  Using context '{}' ({}), namespace '{}'",
+        target.context,
+        describe_cluster_kind(&cluster),
+        target.namespace
+    );
 
-    // Step 5: Wait for Kubernetes to be ready
+    // Step 4: Wait for Kubernetes to be ready
     println!("⏳ Waiting for Kubernetes to be ready...");
     for i in 0..30 {
-        match Command::new("kubectl").args(["get", "nodes"]).output() {
+        match kubectl().args(["get", "nodes"]).run() {
             Ok(output) if output.status.success() => {
                 let nodes = String::from_utf8_lossy(&output.stdout);
                 if nodes.contains("Ready") {
                     println!("✅ Kubernetes is ready!");
-                    return Ok(());
+                    return Ok((cluster, server));
                 }
             }
             _ if i == 29 => {
@@ -1580,9 +3507,230 @@ fn verify_kubernetes_setup() -> io::Result<()> {
         }
     }
 
+    Ok((cluster, server))
+}
+
+fn resolve_kube_target(config: &KubernetesConfig) -> io::Result<KubeTarget> {
+    // Kubeconfig::read merges every file listed in KUBECONFIG, like kubectl
+    let kubeconfig = kube::config::Kubeconfig::read().map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Cannot read kubeconfig: {}", e),
+        )
+    })?;
+
+    let context = match config.context.clone().or_else(|| kubeconfig.current_context.clone()) {
+        Some(context) if !context.is_empty() => context,
+        _ => {
+            println!("⚠️ No Kubernetes context is set");
+            println!("🔄 Pass --context, or create a cluster first, for example:");
+            println!("1. kind create cluster");
+            println!("2. k3d cluster create");
+            println!("3. minikube start");
+            println!("4. Enable Kubernetes in Docker Desktop settings");
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "No Kubernetes context selected",
+            ));
+        }
+    };
+
+    if !kubeconfig.contexts.iter().any(|c| c.name == context) {
+        let available: Vec<&str> = kubeconfig.contexts.iter().map(|c| c.name.as_str()).collect();
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "Context '{}' not found in kubeconfig. Available: {}",
+                context,
+                available.join(", ")
+            ),
+        ));
+    }
+
+    let options = kube::config::KubeConfigOptions {
+        context: Some(context.clone()),
+        ..Default::default()
+    };
+    let client_config = tokio::runtime::Runtime::new()?
+        .block_on(kube::Config::from_custom_kubeconfig(kubeconfig, &options))
+        .map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                format!("Invalid kubeconfig for context '{}': {}", context, e),
+            )
+        })?;
+
+    let namespace = config
+        .namespace
+        .clone()
+        .unwrap_or_else(|| client_config.default_namespace.clone());
+
+    Ok(KubeTarget {
+        context,
+        namespace,
+        client_config,
+    })
+}
+
+/// `major.minor` of the API server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct ServerVersion {
+    major: u32,
+    minor: u32,
+}
+
+impl ServerVersion {
+    /// Managed clusters report minors such as `30+`; `None` when the
+    /// version cannot be read.
+    fn parse(info: &k8s_openapi::apimachinery::pkg::version::Info) -> Option<ServerVersion> {
+        let number = |text: &str| text.trim_end_matches('+').parse().ok();
+        if let (Some(major), Some(minor)) = (number(&info.major), number(&info.minor)) {
+            return Some(ServerVersion { major, minor });
+        }
+        // `v1.30.2-gke.1587003`
+        let mut parts = info.git_version.trim_start_matches('v').split('.');
+        Some(ServerVersion {
+            major: number(parts.next()?)?,
+            minor: number(parts.next()?)?,
+        })
+    }
+
+    fn has_sleep_action(&self) -> bool {
+        *self >= ServerVersion { major: 1, minor: 30 }
+    }
+}
+
+/// Connects to the API server of `target`. Returns its version, or `None`
+/// when the server reports one rustify cannot read.
+fn verify_cluster_api(target: &KubeTarget) -> io::Result<Option<ServerVersion>> {
+    let client_config = target.client_config.clone();
+    let version = tokio::runtime::Runtime::new()?
+        .block_on(async move {
+            let client = kube::Client::try_from(client_config)?;
+            client.apiserver_version().await
+        })
+        .map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                format!(
+                    "Cannot reach the API server of context '{}': {}",
+                    target.context, e
+                ),
+            )
+        })?;
+
+    println!("✅ Connected to Kubernetes {}", version.git_version);
+    Ok(ServerVersion::parse(&version))
+}
+
+// Asks for the context name to be typed back before touching anything that
+// matches one of the configured production patterns
+fn confirm_production_context(
+    target: &KubeTarget,
+    config: &KubernetesConfig,
+    assume_yes: bool,
+) -> io::Result<()> {
+    let pattern = match config
+        .production_contexts
+        .iter()
+        .find(|p| matches_wildcard(p, &target.context))
+    {
+        Some(pattern) => pattern,
+        None => return Ok(()),
+    };
+
+    println!(
+        "{}",
+        GradientText::warning(&format!(
+            "⚠️  Context '{}' matches production pattern '{}'",
+            target.context, pattern
+        ))
+    );
+
+    if assume_yes {
+        println!("➡️  Continuing because --yes was given");
+        return Ok(());
+    }
+
+    if !std::io::IsTerminal::is_terminal(&io::stdin()) {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "Refusing to deploy to a production context without confirmation. Re-run with --yes.",
+        ));
+    }
+
+    print!("Type the context name to continue: ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+
+    if answer.trim() != target.context {
+        return Err(io::Error::new(
+            io::ErrorKind::Interrupted,
+            "Deployment cancelled",
+        ));
+    }
+
     Ok(())
 }
 
+fn matches_wildcard(pattern: &str, text: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == text,
+        Some((prefix, rest)) => match text.strip_prefix(prefix) {
+            Some(remainder) => remainder
+                .char_indices()
+                .map(|(i, _)| i)
+                .chain(std::iter::once(remainder.len()))
+                .any(|i| matches_wildcard(rest, &remainder[i..])),
+            None => false,
+        },
+    }
+}
+
+fn detect_cluster_kind(context: &str) -> io::Result<ClusterKind> {
+    if context == "docker-desktop" || context == "docker-for-desktop" {
+        return Ok(ClusterKind::DockerDesktop);
+    }
+    if let Some(name) = context.strip_prefix("kind-") {
+        return Ok(ClusterKind::Kind(name.to_string()));
+    }
+    if let Some(name) = context.strip_prefix("k3d-") {
+        return Ok(ClusterKind::K3d(name.to_string()));
+    }
+
+    // minikube names the context after its profile, so look for the
+    // provider extension it writes into the cluster entry as well
+    let provider = kubectl()
+        .args([
+            "config",
+            "view",
+            "--minify",
+            "--context",
+            context,
+            "-o",
+            "jsonpath={.clusters[0].cluster.extensions[*].extension.provider}",
+        ])
+        .run()?;
+    if context == "minikube"
+        || String::from_utf8_lossy(&provider.stdout).contains("minikube.sigs.k8s.io")
+    {
+        return Ok(ClusterKind::Minikube(context.to_string()));
+    }
+
+    Ok(ClusterKind::Other(context.to_string()))
+}
+
+fn describe_cluster_kind(cluster: &ClusterKind) -> String {
+    match cluster {
+        ClusterKind::DockerDesktop => "Docker Desktop".to_string(),
+        ClusterKind::Kind(name) => format!("kind cluster '{}'", name),
+        ClusterKind::K3d(name) => format!("k3d cluster '{}'", name),
+        ClusterKind::Minikube(profile) => format!("minikube profile '{}'", profile),
+        ClusterKind::Other(_) => "remote cluster".to_string(),
+    }
+}
+
 // Add high-performance caching layer
 async fn setup_caching_layer() -> io::Result<()> {
     // Multi-layer caching
@@ -1624,20 +3772,16 @@ async fn setup_caching_layer() -> io::Result<()> {
           browser_ttl: 30m
     "#;
 
-    fs::write("traefik-config.yaml", traefik_config)?;
-    fs::write("caching-config.yaml", caching_config)?;
-    fs::write("edge-rules.yaml", edge_rules)?;
+    let files = [
+        artifacts::write("traefik-config.yaml", traefik_config)?,
+        artifacts::write("caching-config.yaml", caching_config)?,
+        artifacts::write("edge-rules.yaml", edge_rules)?,
+    ];
 
     // Apply configurations
-    Command::new("kubectl")
-        .args(["apply", "-f", "traefik-config.yaml"])
-        .output()?;
-    Command::new("kubectl")
-        .args(["apply", "-f", "caching-config.yaml"])
-        .output()?;
-    Command::new("kubectl")
-        .args(["apply", "-f", "edge-rules.yaml"])
-        .output()?;
+    for path in &files {
+        kubectl().args(["apply", "-f"]).arg(path).run()?;
+    }
 
     Ok(())
 }
@@ -1692,7 +3836,7 @@ backend apps
     "#
     );
 
-    fs::write("haproxy.cfg", haproxy_config)?;
+    artifacts::write("haproxy.cfg", haproxy_config)?;
     Ok(())
 }
 
@@ -1708,7 +3852,74 @@ fn default_max_instances() -> u32 {
     5
 }
 
-fn initialize_project(project_type: &str) -> io::Result<()> {
+fn default_scale_threshold() -> f64 {
+    70.0
+}
+
+fn default_scale_up_cooldown() -> u64 {
+    60
+}
+
+fn default_scale_down_cooldown() -> u64 {
+    300
+}
+
+fn default_request_metric() -> String {
+    "http_requests_per_second".to_string()
+}
+
+fn default_image_tag() -> String {
+    "latest".to_string()
+}
+
+fn default_production_contexts() -> Vec<String> {
+    vec!["*prod*".to_string()]
+}
+
+fn default_secret_keys() -> Vec<String> {
+    vec![
+        "*SECRET*".to_string(),
+        "*PASSWORD*".to_string(),
+        "*TOKEN*".to_string(),
+        "*_KEY".to_string(),
+        "DATABASE_URL".to_string(),
+        "MONGODB_URI".to_string(),
+    ]
+}
+
+fn default_ingress_namespace() -> String {
+    "ingress-nginx".to_string()
+}
+
+fn default_health_path() -> String {
+    "/health".to_string()
+}
+
+fn default_ingress_class() -> String {
+    "nginx".to_string()
+}
+
+fn default_ingress_tls_secret() -> Option<String> {
+    Some("tls-secret".to_string())
+}
+
+fn default_ingress_timeout() -> u32 {
+    60
+}
+
+fn default_ingress_body_size() -> String {
+    "10m".to_string()
+}
+
+fn default_cert_valid_days() -> u32 {
+    90
+}
+
+fn default_cert_renew_before_days() -> u32 {
+    30
+}
+
+fn initialize_project(matches: &clap::ArgMatches) -> io::Result<()> {
     // Verify directory is empty or create new
     if !Path::new(".").read_dir()?.next().is_none() {
         return Err(io::Error::new(
@@ -1717,54 +3928,82 @@ fn initialize_project(project_type: &str) -> io::Result<()> {
         ));
     }
 
+    if let Some(spec) = matches.value_of("template") {
+        let template = templates::resolve(spec)?;
+        return create_app_files(spec, &template, matches);
+    }
+
+    let project_type = matches.value_of("type").unwrap();
+    if matches.is_present("offline") {
+        let template = templates::builtin(project_type).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unsupported project type: {}", project_type),
+            )
+        })?;
+        return create_app_files(project_type, &template, matches);
+    }
+
     match project_type {
         "next" => {
             Command::new("npx")
                 .args(["create-next-app", ".", "--typescript", "--tailwind"])
-                .status()?;
-            optimize_existing_nextjs_project()?;
+                .run_status()?;
         }
         "react" => {
             Command::new("npx")
                 .args(["create-react-app", ".", "--template", "typescript"])
-                .status()?;
+                .run_status()?;
         }
         "vue" => {
             Command::new("npm")
                 .args(["create", "vue@latest", "."])
-                .status()?;
+                .run_status()?;
         }
         "svelte" => {
             Command::new("npm")
                 .args(["create", "svelte@latest", "."])
-                .status()?;
+                .run_status()?;
         }
         "astro" => {
             Command::new("npm")
                 .args(["create", "astro@latest", "."])
-                .status()?;
+                .run_status()?;
         }
         "remix" => {
             Command::new("npx")
                 .args(["create-remix", "."])
-                .status()?;
+                .run_status()?;
+        }
+        "angular" => {
+            Command::new("npx")
+                .args(["@angular/cli", "new", "app", "--directory", ".", "--defaults"])
+                .run_status()?;
+        }
+        "nuxt" => {
+            Command::new("npx")
+                .args(["nuxi@latest", "init", "."])
+                .run_status()?;
+        }
+        "bun" => {
+            Command::new("bun").args(["init", "-y"]).run_status()?;
         }
         "mern" => {
             // Initialize both frontend and backend
-            fs::create_dir_all("client")?;
-            fs::create_dir_all("server")?;
+            effects::create_dir_all("client")?;
+            effects::create_dir_all("server")?;
             
             // Initialize React frontend
             Command::new("npx")
                 .current_dir("client")
                 .args(["create-react-app", ".", "--template", "typescript"])
-                .status()?;
+                .run_status()?;
                 
             // Initialize Express backend
             Command::new("npm")
                 .current_dir("server")
                 .args(["init", "-y"])
-                .status()?;
+                .run_status()?;
         }
         _ => {
             return Err(io::Error::new(
@@ -1774,6 +4013,13 @@ fn initialize_project(project_type: &str) -> io::Result<()> {
         }
     }
 
+    // Generators write their own package.json; add rustify's scripts to it
+    if project_type != "bun" && Path::new("package.json").exists() {
+        let mut changes = optimize::Changes::default();
+        optimize_existing_project(project_type, &mut changes)?;
+        changes.write()?;
+    }
+
     println!("✅ Project initialized successfully!");
     Ok(())
 }
@@ -1781,51 +4027,18 @@ fn initialize_project(project_type: &str) -> io::Result<()> {
 async fn setup_security_layer(app_name: &str, namespace: &str) -> io::Result<()> {
     println!("🔒 Setting up enterprise security layer...");
 
-    // Setup mTLS certificates
-    let cert_config = r#"
-[req]
-distinguished_name = req_distinguished_name
-x509_extensions = v3_req
-prompt = no
-
-[req_distinguished_name]
-C = US
-ST = CA
-L = San Francisco
-O = Enterprise
-OU = Security
-CN = service.internal
-
-[v3_req]
-basicConstraints = CA:FALSE
-keyUsage = nonRepudiation, digitalSignature, keyEncipherment
-extendedKeyUsage = serverAuth, clientAuth
-subjectAltName = @alt_names
-
-[alt_names]
-DNS.1 = *.service.internal
-DNS.2 = localhost"#;
-
-    fs::write("cert.conf", cert_config)?;
-
-    // Generate certificates
-    Command::new("openssl")
-        .args([
-            "req",
-            "-x509",
-            "-nodes",
-            "-days",
-            "365",
-            "-newkey",
-            "rsa:2048",
-            "-keyout",
-            "tls.key",
-            "-out",
-            "tls.crt",
-            "-config",
-            "cert.conf",
-        ])
-        .output()?;
+    // Setup mTLS certificates, issued in-process by the local CA
+    let hosts = vec![
+        format!("{}.{}.svc", app_name, namespace),
+        format!("{}.{}.svc.cluster.local", app_name, namespace),
+        "localhost".to_string(),
+    ];
+    install_tls_secrets(
+        namespace,
+        &[&format!("{}-mtls", app_name)],
+        &hosts,
+        &TlsConfig::default(),
+    )?;
 
     // Apply Zero Trust policies
     let zero_trust_policy = format!(
@@ -1846,7 +4059,7 @@ spec:
         methods: ["GET", "POST"]"#
     );
 
-    fs::write("zero-trust-policy.yaml", zero_trust_policy)?;
+    artifacts::write("zero-trust-policy.yaml", zero_trust_policy)?;
 
     Ok(())
 }
@@ -1862,7 +4075,7 @@ appendonly yes
 maxmemory 2gb
 maxmemory-policy allkeys-lru"#;
 
-    fs::write("redis.conf", redis_config)?;
+    artifacts::write("redis.conf", redis_config)?;
 
     Ok(())
 }
@@ -1891,98 +4104,104 @@ sub vcl_recv {
     }
 }"#;
 
-    fs::write("default.vcl", vcl_config)?;
+    artifacts::write("default.vcl", vcl_config)?;
 
     Ok(())
 }
 
-fn deploy_nginx(namespace: &str) -> io::Result<()> {
-    println!("{}", GradientText::cyber("📦 Deploying Nginx..."));
+/// nginx.conf mounted by the nginx Deployment; also shipped in exported charts.
+const NGINX_CONF: &str = r#"worker_processes auto;
+worker_rlimit_nofile 100000;
 
-    // Create Nginx ConfigMap with optimized configuration
-    let nginx_config = format!(
-        r#"
-apiVersion: v1
+events {
+    worker_connections 4096;
+    use epoll;
+    multi_accept on;
+}
+
+http {
+    # Optimization
+    sendfile on;
+    tcp_nopush on;
+    tcp_nodelay on;
+    keepalive_timeout 65;
+    keepalive_requests 100000;
+    
+    # Bun.js Optimizations
+    upstream bun_servers {
+        least_conn;
+        server localhost:3000 max_fails=3 fail_timeout=30s;
+        server localhost:3001 max_fails=3 fail_timeout=30s;
+        keepalive 32;
+    }
+    
+    # Security
+    ssl_protocols TLSv1.2 TLSv1.3;
+    ssl_prefer_server_ciphers on;
+    ssl_session_cache shared:SSL:50m;
+    ssl_session_timeout 1d;
+    
+    # Compression
+    gzip on;
+    gzip_comp_level 6;
+    gzip_types text/plain text/css application/json application/javascript;
+    
+    server {
+        listen 80;
+        listen [::]:80;
+        listen 443 ssl http2;
+        
+        # SSL Configuration
+        ssl_certificate /etc/nginx/ssl/tls.crt;
+        ssl_certificate_key /etc/nginx/ssl/tls.key;
+        
+        location / {
+            proxy_pass http://bun_servers;
+            proxy_http_version 1.1;
+            proxy_set_header Upgrade $http_upgrade;
+            proxy_set_header Connection 'upgrade';
+            proxy_set_header Host $host;
+            proxy_cache_bypass $http_upgrade;
+            
+            # Security headers
+            add_header Strict-Transport-Security "max-age=31536000; includeSubDomains" always;
+            add_header X-Frame-Options "SAMEORIGIN" always;
+            add_header X-Content-Type-Options "nosniff" always;
+        }
+        
+        location /health {
+            access_log off;
+            return 200 'healthy\n';
+        }
+    }
+}
+"#;
+
+/// ConfigMap holding `NGINX_CONF`.
+fn render_nginx_config(namespace: &str) -> String {
+    format!(
+        r#"apiVersion: v1
 kind: ConfigMap
 metadata:
   name: nginx-config
   namespace: {namespace}
 data:
-    nginx.conf: |
-      worker_processes auto;
-      worker_rlimit_nofile 100000;
-      
-      events {{
-          worker_connections 4096;
-          use epoll;
-          multi_accept on;
-      }}
-      
-      http {{
-          # Optimization
-          sendfile on;
-          tcp_nopush on;
-          tcp_nodelay on;
-          keepalive_timeout 65;
-          keepalive_requests 100000;
-          
-          # Bun.js Optimizations
-          upstream bun_servers {{
-              least_conn;
-              server localhost:3000 max_fails=3 fail_timeout=30s;
-              server localhost:3001 max_fails=3 fail_timeout=30s;
-              keepalive 32;
-          }}
-          
-          # Security
-          ssl_protocols TLSv1.2 TLSv1.3;
-          ssl_prefer_server_ciphers on;
-          ssl_session_cache shared:SSL:50m;
-          ssl_session_timeout 1d;
-          
-          # Compression
-          gzip on;
-          gzip_comp_level 6;
-          gzip_types text/plain text/css application/json application/javascript;
-          
-          server {{
-              listen 80;
-              listen [::]:80;
-              listen 443 ssl http2;
-              
-              # SSL Configuration
-              ssl_certificate /etc/nginx/ssl/tls.crt;
-              ssl_certificate_key /etc/nginx/ssl/tls.key;
-              
-              location / {{
-                  proxy_pass http://bun_servers;
-                  proxy_http_version 1.1;
-                  proxy_set_header Upgrade $http_upgrade;
-                  proxy_set_header Connection 'upgrade';
-                  proxy_set_header Host $host;
-                  proxy_cache_bypass $http_upgrade;
-                  
-                  # Security headers
-                  add_header Strict-Transport-Security "max-age=31536000; includeSubDomains" always;
-                  add_header X-Frame-Options "SAMEORIGIN" always;
-                  add_header X-Content-Type-Options "nosniff" always;
-              }}
-              
-              location /health {{
-                  access_log off;
-                  return 200 'healthy\n';
-              }}
-          }}
-      }}
-"#
-    );
+  nginx.conf: |
+{}"#,
+        NGINX_CONF
+            .lines()
+            .map(|line| format!("    {}\n", line))
+            .collect::<String>()
+    )
+}
+
+fn deploy_nginx(namespace: &str) -> io::Result<()> {
+    println!("{}", GradientText::cyber("📦 Deploying Nginx..."));
 
-    fs::write("nginx-config.yaml", nginx_config)?;
+    let path = artifacts::write("nginx-config.yaml", render_nginx_config(namespace))?;
 
     // Apply ConfigMap
-    Command::new("kubectl")
-        .args(["apply", "-f", "nginx-config.yaml"])
-        .output()?;
+    kubectl().args(["apply", "-f"]).arg(&path).run()?;
 
     // Deploy Nginx with optimized settings
     let nginx_deployment = format!(
@@ -2046,11 +4265,9 @@ spec:
 "#
     );
 
-    fs::write("nginx-deployment.yaml", nginx_deployment)?;
+    let path = artifacts::write("nginx-deployment.yaml", nginx_deployment)?;
 
-    Command::new("kubectl")
-        .args(["apply", "-f", "nginx-deployment.yaml"])
-        .output()?;
+    kubectl().args(["apply", "-f"]).arg(&path).run()?;
 
     // Create Nginx Service
     let nginx_service = format!(
@@ -2077,11 +4294,9 @@ spec:
 "#
     );
 
-    fs::write("nginx-service.yaml", nginx_service)?;
+    let path = artifacts::write("nginx-service.yaml", nginx_service)?;
 
-    Command::new("kubectl")
-        .args(["apply", "-f", "nginx-service.yaml"])
-        .output()?;
+    kubectl().args(["apply", "-f"]).arg(&path).run()?;
 
     println!(
         "{}",
@@ -2107,8 +4322,8 @@ fn optimize_kernel_parameters() -> io::Result<()> {
     vm.dirty_background_ratio = 2
     "#;
 
-    fs::write("/etc/sysctl.d/99-performance.conf", sysctl_config)?;
-    Command::new("sysctl").args(["-p"]).output()?;
+    effects::write("/etc/sysctl.d/99-performance.conf", sysctl_config)?;
+    Command::new("sysctl").args(["-p"]).run()?;
     Ok(())
 }
 
@@ -2133,7 +4348,7 @@ fn optimize_bun_runtime() -> io::Result<()> {
       }
     }"#;
 
-    fs::write("bunfig.toml", config)?;
+    effects::write("bunfig.toml", config)?;
     Ok(())
 }
 
@@ -2156,67 +4371,7 @@ fn enhance_load_balancer_config() -> io::Result<()> {
         http-check expect status 200
     }
     "#;
-    fs::write("haproxy-dynamic.cfg", config)?;
-    Ok(())
-}
-
-fn create_nextjs_optimized_config() -> io::Result<()> {
-    // Enhanced Next.js + TypeScript configuration
-    let next_config = r#"
-    module.exports = {
-      reactStrictMode: true,
-      experimental: {
-        serverActions: true,
-        serverComponents: true,
-        concurrentFeatures: true,
-        optimizeCss: true,
-        optimizeImages: true,
-        scrollRestoration: true,
-        runtime: 'experimental-edge',
-      },
-      compiler: {
-        removeConsole: process.env.NODE_ENV === 'production',
-      },
-      typescript: {
-        ignoreBuildErrors: false,
-        tsconfigPath: './tsconfig.json'
-      },
-      // Bun.js optimizations
-      webpack: (config) => {
-        config.experiments = { topLevelAwait: true };
-        config.cache = {
-          type: 'filesystem',
-          buildDependencies: {
-            config: [__filename],
-          },
-        };
-        return config;
-      },
-    }"#;
-
-    let tsconfig = r#"{
-      "compilerOptions": {
-        "target": "esnext",
-        "lib": ["dom", "dom.iterable", "esnext"],
-        "allowJs": true,
-        "skipLibCheck": true,
-        "strict": true,
-        "forceConsistentCasingInFileNames": true,
-        "noEmit": true,
-        "incremental": true,
-        "esModuleInterop": true,
-        "module": "esnext",
-        "moduleResolution": "node",
-        "resolveJsonModule": true,
-        "isolatedModules": true,
-        "jsx": "preserve"
-      },
-      "include": ["next-env.d.ts", "**/*.ts", "**/*.tsx"],
-      "exclude": ["node_modules"]
-    }"#;
-
-    fs::write("next.config.js", next_config)?;
-    fs::write("tsconfig.json", tsconfig)?;
+    artifacts::write("haproxy-dynamic.cfg", config)?;
     Ok(())
 }
 
@@ -2257,22 +4412,10 @@ fn validate_nextjs_project() -> io::Result<bool> {
     Ok(has_required)
 }
 
-fn optimize_existing_nextjs_project() -> io::Result<()> {
+fn optimize_existing_nextjs_project(changes: &mut optimize::Changes) -> io::Result<()> {
     println!("🔍 Analyzing existing Next.js project...");
 
-    // Backup existing configuration
-    if Path::new("next.config.js").exists() {
-        fs::copy("next.config.js", "next.config.js.backup")?;
-    }
-
-    // Read existing next.config.js
-    let _existing_config = if Path::new("next.config.js").exists() {
-        fs::read_to_string("next.config.js")?
-    } else {
-        String::new()
-    };
-
-    // Merge with our optimized config
+    // Only used when the project has no config of its own
     let optimized_config = r#"
     const nextConfig = {
       reactStrictMode: true,
@@ -2300,12 +4443,7 @@ fn optimize_existing_nextjs_project() -> io::Result<()> {
         ignoreBuildErrors: false,
         tsconfigPath: './tsconfig.json'
       },
-      webpack: (config, { dev, isServer }) => {
-        // Keep existing webpack config
-        if (typeof existingWebpackConfig === 'function') {
-          config = existingWebpackConfig(config, { dev, isServer });
-        }
-
+      webpack: (config, { dev }) => {
         // Add our optimizations
         config.experiments = { 
           topLevelAwait: true,
@@ -2370,30 +4508,15 @@ fn optimize_existing_nextjs_project() -> io::Result<()> {
     module.exports = nextConfig;
     "#;
 
-    fs::write("next.config.js", optimized_config)?;
-    use serde_json::json; // Import the json macro
+    changes.config(NEXT_CONFIGS, Some(optimized_config), NEXT_CONFIG_EDITS);
 
-    // Update package.json with optimized scripts and dependencies
+    // Update package.json with optimized scripts
     if Path::new("package.json").exists() {
-        let mut package_json: serde_json::Value =
-            serde_json::from_str(&fs::read_to_string("package.json")?)?;
-
-        // Add optimized scripts
-        if let Some(scripts) = package_json
-            .get_mut("scripts")
-            .and_then(|s| s.as_object_mut())
-        {
-            scripts.insert("dev".to_string(), json!("next dev -p 3000"));
-            scripts.insert("build".to_string(), json!("next build"));
-            scripts.insert("start".to_string(), json!("next start -p 3000"));
-            scripts.insert("analyze".to_string(), json!("ANALYZE=true next build"));
-            scripts.insert("lint".to_string(), json!("next lint && prettier --write ."));
-        }
-
-        fs::write("package.json", serde_json::to_string_pretty(&package_json)?)?;
+        let mut pkg = packagejson::PackageJson::load("package.json")?;
+        pkg.apply(NEXT_PACKAGE_EDITS);
+        changes.update("package.json", pkg.render()?);
     }
 
-    println!("✅ Next.js project optimized successfully!");
     Ok(())
 }
 
@@ -2479,43 +4602,145 @@ docker-compose*.yml
 !api/
 !types/"#;
 
-    fs::write(".dockerignore", dockerignore)?;
+    effects::write(".dockerignore", dockerignore)?;
     Ok(())
 }
 
-fn optimize_existing_project(app_type: &str) -> io::Result<()> {
-    let package_json = fs::read_to_string("package.json")?;
-    let mut pkg: serde_json::Value = serde_json::from_str(&package_json)?;
+const NEXT_CONFIGS: &[&str] = &["next.config.js", "next.config.mjs", "next.config.ts"];
+/// In the order Vite looks them up.
+const VITE_CONFIGS: &[&str] = &[
+    "vite.config.js",
+    "vite.config.mjs",
+    "vite.config.ts",
+    "vite.config.cjs",
+    "vite.config.mts",
+    "vite.config.cts",
+];
+
+// Keys `rustify optimize` merges into configs the project already has
+const NEXT_CONFIG_EDITS: &[jsconfig::Edit] = &[
+    jsconfig::Edit { path: &["reactStrictMode"], value: "true" },
+    jsconfig::Edit { path: &["poweredByHeader"], value: "false" },
+    jsconfig::Edit { path: &["compress"], value: "true" },
+    jsconfig::Edit {
+        path: &["compiler", "removeConsole"],
+        value: "process.env.NODE_ENV === 'production'",
+    },
+];
+const VITE_CONFIG_EDITS: &[jsconfig::Edit] = &[
+    jsconfig::Edit { path: &["build", "sourcemap"], value: "false" },
+    jsconfig::Edit { path: &["build", "cssCodeSplit"], value: "true" },
+    jsconfig::Edit { path: &["build", "chunkSizeWarningLimit"], value: "1000" },
+];
+const VUE_CLI_CONFIG_EDITS: &[jsconfig::Edit] = &[
+    jsconfig::Edit { path: &["productionSourceMap"], value: "false" },
+];
+const ASTRO_CONFIG_EDITS: &[jsconfig::Edit] = &[
+    jsconfig::Edit { path: &["compressHTML"], value: "true" },
+    jsconfig::Edit { path: &["build", "inlineStylesheets"], value: "'auto'" },
+];
+const NUXT_CONFIG_EDITS: &[jsconfig::Edit] = &[
+    jsconfig::Edit { path: &["nitro", "compressPublicAssets"], value: "true" },
+];
+
+const NEXT_PACKAGE_EDITS: &[packagejson::Edit] = &[
+    // next dev and next start read PORT themselves
+    packagejson::Edit::Script("dev", "next dev"),
+    packagejson::Edit::Script("build", "next build"),
+    packagejson::Edit::Script("start", "next start"),
+    packagejson::Edit::OwnedScript("analyze", "ANALYZE=true next build"),
+    packagejson::Edit::Script("lint", "next lint && prettier --write ."),
+    packagejson::Edit::Engine("node", ">=18.17.0"),
+];
+const REACT_PACKAGE_EDITS: &[packagejson::Edit] = &[
+    packagejson::Edit::OwnedScript("analyze", "webpack-bundle-analyzer stats.json"),
+    packagejson::Edit::OwnedScript("build:prod", "GENERATE_SOURCEMAP=false react-scripts build"),
+    packagejson::Edit::DevDependency("webpack-bundle-analyzer", "^4.10.0"),
+];
+const VUE_PACKAGE_EDITS: &[packagejson::Edit] = &[
+    packagejson::Edit::OwnedScript("analyze", "vue-cli-service build --report"),
+    packagejson::Edit::OwnedScript("build:prod", "vue-cli-service build --modern"),
+];
+const NUXT_PACKAGE_EDITS: &[packagejson::Edit] = &[
+    packagejson::Edit::OwnedScript("analyze", "nuxt build --analyze"),
+    packagejson::Edit::OwnedScript("build:prod", "nuxt build --modern=server"),
+];
+const SVELTE_PACKAGE_EDITS: &[packagejson::Edit] = &[
+    packagejson::Edit::OwnedScript("analyze", "vite build --mode analyze"),
+    packagejson::Edit::OwnedScript("build:prod", "vite build --mode production"),
+    packagejson::Edit::Script("preview", "vite preview"),
+];
+const ANGULAR_PACKAGE_EDITS: &[packagejson::Edit] = &[
+    packagejson::Edit::OwnedScript(
+        "analyze",
+        "ng build --stats-json && webpack-bundle-analyzer dist/stats.json",
+    ),
+    packagejson::Edit::OwnedScript(
+        "build:prod",
+        "ng build --configuration production --aot --build-optimizer --optimization",
+    ),
+    packagejson::Edit::DevDependency("webpack-bundle-analyzer", "^4.10.0"),
+];
+const ASTRO_PACKAGE_EDITS: &[packagejson::Edit] = &[
+    packagejson::Edit::OwnedScript("analyze", "astro build --analyze"),
+    packagejson::Edit::OwnedScript("build:prod", "astro build --mode production"),
+    packagejson::Edit::Script("preview", "astro preview"),
+];
+const REMIX_PACKAGE_EDITS: &[packagejson::Edit] = &[
+    packagejson::Edit::OwnedScript("build:prod", "remix build --sourcemap --minify"),
+    packagejson::Edit::OwnedScript("analyze", "REMIX_ANALYZE=1 remix build"),
+    packagejson::Edit::OwnedScript("start:prod", "remix-serve ./build/index.js"),
+    // Required by the server.js optimize_remix_config writes
+    packagejson::Edit::Dependency("@remix-run/express", "^2.9.0"),
+    packagejson::Edit::Dependency("compression", "^1.7.4"),
+    packagejson::Edit::Dependency("express", "^4.19.0"),
+    packagejson::Edit::Dependency("morgan", "^1.10.0"),
+];
+const MERN_PACKAGE_EDITS: &[packagejson::Edit] = &[
+    packagejson::Edit::OwnedScript("build:prod", "npm run build:server && npm run build:client"),
+    packagejson::Edit::OwnedScript("start:prod", "NODE_ENV=production node dist/server/index.js"),
+    packagejson::Edit::OwnedScript(
+        "analyze",
+        "webpack-bundle-analyzer client/build/bundle-stats.json",
+    ),
+    packagejson::Edit::DevDependency("webpack-bundle-analyzer", "^4.10.0"),
+];
+
+fn vite_config_exists() -> bool {
+    VITE_CONFIGS.iter().any(|c| Path::new(c).is_file())
+}
+
+fn optimize_existing_project(app_type: &str, changes: &mut optimize::Changes) -> io::Result<()> {
+    let mut pkg = packagejson::PackageJson::load("package.json")?;
 
     match app_type {
-        "react" => optimize_react_config(&mut pkg)?,
-        "nuxt" => optimize_nuxt_config(&mut pkg)?,
-        "vue" => optimize_vue_config(&mut pkg)?,
-        "svelte" => optimize_svelte_config(&mut pkg)?,
-        "angular" => optimize_angular_config(&mut pkg)?,
-        "astro" => optimize_astro_config(&mut pkg)?,
-        "remix" => optimize_remix_config(&mut pkg)?,
-        "mern" => optimize_mern_config(&mut pkg)?,
-        _ => {}
-    }
-    // Save optimized package.json
-    fs::write("package.json", serde_json::to_string_pretty(&pkg)?)?;
+        "next" => return optimize_existing_nextjs_project(changes),
+        "react" => optimize_react_config(&mut pkg, changes)?,
+        "nuxt" => optimize_nuxt_config(&mut pkg, changes)?,
+        "vue" => optimize_vue_config(&mut pkg, changes)?,
+        "svelte" => optimize_svelte_config(&mut pkg, changes)?,
+        "angular" => optimize_angular_config(&mut pkg, changes)?,
+        "astro" => optimize_astro_config(&mut pkg, changes)?,
+        "remix" => optimize_remix_config(&mut pkg, changes)?,
+        "mern" => optimize_mern_config(&mut pkg, changes)?,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("No optimizations available for {} projects", app_type),
+            ))
+        }
+    }
+    changes.update("package.json", pkg.render()?);
 
     Ok(())
 }
 
-fn optimize_react_config(pkg: &mut serde_json::Value) -> io::Result<()> {
+fn optimize_react_config(
+    pkg: &mut packagejson::PackageJson,
+    changes: &mut optimize::Changes,
+) -> io::Result<()> {
     // Add optimization scripts
-    if let Some(scripts) = pkg.get_mut("scripts").and_then(|s| s.as_object_mut()) {
-        scripts.insert(
-            "analyze".to_string(),
-            json!("webpack-bundle-analyzer stats.json"),
-        );
-        scripts.insert(
-            "build:prod".to_string(),
-            json!("GENERATE_SOURCEMAP=false react-scripts build"),
-        );
-    }
+    pkg.apply(REACT_PACKAGE_EDITS);
 
     // Create optimized webpack config
     let webpack_config = r#"
@@ -2565,22 +4790,21 @@ fn optimize_react_config(pkg: &mut serde_json::Value) -> io::Result<()> {
         ],
     };"#;
 
-    fs::write("webpack.config.js", webpack_config)?;
+    // Vite projects get the same keys merged into their config instead
+    if vite_config_exists() {
+        changes.config(VITE_CONFIGS, None, VITE_CONFIG_EDITS);
+    } else {
+        changes.create("webpack.config.js", webpack_config);
+    }
     Ok(())
 }
 
-fn optimize_vue_config(pkg: &mut serde_json::Value) -> io::Result<()> {
+fn optimize_vue_config(
+    pkg: &mut packagejson::PackageJson,
+    changes: &mut optimize::Changes,
+) -> io::Result<()> {
     // Add optimization scripts
-    if let Some(scripts) = pkg.get_mut("scripts").and_then(|s| s.as_object_mut()) {
-        scripts.insert(
-            "analyze".to_string(),
-            json!("vue-cli-service build --report"),
-        );
-        scripts.insert(
-            "build:prod".to_string(),
-            json!("vue-cli-service build --modern"),
-        );
-    }
+    pkg.apply(VUE_PACKAGE_EDITS);
 
     // Create vue.config.js with optimizations
     let vue_config = r#"
@@ -2623,93 +4847,43 @@ fn optimize_vue_config(pkg: &mut serde_json::Value) -> io::Result<()> {
       },
     };"#;
 
-    fs::write("vue.config.js", vue_config)?;
-    Ok(())
-}
-
-fn create_vue_files(_port: &str) -> io::Result<()> {
-    let package_json = r#"{
-        "name": "vue-app",
-        "version": "0.1.0",
-        "private": true,
-        "scripts": {
-            "serve": "vue-cli-service serve",
-            "build": "vue-cli-service build --modern",
-            "lint": "vue-cli-service lint",
-            "analyze": "vue-cli-service build --report"
-        },
-        "dependencies": {
-            "vue": "^3.3.0",
-            "vue-router": "^4.0.0",
-            "vuex": "^4.0.0"
-        },
-        "devDependencies": {
-            "@vue/cli-plugin-babel": "~5.0.0",
-            "@vue/cli-plugin-router": "~5.0.0",
-            "@vue/cli-plugin-vuex": "~5.0.0",
-            "@vue/cli-service": "~5.0.0"
-        }
-    }"#;
-
-    fs::write("package.json", package_json)?;
-    Ok(())
-}
-
-fn optimize_nuxt_config(pkg: &mut serde_json::Value) -> io::Result<()> {
-    // Add optimization scripts
-    if let Some(scripts) = pkg.get_mut("scripts").and_then(|s| s.as_object_mut()) {
-        scripts.insert("analyze".to_string(), json!("nuxt build --analyze"));
-        scripts.insert(
-            "build:prod".to_string(),
-            json!("nuxt build --modern=server"),
-        );
+    if vite_config_exists() {
+        changes.config(VITE_CONFIGS, None, VITE_CONFIG_EDITS);
+    } else {
+        changes.config(&["vue.config.js"], Some(vue_config), VUE_CLI_CONFIG_EDITS);
     }
-
-    // Create nuxt.config.js with optimizations
-    let nuxt_config = r#"export default {
-        // ... config content ...
-    };"#;
-
-    fs::write("nuxt.config.js", nuxt_config)?;
     Ok(())
 }
 
-fn create_nuxt_files(_port: &str) -> io::Result<()> {
-    let package_json = r#"{
-        "name": "nuxt-app",
-        "version": "1.0.0",
-        "private": true,
-        "scripts": {
-            "dev": "nuxt",
-            "build": "nuxt build",
-            "start": "nuxt start",
-            "generate": "nuxt generate",
-            "analyze": "nuxt build --analyze"
-        },
-        "dependencies": {
-            "nuxt": "^3.0.0",
-            "@nuxtjs/composition-api": "^0.33.0"
-        },
-        "devDependencies": {
-            "@nuxt/types": "^2.15.8",
-            "@nuxt/typescript-build": "^2.1.0"
-        }
-    }"#;
+fn optimize_nuxt_config(
+    pkg: &mut packagejson::PackageJson,
+    changes: &mut optimize::Changes,
+) -> io::Result<()> {
+    // Add optimization scripts
+    pkg.apply(NUXT_PACKAGE_EDITS);
+
+    // Create nuxt.config.ts with optimizations
+    let nuxt_config = r#"export default defineNuxtConfig({
+  nitro: {
+    compressPublicAssets: true,
+  },
+});
+"#;
 
-    fs::write("package.json", package_json)?;
+    changes.config(
+        &["nuxt.config.ts", "nuxt.config.js", "nuxt.config.mjs"],
+        Some(nuxt_config),
+        NUXT_CONFIG_EDITS,
+    );
     Ok(())
 }
 
-fn optimize_svelte_config(pkg: &mut serde_json::Value) -> io::Result<()> {
+fn optimize_svelte_config(
+    pkg: &mut packagejson::PackageJson,
+    changes: &mut optimize::Changes,
+) -> io::Result<()> {
     // Add optimization scripts
-    if let Some(scripts) = pkg.get_mut("scripts").and_then(|s| s.as_object_mut()) {
-        scripts.insert("analyze".to_string(), json!("vite build --mode analyze"));
-        scripts.insert(
-            "build:prod".to_string(),
-            json!("vite build --mode production"),
-        );
-        scripts.insert("preview".to_string(), json!("vite preview"));
-    }
+    pkg.apply(SVELTE_PACKAGE_EDITS);
 
     // Create vite.config.js with Svelte optimizations
     let vite_config = r#"
@@ -2757,22 +4931,16 @@ fn optimize_svelte_config(pkg: &mut serde_json::Value) -> io::Result<()> {
       },
     });"#;
 
-    fs::write("vite.config.js", vite_config)?;
+    changes.config(VITE_CONFIGS, Some(vite_config), VITE_CONFIG_EDITS);
     Ok(())
 }
 
-fn optimize_angular_config(pkg: &mut serde_json::Value) -> io::Result<()> {
+fn optimize_angular_config(
+    pkg: &mut packagejson::PackageJson,
+    changes: &mut optimize::Changes,
+) -> io::Result<()> {
     // Add optimization scripts
-    if let Some(scripts) = pkg.get_mut("scripts").and_then(|s| s.as_object_mut()) {
-        scripts.insert(
-            "analyze".to_string(),
-            json!("ng build --stats-json && webpack-bundle-analyzer dist/stats.json"),
-        );
-        scripts.insert(
-            "build:prod".to_string(),
-            json!("ng build --configuration production --aot --build-optimizer --optimization"),
-        );
-    }
+    pkg.apply(ANGULAR_PACKAGE_EDITS);
 
     // Create custom-webpack.config.js
     let webpack_config = r#"
@@ -2808,7 +4976,7 @@ fn optimize_angular_config(pkg: &mut serde_json::Value) -> io::Result<()> {
       ],
     };"#;
 
-    fs::write("custom-webpack.config.js", webpack_config)?;
+    changes.create("custom-webpack.config.js", webpack_config);
 
     // Update angular.json with optimizations
     let angular_config = r#"{
@@ -2841,29 +5009,25 @@ fn optimize_angular_config(pkg: &mut serde_json::Value) -> io::Result<()> {
       }
     }"#;
 
-    fs::write("angular.json", angular_config)?;
+    changes.create("angular.json", angular_config);
     Ok(())
 }
 
-fn optimize_astro_config(pkg: &mut serde_json::Value) -> io::Result<()> {
+fn optimize_astro_config(
+    pkg: &mut packagejson::PackageJson,
+    changes: &mut optimize::Changes,
+) -> io::Result<()> {
     // Add optimization scripts
-    if let Some(scripts) = pkg.get_mut("scripts").and_then(|s| s.as_object_mut()) {
-        scripts.insert("analyze".to_string(), json!("astro build --analyze"));
-        scripts.insert(
-            "build:prod".to_string(),
-            json!("astro build --mode production"),
-        );
-        scripts.insert("preview".to_string(), json!("astro preview"));
-    }
+    pkg.apply(ASTRO_PACKAGE_EDITS);
 
     // Create astro.config.mjs with optimizations
     let astro_config = r#"
     import { defineConfig } from 'astro/config';
     import compress from 'astro-compress';
-    import prefetch from '@astrojs/prefetch';
     
     export default defineConfig({
       output: 'static',
+      prefetch: true,
       build: {
         inlineStylesheets: 'auto',
         split: true,
@@ -2891,11 +5055,13 @@ fn optimize_astro_config(pkg: &mut serde_json::Value) -> io::Result<()> {
                 }
               },
             },
-          ssr: {
-            noExternal: ['@astrojs/*'],
           },
         },
-        integrations: [
+        ssr: {
+          noExternal: ['@astrojs/*'],
+        },
+      },
+      integrations: [
           compress({
             CSS: true,
             HTML: {
@@ -2911,12 +5077,14 @@ fn optimize_astro_config(pkg: &mut serde_json::Value) -> io::Result<()> {
             Image: true,
             JavaScript: true,
           }),
-          prefetch(),
         ],
-      },
     });"#;
 
-    fs::write("astro.config.mjs", astro_config)?;
+    changes.config(
+        &["astro.config.mjs", "astro.config.ts", "astro.config.js"],
+        Some(astro_config),
+        ASTRO_CONFIG_EDITS,
+    );
 
     // Create tsconfig.json for TypeScript support
     let tsconfig = r#"{
@@ -2937,23 +5105,16 @@ fn optimize_astro_config(pkg: &mut serde_json::Value) -> io::Result<()> {
       }
     }"#;
 
-    fs::write("tsconfig.json", tsconfig)?;
+    changes.create("tsconfig.json", tsconfig);
     Ok(())
 }
 
-fn optimize_remix_config(pkg: &mut serde_json::Value) -> io::Result<()> {
+fn optimize_remix_config(
+    pkg: &mut packagejson::PackageJson,
+    changes: &mut optimize::Changes,
+) -> io::Result<()> {
     // Add optimization scripts
-    if let Some(scripts) = pkg.get_mut("scripts").and_then(|s| s.as_object_mut()) {
-        scripts.insert(
-            "build:prod".to_string(),
-            json!("remix build --sourcemap --minify"),
-        );
-        scripts.insert("analyze".to_string(), json!("REMIX_ANALYZE=1 remix build"));
-        scripts.insert(
-            "start:prod".to_string(),
-            json!("remix-serve ./build/index.js"),
-        );
-    }
+    pkg.apply(REMIX_PACKAGE_EDITS);
 
     // Create remix.config.js with optimizations
     let remix_config = r#"
@@ -2987,10 +5148,13 @@ fn optimize_remix_config(pkg: &mut serde_json::Value) -> io::Result<()> {
       },
     };"#;
 
-    fs::write("remix.config.js", remix_config)?;
+    // Remix on Vite has no remix.config.js
+    if !vite_config_exists() {
+        changes.create("remix.config.js", remix_config);
+    }
 
     // Create custom server.js for production
-    let server_js = r#"
+    let server_js = r##"
     const path = require("path");
     const express = require("express");
     const compression = require("compression");
@@ -3011,6 +5175,33 @@ fn optimize_remix_config(pkg: &mut serde_json::Value) -> io::Result<()> {
     );
     app.use(express.static("public", { maxAge: "1h" }));
 
+    let requests = 0;
+    app.use((req, res, next) => {
+      requests++;
+      next();
+    });
+
+    // Probed by Kubernetes
+    app.get("/health", (req, res) => res.send("ok"));
+
+    // Prometheus text format, scraped from the app port
+    app.get("/metrics", (req, res) => {
+      res.type("text/plain; version=0.0.4").send(
+        [
+          "# HELP http_requests_total Requests received.",
+          "# TYPE http_requests_total counter",
+          `http_requests_total ${requests}`,
+          "# HELP process_resident_memory_bytes Resident memory size in bytes.",
+          "# TYPE process_resident_memory_bytes gauge",
+          `process_resident_memory_bytes ${process.memoryUsage().rss}`,
+          "# HELP process_uptime_seconds Seconds since the process started.",
+          "# TYPE process_uptime_seconds gauge",
+          `process_uptime_seconds ${process.uptime()}`,
+          "",
+        ].join("\n")
+      );
+    });
+
     // REMIX handler with optimizations
     app.all(
       "*",
@@ -3024,28 +5215,18 @@ fn optimize_remix_config(pkg: &mut serde_json::Value) -> io::Result<()> {
     );
 
     const port = process.env.PORT || 3000;
-    app.listen(port, () => console.log(`Express server listening on port ${port}`));"#;
+    app.listen(port, () => console.log(`Express server listening on port ${port}`));"##;
 
-    fs::write("server.js", server_js)?;
+    changes.create("server.js", server_js);
     Ok(())
 }
 
-fn optimize_mern_config(pkg: &mut serde_json::Value) -> io::Result<()> {
+fn optimize_mern_config(
+    pkg: &mut packagejson::PackageJson,
+    changes: &mut optimize::Changes,
+) -> io::Result<()> {
     // Add optimization scripts for both frontend and backend
-    if let Some(scripts) = pkg.get_mut("scripts").and_then(|s| s.as_object_mut()) {
-        scripts.insert(
-            "build:prod".to_string(),
-            json!("npm run build:server && npm run build:client"),
-        );
-        scripts.insert(
-            "start:prod".to_string(),
-            json!("NODE_ENV=production node dist/server/index.js"),
-        );
-        scripts.insert(
-            "analyze".to_string(),
-            json!("webpack-bundle-analyzer client/build/bundle-stats.json"),
-        );
-    }
+    pkg.apply(MERN_PACKAGE_EDITS);
 
     // Create webpack.config.js for React frontend
     let webpack_config = r#"
@@ -3095,7 +5276,7 @@ fn optimize_mern_config(pkg: &mut serde_json::Value) -> io::Result<()> {
       ].filter(Boolean),
     };"#;
 
-    fs::write("webpack.config.js", webpack_config)?;
+    changes.create("webpack.config.js", webpack_config);
 
     // Create optimized Express server configuration
     let server_config = r#"
@@ -3128,6 +5309,33 @@ fn optimize_mern_config(pkg: &mut serde_json::Value) -> io::Result<()> {
     });
     app.use('/api', limiter);
 
+    let requests = 0;
+    app.use((req, res, next) => {
+      requests++;
+      next();
+    });
+
+    // Probed by Kubernetes; answers while the database is still connecting
+    app.get('/health', (req, res) => res.send('ok'));
+
+    // Prometheus text format, scraped from the app port
+    app.get('/metrics', (req, res) => {
+      res.type('text/plain; version=0.0.4').send(
+        [
+          '# HELP http_requests_total Requests received.',
+          '# TYPE http_requests_total counter',
+          `http_requests_total ${requests}`,
+          '# HELP process_resident_memory_bytes Resident memory size in bytes.',
+          '# TYPE process_resident_memory_bytes gauge',
+          `process_resident_memory_bytes ${process.memoryUsage().rss}`,
+          '# HELP process_uptime_seconds Seconds since the process started.',
+          '# TYPE process_uptime_seconds gauge',
+          `process_uptime_seconds ${process.uptime()}`,
+          '',
+        ].join('\n')
+      );
+    });
+
     // MongoDB optimization
     mongoose.connect(process.env.MONGODB_URI, {
       useNewUrlParser: true,
@@ -3155,7 +5363,7 @@ fn optimize_mern_config(pkg: &mut serde_json::Value) -> io::Result<()> {
 
     module.exports = app;"#;
 
-    fs::write("server/config.js", server_config)?;
+    changes.create("server/config.js", server_config);
 
     // Create PM2 ecosystem config for production
     let pm2_config = r#"{
@@ -3172,500 +5380,7 @@ fn optimize_mern_config(pkg: &mut serde_json::Value) -> io::Result<()> {
       }]
     }"#;
 
-    fs::write("ecosystem.config.json", pm2_config)?;
-    Ok(())
-}
-
-// Helper functions for creating specific configurations
-fn create_eslint_config(app_type: &str) -> io::Result<()> {
-    let eslint_config = match app_type {
-        "nextjs" | "react" => {
-            r#"{
-            "extends": [
-                "next/core-web-vitals",
-                "prettier"
-            ],
-            "rules": {
-                "react/no-unused-vars": "error",
-                "react-hooks/rules-of-hooks": "error",
-                "react-hooks/exhaustive-deps": "warn"
-            }
-        }"#
-        }
-        "vue" => {
-            r#"{
-            "extends": [
-                "plugin:vue/vue3-recommended",
-                "prettier"
-            ],
-            "rules": {
-                "vue/multi-word-component-names": "error",
-                "vue/no-unused-vars": "error"
-            }
-        }"#
-        }
-        // Add more framework-specific ESLint configs...
-        _ => {
-            r#"{
-            "extends": ["prettier"],
-            "rules": {
-                "no-unused-vars": "error",
-                "no-console": "warn"
-            }
-        }"#
-        }
-    };
-
-    fs::write(".eslintrc.json", eslint_config)?;
-    Ok(())
-}
-
-fn create_prettier_config() -> io::Result<()> {
-    let prettier_config = r#"{
-        "semi": true,
-        "trailingComma": "es5",
-        "singleQuote": true,
-        "printWidth": 100,
-        "tabWidth": 2,
-        "useTabs": false
-    }"#;
-
-    fs::write(".prettierrc", prettier_config)?;
-    Ok(())
-}
-
-fn create_editor_config() -> io::Result<()> {
-    let editor_config = r#"
-root = true
-
-[*]
-end_of_line = lf
-insert_final_newline = true
-trim_trailing_whitespace = true
-charset = utf-8
-
-[*.{js,jsx,ts,tsx,vue,svelte,astro}]
-indent_style = space
-indent_size = 2
-
-[*.{css,scss,less,styl}]
-indent_style = space
-indent_size = 2
-
-[*.{json,yml,yaml}]
-indent_style = space
-indent_size = 2
-"#;
-
-    fs::write(".editorconfig", editor_config)?;
-    Ok(())
-}
-
-fn create_docker_compose(app_type: &str) -> io::Result<()> {
-    let docker_compose = match app_type {
-        "mern" => {
-            r#"
-version: '3.8'
-services:
-  client:
-    build: ./client
-    ports:
-      - "3000:3000"
-    environment:
-      - NODE_ENV=production
-    depends_on:
-      - server
-  server:
-    build: ./server
-    ports:
-      - "5000:5000"
-    environment:
-      - NODE_ENV=production
-      - MONGODB_URI=mongodb://mongo:27017/app
-    depends_on:
-      - mongo
-  mongo:
-    image: mongo:latest
-    ports:
-      - "27017:27017"
-    volumes:
-      - mongodb_data:/data/db
-
-volumes:
-  mongodb_data:
-"#
-        }
-        _ => {
-            r#"
-version: '3.8'
-services:
-  app:
-    build: .
-    ports:
-      - "3000:3000"
-    environment:
-      - NODE_ENV=production
-"#
-        }
-    };
-
-    fs::write("docker-compose.yml", docker_compose)?;
-    Ok(())
-}
-
-fn create_react_files(_port: &str) -> io::Result<()> {
-    let package_json = r#"{
-        "name": "react-app",
-        "version": "0.1.0",
-        "private": true,
-        "scripts": {
-            "dev": "vite",
-            "build": "vite build",
-            "build:prod": "GENERATE_SOURCEMAP=false vite build",
-            "analyze": "vite build --mode analyze",
-            "preview": "vite preview",
-            "lint": "eslint src --ext .ts,.tsx"
-        },
-        "dependencies": {
-            "react": "^18.2.0",
-            "react-dom": "^18.2.0",
-            "react-router-dom": "^6.8.0"
-        },
-        "devDependencies": {
-            "@types/react": "^18.0.27",
-            "@types/react-dom": "^18.0.10",
-            "@vitejs/plugin-react": "^3.1.0",
-            "typescript": "^4.9.3",
-            "vite": "^4.1.0",
-            "compression-webpack-plugin": "^10.0.0",
-            "terser-webpack-plugin": "^5.3.7"
-        }
-    }"#;
-
-    fs::write("package.json", package_json)?;
-    optimize_react_config(&mut serde_json::from_str(&package_json)?)?;
-    Ok(())
-}
-
-fn create_svelte_files(_port: &str) -> io::Result<()> {
-    let package_json = r#"{
-        "name": "svelte-app",
-        "version": "0.1.0",
-        "private": true,
-        "scripts": {
-            "dev": "vite dev",
-            "build": "vite build",
-            "build:prod": "vite build --mode production",
-            "analyze": "vite build --mode analyze",
-            "preview": "vite preview"
-        },
-        "dependencies": {
-            "svelte": "^4.0.0",
-            "svelte-routing": "^2.0.0"
-        },
-        "devDependencies": {
-            "@sveltejs/vite-plugin-svelte": "^2.4.0",
-            "typescript": "^5.0.0",
-            "vite": "^4.3.9",
-            "vite-plugin-compress": "^2.1.1"
-        }
-    }"#;
-
-    fs::write("package.json", package_json)?;
-    optimize_svelte_config(&mut serde_json::from_str(&package_json)?)?;
-    Ok(())
-}
-
-fn create_angular_files(_port: &str) -> io::Result<()> {
-    let package_json = r#"{
-        "name": "angular-app",
-        "version": "0.0.0",
-        "scripts": {
-            "ng": "ng",
-            "start": "ng serve",
-            "build": "ng build",
-            "build:prod": "ng build --configuration production --aot",
-            "analyze": "ng build --stats-json && webpack-bundle-analyzer dist/stats.json",
-            "watch": "ng build --watch --configuration development",
-            "test": "ng test"
-        },
-        "dependencies": {
-            "@angular/animations": "^16.0.0",
-            "@angular/common": "^16.0.0",
-            "@angular/compiler": "^16.0.0",
-            "@angular/core": "^16.0.0",
-            "@angular/forms": "^16.0.0",
-            "@angular/platform-browser": "^16.0.0",
-            "@angular/platform-browser-dynamic": "^16.0.0",
-            "@angular/router": "^16.0.0",
-            "rxjs": "~7.8.0",
-            "zone.js": "~0.13.0"
-        },
-        "devDependencies": {
-            "@angular-devkit/build-angular": "^16.0.0",
-            "@angular/cli": "^16.0.0",
-            "@angular/compiler-cli": "^16.0.0",
-            "typescript": "~5.0.0",
-            "compression-webpack-plugin": "^10.0.0",
-            "brotli-webpack-plugin": "^1.1.0"
-        }
-    }"#;
-
-    fs::write("package.json", package_json)?;
-    optimize_angular_config(&mut serde_json::from_str(&package_json)?)?;
-    Ok(())
-}
-
-fn create_astro_files(port: &str) -> io::Result<()> {
-    let _ = port;
-    let package_json = r#"{
-        "name": "astro-app",
-        "version": "0.0.1",
-        "scripts": {
-            "dev": "astro dev",
-            "start": "astro dev",
-            "build": "astro build",
-            "build:prod": "astro build --mode production",
-            "preview": "astro preview",
-            "analyze": "astro build --analyze"
-        },
-        "dependencies": {
-            "astro": "^2.5.0",
-            "@astrojs/prefetch": "^0.2.0"
-        },
-        "devDependencies": {
-            "@astrojs/ts-plugin": "^1.0.0",
-            "astro-compress": "^1.1.0",
-            "typescript": "^5.0.0"
-        }
-    }"#;
-
-    fs::write("package.json", package_json)?;
-    optimize_astro_config(&mut serde_json::from_str(&package_json)?)?;
-    Ok(())
-}
-
-fn create_remix_files(port: &str) -> io::Result<()> {
-    let _ = port;
-    let package_json = r#"{
-        "name": "remix-app",
-        "private": true,
-        "sideEffects": false,
-        "scripts": {
-            "build": "remix build",
-            "build:prod": "remix build --sourcemap --minify",
-            "dev": "remix dev",
-            "analyze": "REMIX_ANALYZE=1 remix build",
-            "start": "remix-serve build/index.js",
-            "typecheck": "tsc"
-        },
-        "dependencies": {
-            "@remix-run/node": "^1.19.1",
-            "@remix-run/react": "^1.19.1",
-            "@remix-run/serve": "^1.19.1",
-            "compression": "^1.7.4",
-            "express": "^4.18.2",
-            "morgan": "^1.10.0"
-        },
-        "devDependencies": {
-            "@remix-run/dev": "^1.19.1",
-            "@types/react": "^18.0.35",
-            "@types/react-dom": "^18.0.11",
-            "typescript": "^5.0.4"
-        }
-    }"#;
-
-    fs::write("package.json", package_json)?;
-    optimize_remix_config(&mut serde_json::from_str(&package_json)?)?;
-    Ok(())
-}
-
-fn create_mern_files(port: &str) -> io::Result<()> {
-    let _ = port;
-    // Create root package.json
-    let package_json = r#"{
-        "name": "mern-app",
-        "version": "1.0.0",
-        "scripts": {
-            "build": "npm run build:client && npm run build:server",
-            "build:client": "cd client && npm run build",
-            "build:server": "cd server && npm run build",
-            "build:prod": "npm run build:client:prod && npm run build:server:prod",
-            "start:prod": "NODE_ENV=production node dist/server/index.js",
-            "analyze": "cd client && npm run analyze"
-        },
-        "devDependencies": {
-            "concurrently": "^8.0.1"
-        }
-    }"#;
-
-    fs::write("package.json", package_json)?;
-    optimize_mern_config(&mut serde_json::from_str(&package_json)?)?;
-
-    // Create client and server directories
-    fs::create_dir_all("client")?;
-    fs::create_dir_all("server")?;
-
-    Ok(())
-}
-
-fn create_github_workflows() -> io::Result<()> {
-    fs::create_dir_all(".github/workflows")?;
-
-    // CI/CD Workflow
-    let ci_workflow = r#"name: CI/CD Pipeline
-
-on:
-  push:
-    branches: [ main, develop ]
-  pull_request:
-    branches: [ main, develop ]
-
-jobs:
-  test-and-build:
-    runs-on: ubuntu-latest
-    
-    strategy:
-      matrix:
-        node-version: [18.x, 20.x]
-
-    steps:
-    - uses: actions/checkout@v3
-    
-    - name: Setup Node.js ${{ matrix.node-version }}
-      uses: actions/setup-node@v3
-      with:
-        node-version: ${{ matrix.node-version }}
-        cache: 'npm'
-    
-    - name: Install dependencies
-      run: npm ci
-    
-    - name: Run linting
-      run: npm run lint
-    
-    - name: Run tests
-      run: npm run test
-    
-    - name: Build application
-      run: npm run build:prod
-    
-    - name: Upload build artifacts
-      uses: actions/upload-artifact@v3
-      with:
-        name: build-files
-        path: dist/
-
-  docker:
-    needs: test-and-build
-    runs-on: ubuntu-latest
-    if: github.ref == 'refs/heads/main'
-    
-    steps:
-    - uses: actions/checkout@v3
-    
-    - name: Download build artifacts
-      uses: actions/download-artifact@v3
-      with:
-        name: build-files
-        path: dist/
-    
-    - name: Set up Docker Buildx
-      uses: docker/setup-buildx-action@v2
-    
-    - name: Login to Docker Hub
-      uses: docker/login-action@v2
-      with:
-        username: ${{ secrets.DOCKER_HUB_USERNAME }}
-        password: ${{ secrets.DOCKER_HUB_TOKEN }}
-    
-    - name: Build and push Docker image
-      uses: docker/build-push-action@v4
-      with:
-        context: .
-        push: true
-        tags: ${{ secrets.DOCKER_HUB_USERNAME }}/app:latest
-        cache-from: type=registry,ref=${{ secrets.DOCKER_HUB_USERNAME }}/app:buildcache
-        cache-to: type=registry,ref=${{ secrets.DOCKER_HUB_USERNAME }}/app:buildcache,mode=max"#;
-
-    // Security scanning workflow
-    let security_workflow = r#"name: Security Scan
-
-on:
-  schedule:
-    - cron: '0 0 * * *'
-  workflow_dispatch:
-
-jobs:
-  security:
-    runs-on: ubuntu-latest
-    steps:
-    - uses: actions/checkout@v3
-    
-    - name: Run Snyk to check for vulnerabilities
-      uses: snyk/actions/node@master
-      env:
-        SNYK_TOKEN: ${{ secrets.SNYK_TOKEN }}
-    
-    - name: Run OWASP Dependency-Check
-      uses: dependency-check/Dependency-Check_Action@main
-      with:
-        path: '.'
-        format: 'HTML'
-    
-    - name: Upload security report
-      uses: actions/upload-artifact@v3
-      with:
-        name: security-report
-        path: reports/"#;
-
-    fs::write(".github/workflows/ci.yml", ci_workflow)?;
-    fs::write(".github/workflows/security.yml", security_workflow)?;
-    Ok(())
-}
-fn create_optimization_configs(app_type: &str) -> io::Result<()> {
-    // Count optional directories for optimization level
-    let _optional_count = vec!["api", "lib", "utils", "hooks", "services"]
-        .iter()
-        .filter(|d| Path::new(d).exists())
-        .count();
-
-    println!(
-        "📊 Found {} optional optimization directories",
-        _optional_count
-    );
-
-    // Read existing configuration if it exists
-    let _existing_config = if Path::new("next.config.js").exists() {
-        Some(fs::read_to_string("next.config.js")?)
-    } else {
-        None
-    };
-
-    // Apply framework-specific optimizations
-    match app_type {
-        "nextjs" => {
-            if let Some(_config) = _existing_config {
-                println!("🔄 Merging with existing Next.js configuration");
-                // Merge logic here
-            }
-            create_nextjs_optimized_config()?;
-        }
-        "react" => {
-            create_nextjs_optimized_config()?;
-        }
-        "vue" => {
-            create_nextjs_optimized_config()?;
-        }
-        "svelte" => {
-            create_nextjs_optimized_config()?;
-        }
-        "angular" => {
-            create_nextjs_optimized_config()?;
-        }
-        _ => {}
-    }
-
+    changes.create("ecosystem.config.json", pm2_config);
     Ok(())
 }
 
@@ -3673,7 +5388,7 @@ fn check_docker_setup() -> io::Result<()> {
     println!("🐳 Checking Docker setup...");
     
     // Check if Docker Desktop is installed and running
-    match Command::new("docker").arg("info").output() {
+    match Command::new("docker").arg("info").run() {
         Ok(output) => {
             if output.status.success() {
                 println!("✅ Docker Desktop is running");
@@ -3695,66 +5410,6 @@ fn check_docker_setup() -> io::Result<()> {
     Ok(())
 }
 
-fn initialize_kubernetes() -> io::Result<()> {
-    println!("🚀 Initializing Kubernetes environment...");
-
-    // Check Docker Desktop and Kubernetes status
-    check_kubernetes_connection()?;
-    verify_kubernetes_setup()?;
-
-    // Create necessary namespaces
-    let namespaces = ["default", "monitoring", "ingress-nginx"];
-    for namespace in namespaces.iter() {
-        println!("📦 Creating namespace: {}", namespace);
-        Command::new("kubectl")
-            .args([
-                "create",
-                "namespace",
-                namespace,
-                "--dry-run=client",
-                "-o",
-                "yaml",
-            ])
-            .output()?;
-    }
-
-    // Install NGINX Ingress Controller
-    println!("📦 Installing NGINX Ingress Controller...");
-    Command::new("kubectl")
-        .args([
-            "apply",
-            "-f",
-            "https://raw.githubusercontent.com/kubernetes/ingress-nginx/controller-v1.8.1/deploy/static/provider/cloud/deploy.yaml",
-        ])
-        .output()?;
-
-    // Wait for Ingress Controller to be ready
-    println!("⏳ Waiting for NGINX Ingress Controller...");
-    for _ in 0..30 {
-        let status = Command::new("kubectl")
-            .args([
-                "get",
-                "pods",
-                "-n",
-                "ingress-nginx",
-                "-l",
-                "app.kubernetes.io/component=controller",
-                "-o",
-                "jsonpath='{.items[0].status.phase}'",
-            ])
-            .output()?;
-
-        if String::from_utf8_lossy(&status.stdout).contains("Running") {
-            println!("✅ NGINX Ingress Controller is ready");
-            break;
-        }
-        thread::sleep(Duration::from_secs(2));
-    }
-
-    println!("✅ Kubernetes environment initialized successfully!");
-    Ok(())
-}
-
 fn handle_kubernetes_error(error: io::Error) -> io::Error {
     match error.kind() {
         io::ErrorKind::NotFound => {
@@ -3779,160 +5434,78 @@ fn handle_kubernetes_error(error: io::Error) -> io::Error {
     }
 }
 
-fn create_app_files(app_type: &str, port: &str) -> io::Result<()> {
-    println!(
-        "📝 Creating application files for {} framework...",
-        app_type
-    );
-
-    // Create base directories
-    fs::create_dir_all("src")?;
-    fs::create_dir_all("public")?;
-    fs::create_dir_all("config")?;
-
-    // Create common configuration files
-    create_eslint_config(app_type)?;
-    create_prettier_config()?;
-    create_editor_config()?;
-    create_docker_compose(app_type)?;
-
-    // Route to specific framework file creation
-    match app_type {
-        "vue" => create_vue_files(port)?,
-        "react" => create_react_files(port)?,
-        "nuxt" => create_nuxt_files(port)?,
-        "svelte" => create_svelte_files(port)?,
-        "angular" => create_angular_files(port)?,
-        "astro" => create_astro_files(port)?,
-        "bun" => {
-            // Create basic Bun application files
-            let package_json = format!(
-                r#"{{
-                "name": "bun-app",
-                "version": "0.1.0",
-                "scripts": {{
-                    "dev": "bun run --hot src/index.ts",
-                    "start": "bun run src/index.ts",
-                    "build": "bun build src/index.ts --outdir=dist"
-                }},
-                "dependencies": {{}},
-                "devDependencies": {{
-                    "bun-types": "latest"
-                }}
-            }}"#
-            );
-            fs::write("package.json", package_json)?;
-
-            // Create basic TypeScript configuration
-            let tsconfig = r#"{
-                "compilerOptions": {
-                    "target": "esnext",
-                    "module": "esnext",
-                    "moduleResolution": "node",
-                    "types": ["bun-types"],
-                    "esModuleInterop": true,
-                    "skipLibCheck": true,
-                    "strict": true
-                }
-            }"#;
-            fs::write("tsconfig.json", tsconfig)?;
-
-            // Create main application file
-            let main_file = format!(
-                r#"import {{ serve }} from "bun";
-                const server = serve({{
-                    port: {},
-                    fetch(req) {{
-                        return new Response("Hello from Bun!");
-                    }},
-                }});
-                console.log(`Listening on http://localhost:{}`);
-                "#,
-                port, port
-            );
-            fs::write("src/index.ts", main_file)?;
-        }
-        _ => {
-            return Err(io::Error::new(
+/// Template variables for `init`: `--var` and the dedicated flags first,
+/// then the manifest's prompts and defaults.
+fn init_variables(
+    template: &templates::Template,
+    matches: &clap::ArgMatches,
+) -> io::Result<templates::Variables> {
+    let mut given = templates::Variables::new();
+    for pair in matches.values_of("var").into_iter().flatten() {
+        let (key, value) = pair.split_once('=').ok_or_else(|| {
+            io::Error::new(
                 io::ErrorKind::InvalidInput,
-                format!("Unsupported application type: {}", app_type),
-            ))
+                format!("Invalid --var '{}', expected KEY=VALUE", pair),
+            )
+        })?;
+        given.insert(key.trim().to_string(), value.to_string());
+    }
+    for (arg, key) in [("name", "name"), ("port", "port"), ("package-manager", "packageManager")] {
+        if let Some(value) = matches.value_of(arg) {
+            given.insert(key.to_string(), value.to_string());
         }
     }
 
-    // Create .gitignore
-    let gitignore = r#"# Dependencies
-    node_modules/
-    .pnp
-    .pnp.js
-
-    # Build outputs
-    dist
-    build
-    .next
-    out
-    .nuxt
-
-    # Environment variables
-    .env
-    .env.local
-    .env.*.local
-
-    # Logs
-    npm-debug.log*
-    yarn-debug.log*
-    yarn-error.log*
-
-    # Editor directories
-    .idea
-    .vscode
-    *.swp
-    *.swo
-
-    # OS
-    .DS_Store
-    Thumbs.db
-
-    # Build files
-    *.log
-    *.pid
-    *.seed
-
-    # Cache
-    .eslintcache
-    .cache
-    .parcel-cache
-
-    # Docker
-    Dockerfile
-    .dockerignore
-    docker-compose*.yml
-
-    # Temporary files
-    *.tmp
-    *.temp
-    .temp
-    .tmp
-
-    # Keep these files
-    !package.json
-    !package-lock.json
-    !yarn.lock
-    !next.config.js
-    !tsconfig.json
-    !public/
-    !src/
-    !app/
-    !pages/
-    !components/
-    !styles/
-    !lib/
-    !utils/
-    !hooks/
-    !services/
-    !api/
-    !types/"#;
-    fs::write(".gitignore", gitignore)?;
+    let interactive =
+        !matches.is_present("yes") && std::io::IsTerminal::is_terminal(&io::stdin());
+    let mut resolved = templates::resolve_variables(&template.manifest, &given, interactive)?;
+
+    let name = match resolved.remove("name") {
+        Some(name) => name,
+        None => std::env::current_dir()?
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    };
+    let port = resolved.remove("port").unwrap_or_else(|| "3000".to_string());
+    let package_manager = resolved
+        .remove("packageManager")
+        .unwrap_or_else(|| "npm".to_string());
+
+    let mut vars = templates::variables(&templates::package_name(&name), &port, &package_manager)?;
+    // Pins the version corepack gives CI and the Docker build
+    if let Some(spec) = templates::installed_package_manager(&package_manager) {
+        vars.insert(templates::PACKAGE_MANAGER_SPEC.to_string(), spec);
+    }
+    vars.extend(resolved);
+    Ok(vars)
+}
+
+fn create_app_files(
+    label: &str,
+    template: &templates::Template,
+    matches: &clap::ArgMatches,
+) -> io::Result<()> {
+    let vars = init_variables(template, matches)?;
+    println!("📝 Creating application files for {}...", label);
+
+    let files = template.selected_files(&vars)?;
+    for path in templates::write_files(
+        Path::new("."),
+        &files,
+        &vars,
+        template.manifest.catalog.as_deref(),
+    )? {
+        println!("   {}", path);
+    }
+
+    if matches.is_present("no-hooks") {
+        if !template.manifest.hooks.post_init.is_empty() {
+            println!("⏭️  Skipping post-init hooks");
+        }
+    } else {
+        template.run_hooks(&vars)?;
+    }
 
     println!("✅ Application files created successfully!");
     Ok(())
@@ -4024,8 +5597,8 @@ coverage
 .temp"#;
 
     // Write Docker files
-    fs::write("Dockerfile", dockerfile)?;
-    fs::write(".dockerignore", dockerignore)?;
+    effects::write("Dockerfile", dockerfile)?;
+    effects::write(".dockerignore", dockerignore)?;
 
     Ok(())
 }
@@ -4044,7 +5617,7 @@ fn deploy_with_docker(metadata: &AppMetadata) -> io::Result<()> {
             &format!("{}-app", metadata.app_name),
             "."
         ])
-        .status()?;
+        .run_status()?;
 
     if !build_status.success() {
         return Err(io::Error::new(
@@ -4062,7 +5635,7 @@ fn deploy_with_docker(metadata: &AppMetadata) -> io::Result<()> {
             &format!("{}:{}", metadata.port, metadata.port),
             &format!("{}-app", metadata.app_name)
         ])
-        .output()?;
+        .run()?;
 
     if !run_output.status.success() {
         return Err(io::Error::new(
@@ -4081,65 +5654,26 @@ fn deploy_with_docker(metadata: &AppMetadata) -> io::Result<()> {
     Ok(())
 }
 
-fn setup_horizontal_pod_autoscaler(app_name: &str, namespace: &str) -> io::Result<()> {
+fn setup_horizontal_pod_autoscaler(
+  app_name: &str,
+  namespace: &str,
+  scaling: &ScalingConfig,
+) -> io::Result<()> {
   println!("️  Setting up Horizontal Pod Autoscaler...");
 
   // Create HPA manifest
-  let hpa_manifest = format!(
-      r#"apiVersion: autoscaling/v2
-kind: HorizontalPodAutoscaler
-metadata:
-name: {}-hpa
-namespace: {}
-spec:
-scaleTargetRef:
-  apiVersion: apps/v1
-  kind: Deployment
-  name: {}-deployment
-minReplicas: 1
-maxReplicas: 10
-metrics:
-- type: Resource
-  resource:
-    name: cpu
-    target:
-      type: Utilization
-      averageUtilization: 70
-- type: Resource
-  resource:
-    name: memory
-    target:
-      type: Utilization
-      averageUtilization: 80
-behavior:
-  scaleUp:
-    stabilizationWindowSeconds: 60
-    policies:
-    - type: Pods
-      value: 2
-      periodSeconds: 60
-  scaleDown:
-    stabilizationWindowSeconds: 300
-    policies:
-    - type: Pods
-      value: 1
-      periodSeconds: 60"#,
-      app_name, namespace, app_name
-  );
+  autoscale::validate(scaling)?;
+  let hpa_manifest = autoscale::manifest(app_name, namespace, scaling);
 
   // Write HPA manifest to file
-  fs::write("k8s-hpa.yaml", hpa_manifest)?;
+  let path = artifacts::write("k8s-hpa.yaml", hpa_manifest)?;
 
   // Apply HPA manifest
-  let output = Command::new("kubectl")
-      .args([
-          "apply",
-          "-f",
-          "k8s-hpa.yaml",
-          "-n",
-          namespace,
-      ])
-      .output()?;
+  let output = kubectl()
+      .args(["apply", "-f"])
+      .arg(&path)
+      .args(["-n", namespace])
+      .run()?;
 
   if !output.status.success() {
       let error = String::from_utf8_lossy(&output.stderr);
@@ -4150,7 +5684,7 @@ behavior:
   }
 
   // Verify HPA creation
-  let verify_output = Command::new("kubectl")
+  let verify_output = kubectl()
       .args([
           "get",
           "hpa",
@@ -4160,7 +5694,7 @@ behavior:
           "-o",
           "jsonpath={.status.conditions[?(@.type=='Ready')].status}",
       ])
-      .output()?;
+      .run()?;
 
   if !verify_output.status.success() {
       println!("⚠️  Warning: HPA created but status verification failed");
@@ -4177,7 +5711,7 @@ behavior:
   println!("⏳ Waiting for HPA to be ready...");
   let mut retries = 0;
   while retries < 30 {
-      let status = Command::new("kubectl")
+      let status = kubectl()
           .args([
               "get",
               "hpa",
@@ -4187,7 +5721,7 @@ behavior:
               "-o",
               "jsonpath={.status.currentReplicas}",
           ])
-          .output()?;
+          .run()?;
 
       if status.status.success() {
           println!("✅ HPA is active and monitoring the deployment");
@@ -4213,9 +5747,9 @@ fn update_cli() -> io::Result<()> {
     // Create temporary directory with better error handling
     let temp_dir = std::env::temp_dir().join("rustify_update");
     if temp_dir.exists() {
-        fs::remove_dir_all(&temp_dir)?;
+        effects::remove_dir_all(&temp_dir)?;
     }
-    fs::create_dir_all(&temp_dir)?;
+    effects::create_dir_all(&temp_dir)?;
     
     // Enhanced install script with better error handling
     let install_script = format!(r#"#!/bin/bash
@@ -4302,15 +5836,15 @@ fi"#, current_version, std::env::consts::OS, std::env::consts::ARCH);
 
     // Write and execute script with proper error handling
     let script_path = temp_dir.join("update.sh");
-    fs::write(&script_path, install_script)?;
+    effects::write(&script_path, install_script)?;
     set_executable_permissions(&script_path)?;  // Use our new function here
 
     let status = Command::new("bash")
         .arg(&script_path)
-        .status()?;
+        .run_status()?;
 
     // Cleanup temp directory
-    fs::remove_dir_all(&temp_dir)?;
+    effects::remove_dir_all(&temp_dir)?;
 
     if !status.success() {
         return Err(io::Error::new(
@@ -4333,7 +5867,7 @@ fn check_for_updates() -> io::Result<bool> {
             "-s",
             "https://api.github.com/repos/duggal1/rustify/releases/latest"
         ])
-        .output()?;
+        .run()?;
     
     let response = String::from_utf8_lossy(&output.stdout);
     if let Ok(json) = serde_json::from_str::<serde_json::Value>(&response) {
@@ -4353,43 +5887,13 @@ fn check_for_updates() -> io::Result<bool> {
 }
 
 fn detect_project_type() -> io::Result<String> {
-    if Path::new("package.json").exists() {
-        let content = fs::read_to_string("package.json")?;
-        let pkg: serde_json::Value = serde_json::from_str(&content)?;
-
-        if let Some(deps) = pkg.get("dependencies") {
-            if deps.get("next").is_some() {
-                return Ok("next".to_string());
-            } else if deps.get("react").is_some() {
-                return Ok("react".to_string());
-            } else if deps.get("vue").is_some() {
-                return Ok("vue".to_string());
-            } else if deps.get("svelte").is_some() {
-                return Ok("svelte".to_string());
-            } else if deps.get("astro").is_some() {
-                return Ok("astro".to_string());
-            } else if deps.get("@remix-run/react").is_some() {
-                return Ok("remix".to_string());
-            }
-        }
-    }
-
-    // Check for specific framework files
-    if Path::new("next.config.js").exists() {
-        return Ok("next".to_string());
-    } else if Path::new("vite.config.js").exists() && Path::new("svelte.config.js").exists() {
-        return Ok("svelte".to_string());
-    } else if Path::new("astro.config.mjs").exists() {
-        return Ok("astro".to_string());
-    }
-
-    Ok("bun".to_string()) // Default to Bun
+    detect::detect().map(|detection| detection.framework.to_string())
 }
 
 fn verify_installation() -> io::Result<bool> {
     let output = Command::new("rustify")
         .arg("--version")
-        .output()?;
+        .run()?;
     
     Ok(output.status.success())
 }
@@ -4405,7 +5909,11 @@ fn detect_app_name() -> io::Result<String> {
         }
     }
 
-    // If package.json doesn't exist or doesn't have a name, use directory name
+    if let Some(name) = stacks::app_name() {
+        return Ok(name);
+    }
+
+    // Without a name in the project manifest, use directory name
     if let Some(dir_name) = std::env::current_dir()?.file_name() {
         if let Some(name) = dir_name.to_str() {
             return Ok(name.to_string());
@@ -4416,30 +5924,626 @@ fn detect_app_name() -> io::Result<String> {
     Ok("app".to_string())
 }
 
-fn handle_env_files() -> io::Result<()> {
-    // Copy all .env files if they exist
-    let env_files = [".env", ".env.local", ".env.development", ".env.production"];
-    
-    for env_file in env_files.iter() {
-        if Path::new(env_file).exists() {
-            println!("📄 Found {} file", env_file);
+fn handle_env_files(is_prod: bool, config: &EnvConfig) -> io::Result<envfile::ProjectEnv> {
+    let environment = if is_prod { "production" } else { "development" };
+    let mut project_env = envfile::load(environment, &config.secret_keys)?;
+
+    for env_file in &project_env.files {
+        println!("📄 Found {} file", env_file);
+    }
+
+    // Encrypted store secrets are only ever decrypted into memory
+    if secrets::store_exists() {
+        let store = secrets::SecretStore::open(false)?;
+        let values = store.decrypt_all()?;
+        println!("🔑 Decrypted {} secrets from {}", values.len(), secrets::STORE_FILE);
+        project_env.add_secrets(values, &fs::read(secrets::STORE_FILE)?);
+    }
+    if project_env.files.is_empty() && project_env.secret.is_empty() {
+        println!("ℹ️  No .env files found, deploying without extra environment variables");
+    } else {
+        println!(
+            "🔐 Loaded {} variables ({} secret)",
+            project_env.plain.len() + project_env.secret.len(),
+            project_env.secret.len()
+        );
+    }
+
+    Ok(project_env)
+}
+
+fn report_clean(root: &Path, environment: Option<&str>) -> io::Result<()> {
+    let (removed, kept) = artifacts::clean(root, environment)?;
+    for path in &kept {
+        println!("⚠️  Kept {} (edited since rustify wrote it)", path.display());
+    }
+    println!("🧹 Removed {} generated files from {}", removed, root.display());
+    Ok(())
+}
+
+fn handle_clean_command(matches: &clap::ArgMatches) -> io::Result<()> {
+    let config = load_project_config()?;
+    report_clean(Path::new(config.build_dir()), matches.value_of("env"))
+}
+
+/// Deletes what the last deploy started: the resources of the recorded prod
+/// manifests when `namespace` is set, the dev container otherwise. The
+/// KUBE_CONTEXT must already be pinned for prod. `wait` blocks until the
+/// objects are gone, so they can be recreated right away.
+fn remove_deployment(
+    app_name: &str,
+    root: &Path,
+    namespace: Option<&str>,
+    wait: bool,
+) -> io::Result<()> {
+    let namespace = match namespace {
+        Some(namespace) => namespace,
+        None => {
+            let container = format!("{}-container", app_name);
+            Command::new("docker").args(["rm", "-f", &container]).run()?;
+            return Ok(());
+        }
+    };
+
+    for path in artifacts::owned(root, Some("prod"))?.iter().rev() {
+        if path.extension().and_then(|e| e.to_str()) != Some("yaml") || !path.exists() {
+            continue;
+        }
+        let output = kubectl()
+            .args(["delete", "--ignore-not-found", &format!("--wait={}", wait), "-f"])
+            .arg(path)
+            .run()?;
+        if !output.status.success() {
+            println!(
+                "⚠️  Could not delete resources from {}: {}",
+                path.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+    }
+
+    // Env resources are piped to kubectl and never written to disk
+    kubectl()
+        .args([
+            "delete",
+            &format!("configmap/{}-env", app_name),
+            &format!("secret/{}-env-secrets", app_name),
+            "-n",
+            namespace,
+            "--ignore-not-found",
+            &format!("--wait={}", wait),
+        ])
+        .run()?;
+    Ok(())
+}
+
+/// Deletes what the recorded manifests of an environment created, then
+/// cleans the files themselves.
+fn handle_destroy_command(matches: &clap::ArgMatches) -> io::Result<()> {
+    let is_prod = matches.is_present("prod");
+    let environment = if is_prod { "prod" } else { "dev" };
+    let mut config = load_project_config()?;
+    if let Some(context) = matches.value_of("context") {
+        config.kubernetes.context = Some(context.to_string());
+    }
+    if let Some(namespace) = matches.value_of("namespace") {
+        config.kubernetes.namespace = Some(namespace.to_string());
+    }
+    let app_name = detect_app_name()?;
+    let root = Path::new(config.build_dir());
+
+    if is_prod {
+        let target = resolve_kube_target(&config.kubernetes)?;
+        confirm_production_context(&target, &config.kubernetes, matches.is_present("yes"))?;
+        let _ = KUBE_CONTEXT.set(target.context.clone());
+        println!("🗑️  Removing {} from {}/{}", app_name, target.context, target.namespace);
+        remove_deployment(&app_name, root, Some(&target.namespace), false)?;
+    } else {
+        println!("🗑️  Removing container {}-container", app_name);
+        remove_deployment(&app_name, root, None, false)?;
+    }
+
+    report_clean(root, Some(environment))
+}
+
+fn handle_export_command(matches: &clap::ArgMatches) -> io::Result<()> {
+    let (format, sub_m) = match matches.subcommand() {
+        Some(subcommand) => subcommand,
+        None => {
+            println!("Use 'rustify export --help' for usage information.");
+            return Ok(());
+        }
+    };
+
+    let dir = Path::new(sub_m.value_of("dir").unwrap());
+    let non_empty = dir.exists() && dir.read_dir()?.next().is_some();
+    if non_empty && !sub_m.is_present("force") {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} is not empty; pass --force to overwrite", dir.display()),
+        ));
+    }
+
+    let config = load_project_config()?;
+    let app_name = detect_app_name()?;
+    let ctx = export::ExportContext {
+        app_name: &app_name,
+        port: sub_m.value_of("port").unwrap_or("3000"),
+        config: &config,
+        static_files: detect_project_type().is_ok_and(|t| serves_static_files(&t)),
+    };
+
+    match format {
+        "helm" => {
+            let mode = if sub_m.is_present("prod") { "prod" } else { "dev" };
+            export::write_helm_chart(dir, &ctx, mode)?;
+            println!("📦 Helm chart written to {}", dir.display());
+            export::validate_helm_chart(dir)
+        }
+        "kustomize" => {
+            export::write_kustomize(dir, &ctx)?;
+            println!("📦 Kustomize base and overlays written to {}", dir.display());
+            export::validate_kustomize(dir)
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Renders what `deploy` would create and diffs it against the live cluster,
+/// or the running container in dev mode. Returns whether anything differs.
+fn handle_plan_command(matches: &clap::ArgMatches) -> io::Result<i32> {
+    let is_prod = matches.is_present("prod");
+    let port = matches.value_of("port").unwrap_or("3000");
+    let mut config = load_project_config()?;
+    if let Some(context) = matches.value_of("context") {
+        config.kubernetes.context = Some(context.to_string());
+    }
+    if let Some(namespace) = matches.value_of("namespace") {
+        config.kubernetes.namespace = Some(namespace.to_string());
+    }
+
+    let app_name = detect_app_name()?;
+    let project_env = handle_env_files(is_prod, &config.env)?;
+
+    if !is_prod {
+        let container = format!("{}-container", app_name);
+        println!("{}", GradientText::cyber(&format!("🔎 Planning Docker container {}", container)));
+        let changes = plan::docker_changes(&container, &app_name, port, &project_env)?;
+        plan::print_changes(&changes);
+        return Ok(plan::exit_code(&changes));
+    }
+
+    let target = resolve_kube_target(&config.kubernetes)?;
+    let _ = KUBE_CONTEXT.set(target.context.clone());
+    let namespace = target.namespace.clone();
+    let mode = "prod";
+    let replicas = config.scaling.min_instances.max(1) as i32;
+
+    let image = planned_cluster_image(&app_name, &config)?;
+    let (env_sources, env_manifests) = render_env_resources(&app_name, &namespace, &project_env);
+    let server = verify_cluster_api(&target)?;
+    let mut availability = config.kubernetes.availability.resolve(mode, replicas)?;
+    availability.sleep_action = pre_stop_sleep_action(server, &availability);
+    let static_files = detect_project_type().is_ok_and(|t| serves_static_files(&t));
+
+    let mut manifests = Vec::new();
+    if owns_namespace(&namespace)? {
+        manifests.push(render_quota(&namespace, mode, &config, static_files));
+    }
+    manifests.extend(env_manifests);
+    manifests.extend(
+        render_kubernetes_manifests(&Workload {
+            app_name: &app_name,
+            port,
+            replicas,
+            namespace: &namespace,
+            mode,
+            image: &image,
+            env_sources: &env_sources,
+            availability: &availability,
+            static_files,
+            health_path: &config.kubernetes.health_path,
+        })
+        .into_iter()
+        .map(|(_, manifest)| manifest),
+    );
+    manifests.push(render_network_policy(
+        &app_name,
+        &namespace,
+        port,
+        &config.kubernetes.ingress_namespace,
+        config.kubernetes.egress.as_deref(),
+    ));
+    manifests.push(render_ingress(&app_name, &namespace, port, &config.ingress));
+
+    // Resources deploy leaves behind are only reported when it would delete
+    // or replace them
+    let mut candidates = vec![
+        plan::RemovalCandidate {
+            kind: "PodDisruptionBudget",
+            resource: "poddisruptionbudget",
+            name: format!("{}-pdb", app_name),
+        },
+        plan::RemovalCandidate {
+            kind: "ConfigMap",
+            resource: "configmap",
+            name: format!("{}-env", app_name),
+        },
+        plan::RemovalCandidate {
+            kind: "Secret",
+            resource: "secret",
+            name: format!("{}-env-secrets", app_name),
+        },
+    ];
+    if matches.is_present("rpl") {
+        autoscale::validate(&config.scaling)?;
+        manifests.push(autoscale::manifest(&app_name, &namespace, &config.scaling));
+        if config.scaling.keda.is_none() {
+            candidates.push(plan::RemovalCandidate {
+                kind: "ScaledObject",
+                resource: "scaledobject",
+                name: format!("{}-scaler", app_name),
+            });
+        }
+    }
+
+    println!(
+        "{}",
+        GradientText::cyber(&format!(
+            "🔎 Planning {} in {} (context {})",
+            app_name, namespace, target.context
+        ))
+    );
+    let changes = plan::kubernetes_changes(&manifests, &namespace, &candidates)?;
+    plan::print_changes(&changes);
+    Ok(plan::exit_code(&changes))
+}
+
+fn handle_scale_command(matches: &clap::ArgMatches) -> io::Result<()> {
+    if !matches.is_present("show") {
+        println!("Use 'rustify scale --show' to explain the current autoscaling decisions.");
+        return Ok(());
+    }
+
+    let mut config = load_project_config()?;
+    if let Some(context) = matches.value_of("context") {
+        config.kubernetes.context = Some(context.to_string());
+    }
+    if let Some(namespace) = matches.value_of("namespace") {
+        config.kubernetes.namespace = Some(namespace.to_string());
+    }
+    let target = resolve_kube_target(&config.kubernetes)?;
+    let _ = KUBE_CONTEXT.set(target.context.clone());
+
+    let app_name = detect_app_name()?;
+    let hpa = autoscale::hpa_name(&app_name);
+    let output = kubectl()
+        .args(["get", "hpa", &hpa, "-n", &target.namespace, "-o", "json"])
+        .run()?;
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "No autoscaler {} in namespace {}. Deploy with --rpl first",
+                hpa, target.namespace
+            ),
+        ));
+    }
+
+    println!("{}", GradientText::cyber(&format!("📊 Autoscaler {}/{}", target.namespace, hpa)));
+    autoscale::explain(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn handle_detect_command(matches: &clap::ArgMatches) -> io::Result<()> {
+    let detection = detect::detect()?;
+    if !matches.is_present("explain") {
+        println!("🔍 Detected {} project", detection.framework);
+        return Ok(());
+    }
+
+    println!("🔍 Detected {} project (score {})", detection.framework, detection.score);
+
+    println!("   Rendering:      {}", detection.rendering);
+    println!(
+        "   Build output:   {}",
+        detection.output_dir.as_deref().unwrap_or("none")
+    );
+    let start = match (&detection.start_command, detection.rendering.is_static()) {
+        (Some(command), _) => command.clone(),
+        (None, true) => "none, static files are served by a web server".to_string(),
+        (None, false) => "unknown, add a start script to package.json".to_string(),
+    };
+    println!("   Start command:  {}", start);
+    println!("   Port:           {} ({})", detection.port, detection.port_source);
+
+    println!("\n📋 Signals:");
+    for candidate in &detection.candidates {
+        match candidate.part_of {
+            Some(outer) => println!("   {:<8} {:>3}  part of {}", candidate.name, candidate.score, outer),
+            None => println!("   {:<8} {:>3}", candidate.name, candidate.score),
+        }
+        for signal in &candidate.signals {
+            println!("      {}", signal);
         }
     }
+    Ok(())
+}
+
+fn handle_optimize_command(matches: &clap::ArgMatches) -> io::Result<()> {
+    let config = load_project_config()?;
+    artifacts::init(config.build_dir(), "dev");
+    let backups = artifacts::root().join("backups");
+
+    if matches.is_present("undo") {
+        for path in optimize::undo(&backups)? {
+            println!("↩️  Restored {}", path);
+        }
+        println!("✅ Last optimize run undone");
+        return Ok(());
+    }
+
+    if !Path::new("package.json").exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "No package.json found in the current directory",
+        ));
+    }
+    let app_type = match matches.value_of("type") {
+        Some(app_type) => app_type.to_string(),
+        None => detect_project_type()?,
+    };
+    println!("🔍 Proposing optimizations for {} project...", app_type);
+
+    let mut changes = optimize::Changes::default();
+    optimize_existing_project(&app_type, &mut changes)?;
+    changes.print_diff();
+    if changes.is_empty() {
+        println!("✅ Nothing to change");
+        return Ok(());
+    }
+
+    if effects::dry_run() {
+        println!("✅ Dry run finished, nothing was changed");
+        return Ok(());
+    }
+    if !matches.is_present("yes") {
+        if !std::io::IsTerminal::is_terminal(&io::stdin()) {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "Refusing to change files without confirmation. Re-run with --yes.",
+            ));
+        }
+        print!("Apply these changes? [y/N] ");
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+            println!("❎ No changes applied");
+            return Ok(());
+        }
+    }
+
+    artifacts::ensure_gitignored(&format!("/{}/", artifacts::root().display()))?;
+    let backup = changes.apply(&backups)?;
+    println!(
+        "✅ Optimizations applied. Previous files are saved in {}",
+        backup.display()
+    );
+    println!("↩️  Run 'rustify optimize --undo' to restore them");
+    Ok(())
+}
+
+fn handle_templates_command(matches: &clap::ArgMatches) -> io::Result<()> {
+    match matches.subcommand() {
+        Some(("list", _)) => {
+            let registry = templates::Registry::load()?;
+            println!("📦 Templates:");
+            for name in templates::BUILTIN {
+                if !registry.templates.contains_key(*name) {
+                    println!("   {:<14} built-in", name);
+                }
+            }
+            for (name, entry) in &registry.templates {
+                println!("   {:<14} {}", name, entry.source);
+                if !entry.description.is_empty() {
+                    println!("   {:<14} {}", "", entry.description);
+                }
+            }
+        }
+        Some(("add", sub_m)) => {
+            let name = sub_m.value_of("name").unwrap();
+            let mut registry = templates::Registry::load()?;
+            registry.add(name, sub_m.value_of("source").unwrap(), sub_m.value_of("description"))?;
+            registry.save()?;
+            if templates::BUILTIN.contains(&name) {
+                println!("ℹ️  '{}' now replaces the built-in template", name);
+            }
+            println!("✅ Registered template {}", name);
+        }
+        Some(("remove", sub_m)) => {
+            let name = sub_m.value_of("name").unwrap();
+            let mut registry = templates::Registry::load()?;
+            if !registry.remove(name)? {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("Template '{}' is not registered", name),
+                ));
+            }
+            registry.save()?;
+            println!("✅ Removed template {}", name);
+        }
+        Some(("catalog", sub_m)) => {
+            let catalog = catalog::Catalog::load()?;
+            let framework = sub_m.value_of("framework");
+            for (name, set) in &catalog.frameworks {
+                if framework.is_some_and(|f| f != name) {
+                    continue;
+                }
+                println!("📦 {}", name);
+                for (package, entry) in set {
+                    println!("   {:<32} {}", package, entry.range());
+                }
+            }
+            let problems = catalog.problems(framework);
+            for problem in &problems {
+                println!("⚠️  {}", problem);
+            }
+            if problems.is_empty() {
+                println!("✅ All peer dependencies are satisfied");
+            }
+        }
+        Some(("update-catalog", sub_m)) => {
+            if sub_m.is_present("reset") {
+                let path = catalog::path()?;
+                if path.exists() {
+                    effects::remove_file(&path)?;
+                }
+                println!("✅ Using the built-in catalog");
+                return Ok(());
+            }
 
-    // Create default .env if none exists
-    if !env_files.iter().any(|f| Path::new(f).exists()) {
-        println!("⚠️  No .env files found, creating default .env");
-        fs::write(".env", "NODE_ENV=development\nPORT=3000\n")?;
+            let mut catalog = catalog::Catalog::load()?;
+            let changes = match (sub_m.value_of("registry"), sub_m.value_of("file")) {
+                (Some(registry), _) => {
+                    println!("🔍 Checking {} for newer releases...", registry);
+                    catalog.update_from_registry(registry, sub_m.is_present("latest"))?
+                }
+                (None, Some(file)) => catalog.update_from_file(Path::new(file))?,
+                (None, None) => unreachable!("clap requires a source"),
+            };
+            if changes.is_empty() {
+                println!("✅ Catalog is up to date");
+                return Ok(());
+            }
+            for change in &changes {
+                println!("   {}", change);
+            }
+
+            let problems = catalog.problems(None);
+            for problem in &problems {
+                println!("⚠️  {}", problem);
+            }
+            if !problems.is_empty() && !sub_m.is_present("force") {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Catalog not saved: the new versions are incompatible. Re-run with --force to save anyway.",
+                ));
+            }
+            let path = catalog.save()?;
+            println!("✅ Updated {} entries in {}", changes.len(), path.display());
+        }
+        _ => println!("Use 'rustify templates --help' for usage information."),
     }
+    Ok(())
+}
 
+fn handle_secrets_command(matches: &clap::ArgMatches) -> io::Result<()> {
+    match matches.subcommand() {
+        Some(("set", sub_m)) => {
+            let name = sub_m.value_of("name").unwrap();
+            let value = match sub_m.value_of("value") {
+                Some(value) => value.to_string(),
+                None => {
+                    // Reading from stdin keeps the value out of shell history
+                    let mut value = String::new();
+                    io::stdin().read_to_string(&mut value)?;
+                    value.trim_end_matches(&['\r', '\n'][..]).to_string()
+                }
+            };
+            let mut store = secrets::SecretStore::open(true)?;
+            store.set(name, &value)?;
+            println!("✅ Stored {} in {}", name, secrets::STORE_FILE);
+        }
+        Some(("get", sub_m)) => {
+            let name = sub_m.value_of("name").unwrap();
+            match secrets::SecretStore::open(false)?.get(name)? {
+                Some(value) => println!("{}", value),
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("Secret '{}' not found", name),
+                    ))
+                }
+            }
+        }
+        Some(("list", _)) => {
+            if !secrets::store_exists() {
+                println!("ℹ️  No secrets stored yet");
+                return Ok(());
+            }
+            for name in secrets::SecretStore::open(false)?.names() {
+                println!("{}", name);
+            }
+        }
+        Some(("rm", sub_m)) => {
+            let name = sub_m.value_of("name").unwrap();
+            if secrets::SecretStore::open(false)?.remove(name)? {
+                println!("🗑️  Removed {}", name);
+            } else {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("Secret '{}' not found", name),
+                ));
+            }
+        }
+        _ => println!("Use 'rustify secrets --help' for usage information."),
+    }
     Ok(())
 }
 
+fn apply_env_resources(
+    app_name: &str,
+    namespace: &str,
+    project_env: &envfile::ProjectEnv,
+) -> io::Result<EnvSources> {
+    let (sources, manifests) = render_env_resources(app_name, namespace, project_env);
+    for manifest in &manifests {
+        apply_manifest_from_stdin(manifest)?;
+    }
+    Ok(sources)
+}
+
+/// ConfigMap / Secret manifests for the project env. They hold values, so
+/// callers pipe them to kubectl instead of writing them to disk.
+fn render_env_resources(
+    app_name: &str,
+    namespace: &str,
+    project_env: &envfile::ProjectEnv,
+) -> (EnvSources, Vec<String>) {
+    let mut sources = EnvSources {
+        hash: project_env.hash.clone(),
+        ..Default::default()
+    };
+    let mut manifests = Vec::new();
+
+    if !project_env.plain.is_empty() {
+        let name = format!("{}-env", app_name);
+        manifests.push(envfile::config_map_manifest(
+            &name,
+            namespace,
+            &project_env.plain,
+        ));
+        sources.config_map = Some(name);
+    }
+
+    if !project_env.secret.is_empty() {
+        let name = format!("{}-env-secrets", app_name);
+        manifests.push(envfile::secret_manifest(
+            &name,
+            namespace,
+            &project_env.secret,
+        ));
+        sources.secret = Some(name);
+    }
+
+    (sources, manifests)
+}
+
 // Replace the permissions setting code with a cross-platform version
 fn set_executable_permissions(path: &Path) -> io::Result<()> {
     #[cfg(unix)]
     {
-        fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))?;
+        effects::set_permissions(path, std::fs::Permissions::from_mode(0o755))?;
     }
     #[cfg(windows)]
     {
@@ -4447,7 +6551,111 @@ fn set_executable_permissions(path: &Path) -> io::Result<()> {
         // But we'll make sure the file is writable
         let mut perms = fs::metadata(path)?.permissions();
         perms.set_readonly(false);
-        fs::set_permissions(path, perms)?;
+        effects::set_permissions(path, perms)?;
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::apimachinery::pkg::version::Info;
+
+    fn info(major: &str, minor: &str, git_version: &str) -> Info {
+        Info {
+            major: major.to_string(),
+            minor: minor.to_string(),
+            git_version: git_version.to_string(),
+            ..Info::default()
+        }
+    }
+
+    #[test]
+    fn reads_server_versions() {
+        let version = |major, minor| Some(ServerVersion { major, minor });
+        assert_eq!(ServerVersion::parse(&info("1", "30", "v1.30.0")), version(1, 30));
+        assert_eq!(ServerVersion::parse(&info("1", "29+", "v1.29.4-gke.1")), version(1, 29));
+        assert_eq!(ServerVersion::parse(&info("", "", "v1.31.2+k3s1")), version(1, 31));
+        assert_eq!(ServerVersion::parse(&info("", "", "unknown")), None);
+    }
+
+    #[test]
+    fn sleep_action_needs_kubernetes_1_30() {
+        let availability = AvailabilityConfig::default().resolve("prod", 3).unwrap();
+        let at = |minor| Some(ServerVersion { major: 1, minor });
+        assert!(pre_stop_sleep_action(at(30), &availability));
+        assert!(pre_stop_sleep_action(at(31), &availability));
+        assert!(!pre_stop_sleep_action(at(29), &availability));
+        assert!(!pre_stop_sleep_action(None, &availability));
+    }
+
+    #[test]
+    fn default_quota_fits_the_autoscaler_and_the_surge() {
+        let scaling = ScalingConfig::default();
+        assert_eq!(peak_pods(&scaling), 7);
+        let quota = QuotaConfig::for_pods("prod", false, peak_pods(&scaling));
+        assert_eq!((quota.requests.cpu.as_str(), quota.requests.memory.as_str()), ("7", "14Gi"));
+        assert_eq!((quota.limits.cpu.as_str(), quota.limits.memory.as_str()), ("14", "28Gi"));
+
+        let scaling = ScalingConfig {
+            min_instances: 3,
+            max_instances: 1,
+            ..ScalingConfig::default()
+        };
+        assert_eq!(peak_pods(&scaling), 4);
+    }
+
+    #[test]
+    fn multiplies_resource_amounts() {
+        let amounts = ResourceAmounts::new("500m", "512Mi");
+        assert_eq!(amounts.times(3).cpu, "1500m");
+        assert_eq!(amounts.times(4).cpu, "2");
+        assert_eq!(amounts.times(2).memory, "1Gi");
+        assert_eq!(ResourceAmounts::new("0.5", "1G").times(2).memory, "2G");
+        assert_eq!(ResourceAmounts::new("0.5", "1G").times(3).cpu, "1.5");
+    }
+
+    #[test]
+    fn network_policy_allows_all_egress_by_default() {
+        let policy = render_network_policy("shop", "web", "3000", "ingress-nginx", None);
+        assert!(policy.contains("    - protocol: UDP\n      port: 53\n    - protocol: TCP\n      port: 53"));
+        assert!(policy.ends_with("\n  - {}"), "{}", policy);
+    }
+
+    #[test]
+    fn network_policy_limits_egress_to_configured_rules() {
+        let rules = [
+            EgressRule {
+                namespace: Some("databases".to_string()),
+                ports: vec![27017],
+                ..EgressRule::default()
+            },
+            EgressRule {
+                cidr: Some("203.0.113.0/24".to_string()),
+                ..EgressRule::default()
+            },
+        ];
+        let policy = render_network_policy("shop", "web", "3000", "ingress-nginx", Some(&rules));
+        assert!(!policy.contains("- {}"));
+        assert!(policy.ends_with(
+            r#"
+  - to:
+    - namespaceSelector:
+        matchLabels:
+          kubernetes.io/metadata.name: databases
+    ports:
+    - protocol: TCP
+      port: 27017
+  - to:
+    - ipBlock:
+        cidr: 203.0.113.0/24"#
+        ));
+    }
+
+    #[test]
+    fn renders_the_pre_stop_hook() {
+        assert_eq!(render_pre_stop(0, true), "");
+        assert!(render_pre_stop(10, true).contains("sleep:\n              seconds: 10"));
+        assert!(render_pre_stop(10, false).contains(r#"command: ["sleep", "10"]"#));
+    }
+}
@@ -0,0 +1,341 @@
+use super::{effects, jsconfig};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+const BACKUP_MANIFEST: &str = "backup.json";
+
+/// Files `rustify optimize` would write, collected up front so they can be
+/// shown as a diff and backed up before anything is touched.
+#[derive(Debug, Default)]
+pub struct Changes {
+    files: BTreeMap<PathBuf, String>,
+    /// Files or keys left alone, and why.
+    notes: Vec<String>,
+}
+
+/// What a backup holds for one path.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+enum Backup {
+    /// The original contents are stored in the backup directory.
+    Saved,
+    /// The file did not exist; undo deletes it.
+    Created,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BackupManifest {
+    files: BTreeMap<String, Backup>,
+}
+
+impl Changes {
+    /// Proposes new contents for `path`, replacing whatever is there.
+    pub fn update<P: AsRef<Path>, C: Into<String>>(&mut self, path: P, contents: C) {
+        self.files.insert(path.as_ref().to_path_buf(), contents.into());
+    }
+
+    /// Proposes `path` only when the project has no such file yet, so
+    /// existing configs are never replaced wholesale.
+    pub fn create<P: AsRef<Path>, C: Into<String>>(&mut self, path: P, contents: C) {
+        let path = path.as_ref();
+        if path.exists() {
+            self.notes
+                .push(format!("{} exists, left unchanged", path.display()));
+        } else {
+            self.update(path, contents);
+        }
+    }
+
+    /// Merges `edits` into the first of `candidates` that exists (e.g.
+    /// `next.config.js`, `next.config.mjs`). Without one, `template` is
+    /// written to the first candidate. A config that cannot be merged
+    /// safely is left alone and reported.
+    pub fn config(&mut self, candidates: &[&str], template: Option<&str>, edits: &[jsconfig::Edit]) {
+        let existing = candidates.iter().find(|c| Path::new(c).is_file());
+        let path = match (existing, template) {
+            (Some(path), _) => *path,
+            (None, Some(template)) => return self.update(candidates[0], template),
+            (None, None) => return,
+        };
+        let merged = fs::read_to_string(path)
+            .map_err(|e| jsconfig::Refusal(e.to_string()))
+            .and_then(|src| jsconfig::merge(&src, edits));
+        match merged {
+            Ok((merged, notes)) => {
+                self.notes
+                    .extend(notes.into_iter().map(|n| format!("{}: {}", path, n)));
+                self.update(path, merged);
+            }
+            Err(refusal) => {
+                let keys = edits
+                    .iter()
+                    .map(|e| format!("{} = {}", e.path.join("."), e.value))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                self.notes.push(format!(
+                    "{} not changed: {}. Set these by hand if you want them: {}",
+                    path, refusal, keys
+                ));
+            }
+        }
+    }
+
+    /// Changed files with their current contents (`None` when new).
+    fn changed(&self) -> Vec<(&PathBuf, Option<String>, &String)> {
+        self.files
+            .iter()
+            .map(|(path, new)| (path, fs::read_to_string(path).ok(), new))
+            .filter(|(_, old, new)| old.as_ref() != Some(*new))
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changed().is_empty()
+    }
+
+    pub fn print_diff(&self) {
+        for line in self.diff() {
+            println!("{}", line);
+        }
+        for note in &self.notes {
+            println!("ℹ️  {}", note);
+        }
+    }
+
+    /// `~ path` for updated and `+ path` for new files, each followed by
+    /// its indented unified diff.
+    fn diff(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for (path, old, new) in self.changed() {
+            let name = path.display().to_string();
+            match old {
+                Some(_) => lines.push(format!("~ {}", name)),
+                None => lines.push(format!("+ {}", name)),
+            }
+            let old = old.unwrap_or_default();
+            for line in effects::unified_diff(&name, &old, new) {
+                lines.push(format!("    {}", line));
+            }
+        }
+        lines
+    }
+
+    /// Backs up every file about to change into a new directory below
+    /// `backups`, then writes the changes. Returns the backup directory.
+    pub fn apply(&self, backups: &Path) -> io::Result<PathBuf> {
+        let stamp = Local::now().format("%Y%m%d-%H%M%S").to_string();
+        let mut backup = backups.join(&stamp);
+        // Runs within the same second must not share a backup
+        let mut n = 1;
+        while backup.exists() {
+            backup = backups.join(format!("{}-{}", stamp, n));
+            n += 1;
+        }
+        let mut manifest = BackupManifest::default();
+
+        effects::create_dir_all(&backup)?;
+        for (path, old, _) in self.changed() {
+            let key = path.to_string_lossy().replace('\\', "/");
+            match old {
+                Some(old) => {
+                    let saved = backup.join("files").join(stored(path));
+                    if let Some(parent) = saved.parent() {
+                        effects::create_dir_all(parent)?;
+                    }
+                    effects::write(&saved, old)?;
+                    manifest.files.insert(key, Backup::Saved);
+                }
+                None => {
+                    manifest.files.insert(key, Backup::Created);
+                }
+            }
+        }
+        let json = serde_json::to_string_pretty(&manifest)
+            .map_err(io::Error::other)?;
+        effects::write(backup.join(BACKUP_MANIFEST), json + "\n")?;
+
+        self.write()?;
+        Ok(backup)
+    }
+
+    /// Writes the changes without a backup, for freshly generated projects.
+    pub fn write(&self) -> io::Result<()> {
+        for (path, _, new) in self.changed() {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                effects::create_dir_all(parent)?;
+            }
+            effects::write_atomic(path, new)?;
+        }
+        Ok(())
+    }
+}
+
+/// Where the backup of `path` goes below `files/`. Absolute paths lose
+/// their root, which would otherwise replace the backup directory in a join.
+fn stored(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|c| matches!(c, Component::Normal(_)))
+        .collect()
+}
+
+/// Restores the most recent backup below `backups` and removes it.
+/// Returns the restored paths.
+pub fn undo(backups: &Path) -> io::Result<Vec<String>> {
+    let mut runs: Vec<PathBuf> = match fs::read_dir(backups) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.join(BACKUP_MANIFEST).is_file())
+            .collect(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e),
+    };
+    runs.sort();
+    let backup = runs.pop().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, "No optimize backup to restore")
+    })?;
+
+    let manifest: BackupManifest =
+        serde_json::from_str(&fs::read_to_string(backup.join(BACKUP_MANIFEST))?).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid backup in {}: {}", backup.display(), e),
+            )
+        })?;
+
+    let mut restored = Vec::new();
+    for (path, entry) in &manifest.files {
+        match entry {
+            Backup::Saved => effects::copy(backup.join("files").join(stored(Path::new(path))), path)?,
+            Backup::Created => match fs::metadata(path) {
+                Ok(_) => effects::remove_file(path)?,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            },
+        }
+        restored.push(path.clone());
+    }
+    effects::remove_dir_all(&backup)?;
+    if fs::read_dir(backups).is_ok_and(|mut d| d.next().is_none()) {
+        effects::remove_dir_all(backups)?;
+    }
+    Ok(restored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::MutexGuard;
+
+    /// A scratch project directory; holds the effects lock so dry-run stays
+    /// off while the test writes.
+    struct Project {
+        dir: PathBuf,
+        _guard: MutexGuard<'static, ()>,
+    }
+
+    impl Project {
+        fn new(name: &str, files: &[(&str, &[u8])]) -> Self {
+            let guard = effects::TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+            let dir = std::env::temp_dir().join(format!("rustify-optimize-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            for (path, contents) in files {
+                let path = dir.join(path);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, contents).unwrap();
+            }
+            fs::create_dir_all(&dir).unwrap();
+            Project { dir, _guard: guard }
+        }
+
+        fn path(&self, name: &str) -> PathBuf {
+            self.dir.join(name)
+        }
+
+        fn backups(&self) -> PathBuf {
+            self.dir.join(".rustify/backups")
+        }
+    }
+
+    impl Drop for Project {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    const CONFIG: &[u8] = b"module.exports = {\r\n\treactStrictMode: false, // \xc3\xa9\r\n}";
+
+    #[test]
+    fn undo_restores_updated_files_and_removes_created_ones() {
+        let project = Project::new("undo", &[("next.config.js", CONFIG), ("package.json", b"{}\n")]);
+        let mut changes = Changes::default();
+        changes.update(project.path("next.config.js"), "module.exports = {};\n");
+        changes.update(project.path("package.json"), "{}\n");
+        changes.create(project.path("src/app/globals.css"), "body {}\n");
+
+        let backup = changes.apply(&project.backups()).unwrap();
+        assert!(backup.starts_with(project.backups()));
+        assert_eq!(fs::read_to_string(project.path("next.config.js")).unwrap(), "module.exports = {};\n");
+        assert_eq!(fs::read_to_string(project.path("src/app/globals.css")).unwrap(), "body {}\n");
+        let manifest: BackupManifest =
+            serde_json::from_str(&fs::read_to_string(backup.join(BACKUP_MANIFEST)).unwrap()).unwrap();
+        let entries: Vec<(&str, &Backup)> = manifest
+            .files
+            .iter()
+            .map(|(path, entry)| (path.rsplit('/').next().unwrap(), entry))
+            .collect();
+        // package.json did not change, so it is neither written nor backed up
+        assert_eq!(entries, [("next.config.js", &Backup::Saved), ("globals.css", &Backup::Created)]);
+
+        let mut restored = undo(&project.backups()).unwrap();
+        restored.sort();
+        assert_eq!(restored.len(), 2);
+        assert_eq!(fs::read(project.path("next.config.js")).unwrap(), CONFIG);
+        assert!(!project.path("src/app/globals.css").exists());
+        assert_eq!(fs::read(project.path("package.json")).unwrap(), b"{}\n");
+        assert!(!project.backups().exists());
+    }
+
+    #[test]
+    fn undo_takes_the_latest_backup_first() {
+        let project = Project::new("twice", &[("vite.config.ts", b"first\n")]);
+        for contents in ["second\n", "third\n"] {
+            let mut changes = Changes::default();
+            changes.update(project.path("vite.config.ts"), contents);
+            changes.apply(&project.backups()).unwrap();
+        }
+        assert_eq!(fs::read_dir(project.backups()).unwrap().count(), 2);
+
+        undo(&project.backups()).unwrap();
+        assert_eq!(fs::read_to_string(project.path("vite.config.ts")).unwrap(), "second\n");
+        undo(&project.backups()).unwrap();
+        assert_eq!(fs::read_to_string(project.path("vite.config.ts")).unwrap(), "first\n");
+        let err = undo(&project.backups()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn diff_lists_updated_and_created_files() {
+        let project = Project::new("diff", &[("astro.config.mjs", b"export default {};\n")]);
+        let mut changes = Changes::default();
+        changes.update(project.path("astro.config.mjs"), "export default { compressHTML: true };\n");
+        changes.create(project.path("public/robots.txt"), "User-agent: *\n");
+        changes.create(project.path("astro.config.mjs"), "replaced\n");
+
+        let config = project.path("astro.config.mjs").display().to_string();
+        let robots = project.path("public/robots.txt").display().to_string();
+        let diff = changes.diff();
+        assert_eq!(diff[0], format!("~ {}", config));
+        assert!(diff.contains(&"    -export default {};".to_string()), "{:#?}", diff);
+        assert!(diff.contains(&"    +export default { compressHTML: true };".to_string()), "{:#?}", diff);
+        assert!(diff.contains(&format!("+ {}", robots)), "{:#?}", diff);
+        assert!(diff.contains(&"    +User-agent: *".to_string()), "{:#?}", diff);
+        assert_eq!(changes.notes, [format!("{} exists, left unchanged", config)]);
+        assert!(!changes.is_empty());
+    }
+}
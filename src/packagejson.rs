@@ -0,0 +1,531 @@
+use serde::Serialize;
+use serde_json::{ser::PrettyFormatter, Map, Value};
+use std::{fs, io, path::Path};
+
+/// One change rustify makes to a package.json.
+#[derive(Debug, Clone, Copy)]
+pub enum Edit<'a> {
+    /// Adds `scripts.<name>` unless the project defines it already, so a
+    /// custom `start` or `build` is kept.
+    Script(&'a str, &'a str),
+    /// Sets `scripts.<name>`, replacing what is there. Only for names rustify
+    /// introduces, such as `analyze` and `build:prod`.
+    OwnedScript(&'a str, &'a str),
+    /// Adds a runtime dependency unless the project already lists the
+    /// package in any dependency section.
+    Dependency(&'a str, &'a str),
+    /// Like `Dependency`, for `devDependencies`.
+    DevDependency(&'a str, &'a str),
+    /// Adds `engines.<name>` unless the project constrains it already.
+    Engine(&'a str, &'a str),
+    /// Sets the corepack `packageManager` field, e.g. `pnpm@9.12.0`, unless
+    /// the project pins one already.
+    PackageManager(&'a str),
+}
+
+const DEPENDENCY_SECTIONS: &[&str] = &[
+    "dependencies",
+    "devDependencies",
+    "peerDependencies",
+    "optionalDependencies",
+];
+
+/// A parsed package.json that remembers how it was formatted, so edits
+/// show up in a diff as the changed lines only.
+#[derive(Debug)]
+pub struct PackageJson {
+    source: String,
+    original: Map<String, Value>,
+    fields: Map<String, Value>,
+    indent: String,
+    crlf: bool,
+    final_newline: bool,
+}
+
+impl PackageJson {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        Self::parse(&fs::read_to_string(path)?).map_err(|e| {
+            io::Error::new(e.kind(), format!("{}: {}", path.display(), e))
+        })
+    }
+
+    pub fn parse(source: &str) -> io::Result<Self> {
+        let fields = match serde_json::from_str(source) {
+            Ok(Value::Object(fields)) => fields,
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "expected a JSON object",
+                ))
+            }
+            Err(e) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid JSON: {}", e),
+                ))
+            }
+        };
+        // The first indented line sets the style; npm writes two spaces
+        let indent = source
+            .lines()
+            .skip(1)
+            .map(|line| &line[..line.len() - line.trim_start().len()])
+            .find(|ws| !ws.is_empty())
+            .unwrap_or("  ")
+            .to_string();
+        Ok(Self {
+            source: source.to_string(),
+            original: fields.clone(),
+            fields,
+            indent,
+            crlf: source.contains("\r\n"),
+            final_newline: source.is_empty() || source.ends_with('\n'),
+        })
+    }
+
+    pub fn apply(&mut self, edits: &[Edit]) {
+        for edit in edits {
+            match *edit {
+                Edit::Script(name, command) => {
+                    self.section("scripts")
+                        .entry(name.to_string())
+                        .or_insert_with(|| command.into());
+                }
+                Edit::OwnedScript(name, command) => {
+                    self.section("scripts").insert(name.to_string(), command.into());
+                }
+                Edit::Dependency(name, range) => self.add_dependency("dependencies", name, range),
+                Edit::DevDependency(name, range) => {
+                    self.add_dependency("devDependencies", name, range)
+                }
+                Edit::Engine(name, range) => {
+                    self.section("engines")
+                        .entry(name.to_string())
+                        .or_insert_with(|| range.into());
+                }
+                Edit::PackageManager(spec) => {
+                    self.fields
+                        .entry("packageManager")
+                        .or_insert_with(|| spec.into());
+                }
+            }
+        }
+    }
+
+    fn add_dependency(&mut self, section: &str, name: &str, range: &str) {
+        let listed = DEPENDENCY_SECTIONS.iter().any(|s| {
+            self.fields
+                .get(*s)
+                .and_then(Value::as_object)
+                .is_some_and(|deps| deps.contains_key(name))
+        });
+        if !listed {
+            self.section(section).insert(name.to_string(), range.into());
+        }
+    }
+
+    /// Passes every dependency's range to `f` and takes the replacement it
+    /// returns, if any.
+    pub fn map_ranges<F>(&mut self, mut f: F) -> io::Result<()>
+    where
+        F: FnMut(&str, &str) -> io::Result<Option<String>>,
+    {
+        for section in DEPENDENCY_SECTIONS {
+            let deps = match self.fields.get_mut(*section).and_then(Value::as_object_mut) {
+                Some(deps) => deps,
+                None => continue,
+            };
+            for (name, range) in deps.iter_mut() {
+                if let Some(replacement) = range.as_str().map(|r| f(name, r)).transpose()?.flatten() {
+                    *range = replacement.into();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The object under `key`, created at the end of the file when missing.
+    /// A non-object value there is replaced.
+    fn section(&mut self, key: &str) -> &mut Map<String, Value> {
+        let value = self
+            .fields
+            .entry(key.to_string())
+            .or_insert_with(|| Value::Object(Map::new()));
+        if !value.is_object() {
+            *value = Value::Object(Map::new());
+        }
+        value.as_object_mut().unwrap()
+    }
+
+    /// The file contents after the edits; the original text when nothing
+    /// changed.
+    pub fn render(&self) -> io::Result<String> {
+        if self.fields == self.original {
+            return Ok(self.source.clone());
+        }
+        let mut out = Vec::new();
+        let formatter = PrettyFormatter::with_indent(self.indent.as_bytes());
+        let mut serializer = serde_json::Serializer::with_formatter(&mut out, formatter);
+        self.fields
+            .serialize(&mut serializer)
+            .map_err(io::Error::other)?;
+        let mut out = String::from_utf8(out)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if self.final_newline {
+            out.push('\n');
+        }
+        if self.crlf {
+            out = out.replace('\n', "\r\n");
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edited(source: &str, edits: &[Edit]) -> String {
+        let mut pkg = PackageJson::parse(source).unwrap();
+        pkg.apply(edits);
+        pkg.render().unwrap()
+    }
+
+    #[test]
+    fn next_keeps_custom_scripts() {
+        let before = r#"{
+  "name": "demo",
+  "private": true,
+  "scripts": {
+    "dev": "next dev --turbo",
+    "start": "node server.js"
+  },
+  "dependencies": {
+    "next": "14.2.3",
+    "react": "^18"
+  }
+}
+"#;
+        let after = r#"{
+  "name": "demo",
+  "private": true,
+  "scripts": {
+    "dev": "next dev --turbo",
+    "start": "node server.js",
+    "build": "next build",
+    "analyze": "ANALYZE=true next build",
+    "lint": "next lint && prettier --write ."
+  },
+  "dependencies": {
+    "next": "14.2.3",
+    "react": "^18"
+  },
+  "engines": {
+    "node": ">=18.17.0"
+  }
+}
+"#;
+        assert_eq!(edited(before, crate::NEXT_PACKAGE_EDITS), after);
+    }
+
+    #[test]
+    fn next_keeps_engine_constraint() {
+        let before = "{\n  \"engines\": {\n    \"node\": \"20.x\"\n  }\n}\n";
+        let pkg = edited(before, crate::NEXT_PACKAGE_EDITS);
+        assert!(pkg.contains("\"node\": \"20.x\""), "{}", pkg);
+        assert!(!pkg.contains(">=18.17.0"), "{}", pkg);
+    }
+
+    #[test]
+    fn react_adds_analyzer() {
+        let before = r#"{
+  "name": "demo",
+  "scripts": {
+    "start": "react-scripts start",
+    "build": "react-scripts build",
+    "analyze": "source-map-explorer 'build/static/js/*.js'"
+  },
+  "dependencies": {
+    "react": "^18.3.1",
+    "react-scripts": "5.0.1"
+  }
+}
+"#;
+        let after = r#"{
+  "name": "demo",
+  "scripts": {
+    "start": "react-scripts start",
+    "build": "react-scripts build",
+    "analyze": "webpack-bundle-analyzer stats.json",
+    "build:prod": "GENERATE_SOURCEMAP=false react-scripts build"
+  },
+  "dependencies": {
+    "react": "^18.3.1",
+    "react-scripts": "5.0.1"
+  },
+  "devDependencies": {
+    "webpack-bundle-analyzer": "^4.10.0"
+  }
+}
+"#;
+        assert_eq!(edited(before, crate::REACT_PACKAGE_EDITS), after);
+    }
+
+    #[test]
+    fn vue_and_nuxt_add_owned_scripts() {
+        let before = "{\n  \"name\": \"demo\",\n  \"scripts\": {\n    \"build\": \"x\"\n  }\n}\n";
+        assert_eq!(
+            edited(before, crate::VUE_PACKAGE_EDITS),
+            r#"{
+  "name": "demo",
+  "scripts": {
+    "build": "x",
+    "analyze": "vue-cli-service build --report",
+    "build:prod": "vue-cli-service build --modern"
+  }
+}
+"#
+        );
+        assert_eq!(
+            edited(before, crate::NUXT_PACKAGE_EDITS),
+            r#"{
+  "name": "demo",
+  "scripts": {
+    "build": "x",
+    "analyze": "nuxt build --analyze",
+    "build:prod": "nuxt build --modern=server"
+  }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn vite_frameworks_keep_preview() {
+        let before = r#"{
+  "name": "demo",
+  "scripts": {
+    "preview": "vite preview --port 4173"
+  }
+}
+"#;
+        assert_eq!(
+            edited(before, crate::SVELTE_PACKAGE_EDITS),
+            r#"{
+  "name": "demo",
+  "scripts": {
+    "preview": "vite preview --port 4173",
+    "analyze": "vite build --mode analyze",
+    "build:prod": "vite build --mode production"
+  }
+}
+"#
+        );
+        assert_eq!(
+            edited(before, crate::ASTRO_PACKAGE_EDITS),
+            r#"{
+  "name": "demo",
+  "scripts": {
+    "preview": "vite preview --port 4173",
+    "analyze": "astro build --analyze",
+    "build:prod": "astro build --mode production"
+  }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn angular_skips_listed_analyzer() {
+        let before = r#"{
+  "name": "demo",
+  "dependencies": {
+    "@angular/core": "^17.3.0"
+  },
+  "devDependencies": {
+    "@angular/cli": "^17.3.0",
+    "webpack-bundle-analyzer": "^4.9.0"
+  }
+}
+"#;
+        let after = r#"{
+  "name": "demo",
+  "dependencies": {
+    "@angular/core": "^17.3.0"
+  },
+  "devDependencies": {
+    "@angular/cli": "^17.3.0",
+    "webpack-bundle-analyzer": "^4.9.0"
+  },
+  "scripts": {
+    "analyze": "ng build --stats-json && webpack-bundle-analyzer dist/stats.json",
+    "build:prod": "ng build --configuration production --aot --build-optimizer --optimization"
+  }
+}
+"#;
+        assert_eq!(edited(before, crate::ANGULAR_PACKAGE_EDITS), after);
+    }
+
+    #[test]
+    fn remix_adds_server_dependencies() {
+        let before = r#"{
+  "name": "demo",
+  "scripts": {
+    "build": "remix build"
+  },
+  "dependencies": {
+    "@remix-run/node": "^2.9.0",
+    "express": "^4.18.2"
+  }
+}
+"#;
+        let after = r#"{
+  "name": "demo",
+  "scripts": {
+    "build": "remix build",
+    "build:prod": "remix build --sourcemap --minify",
+    "analyze": "REMIX_ANALYZE=1 remix build",
+    "start:prod": "remix-serve ./build/index.js"
+  },
+  "dependencies": {
+    "@remix-run/node": "^2.9.0",
+    "express": "^4.18.2",
+    "@remix-run/express": "^2.9.0",
+    "compression": "^1.7.4",
+    "morgan": "^1.10.0"
+  }
+}
+"#;
+        assert_eq!(edited(before, crate::REMIX_PACKAGE_EDITS), after);
+    }
+
+    #[test]
+    fn mern_adds_production_scripts() {
+        let before = r#"{
+  "name": "demo",
+  "scripts": {
+    "start:prod": "node server/index.js"
+  }
+}
+"#;
+        let after = r#"{
+  "name": "demo",
+  "scripts": {
+    "start:prod": "NODE_ENV=production node dist/server/index.js",
+    "build:prod": "npm run build:server && npm run build:client",
+    "analyze": "webpack-bundle-analyzer client/build/bundle-stats.json"
+  },
+  "devDependencies": {
+    "webpack-bundle-analyzer": "^4.10.0"
+  }
+}
+"#;
+        assert_eq!(edited(before, crate::MERN_PACKAGE_EDITS), after);
+    }
+
+    #[test]
+    fn every_framework_pins_a_missing_package_manager() {
+        let frameworks = [
+            ("next", crate::NEXT_PACKAGE_EDITS),
+            ("react", crate::REACT_PACKAGE_EDITS),
+            ("vue", crate::VUE_PACKAGE_EDITS),
+            ("nuxt", crate::NUXT_PACKAGE_EDITS),
+            ("svelte", crate::SVELTE_PACKAGE_EDITS),
+            ("angular", crate::ANGULAR_PACKAGE_EDITS),
+            ("astro", crate::ASTRO_PACKAGE_EDITS),
+            ("remix", crate::REMIX_PACKAGE_EDITS),
+            ("mern", crate::MERN_PACKAGE_EDITS),
+        ];
+        for (framework, framework_edits) in frameworks {
+            let mut edits = framework_edits.to_vec();
+            edits.push(Edit::PackageManager("pnpm@9.12.0"));
+
+            let pkg = edited("{\n  \"name\": \"demo\"\n}\n", &edits);
+            let fields: Value = serde_json::from_str(&pkg).unwrap();
+            assert_eq!(fields["packageManager"], "pnpm@9.12.0", "{}", framework);
+
+            let pinned = "{\n  \"name\": \"demo\",\n  \"packageManager\": \"yarn@4.5.0\"\n}\n";
+            let pkg = edited(pinned, &edits);
+            assert!(pkg.contains("\"packageManager\": \"yarn@4.5.0\""), "{}: {}", framework, pkg);
+            assert!(!pkg.contains("pnpm@"), "{}: {}", framework, pkg);
+        }
+    }
+
+    #[test]
+    fn package_manager_goes_after_existing_fields() {
+        let before = "{\n  \"name\": \"demo\",\n  \"version\": \"0.1.0\"\n}\n";
+        assert_eq!(
+            edited(before, &[Edit::PackageManager("npm@10.8.2")]),
+            "{\n  \"name\": \"demo\",\n  \"version\": \"0.1.0\",\n  \"packageManager\": \"npm@10.8.2\"\n}\n"
+        );
+    }
+
+    #[test]
+    fn keeps_tab_indentation() {
+        let before = "{\n\t\"name\": \"demo\",\n\t\"scripts\": {\n\t\t\"build\": \"vite build\"\n\t}\n}\n";
+        assert_eq!(
+            edited(before, &[Edit::Script("preview", "vite preview")]),
+            "{\n\t\"name\": \"demo\",\n\t\"scripts\": {\n\t\t\"build\": \"vite build\",\n\t\t\"preview\": \"vite preview\"\n\t}\n}\n"
+        );
+    }
+
+    #[test]
+    fn keeps_crlf_line_endings() {
+        let before = "{\r\n  \"name\": \"demo\"\r\n}\r\n";
+        assert_eq!(
+            edited(before, &[Edit::Engine("node", ">=18")]),
+            "{\r\n  \"name\": \"demo\",\r\n  \"engines\": {\r\n    \"node\": \">=18\"\r\n  }\r\n}\r\n"
+        );
+    }
+
+    #[test]
+    fn keeps_missing_final_newline() {
+        let before = "{\n    \"name\": \"demo\"\n}";
+        assert_eq!(
+            edited(before, &[Edit::DevDependency("vite", "^5.0.0")]),
+            "{\n    \"name\": \"demo\",\n    \"devDependencies\": {\n        \"vite\": \"^5.0.0\"\n    }\n}"
+        );
+    }
+
+    #[test]
+    fn keeps_key_order() {
+        let before = r#"{
+  "version": "1.0.0",
+  "name": "demo",
+  "scripts": {
+    "test": "vitest",
+    "build": "vite build"
+  },
+  "type": "module"
+}
+"#;
+        let after = r#"{
+  "version": "1.0.0",
+  "name": "demo",
+  "scripts": {
+    "test": "vitest",
+    "build": "vite build",
+    "analyze": "vite build --mode analyze"
+  },
+  "type": "module"
+}
+"#;
+        assert_eq!(
+            edited(before, &[Edit::OwnedScript("analyze", "vite build --mode analyze")]),
+            after
+        );
+    }
+
+    #[test]
+    fn unchanged_file_is_returned_verbatim() {
+        let before = "{ \"name\": \"demo\",\n  \"scripts\": { \"build\": \"next build\" } }";
+        assert_eq!(edited(before, &[Edit::Script("build", "next build")]), before);
+    }
+
+    #[test]
+    fn rejects_non_objects() {
+        assert!(PackageJson::parse("[]").is_err());
+        assert!(PackageJson::parse("{").is_err());
+    }
+}
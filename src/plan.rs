@@ -0,0 +1,458 @@
+use super::{effects::Effect, envfile::ProjectEnv, kubectl};
+use colored::*;
+use serde_json::Value;
+use std::{collections::BTreeMap, io, process::Command};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChangeKind {
+    Added,
+    Changed,
+    Removed,
+}
+
+/// One resource that differs between the configuration and what is running.
+#[derive(Debug, Clone)]
+pub struct ResourceChange {
+    pub kind: ChangeKind,
+    pub resource: String,
+    /// Unified diff lines; empty for whole-resource additions and removals.
+    pub diff: Vec<String>,
+}
+
+/// A resource rustify may have created earlier and that should go away when
+/// the current configuration no longer renders it.
+pub struct RemovalCandidate {
+    pub kind: &'static str,
+    pub resource: &'static str,
+    pub name: String,
+}
+
+/// Splits rendered manifests into `(kind, name)` pairs.
+fn rendered_ids(manifests: &[String]) -> Vec<(String, String)> {
+    let mut ids = Vec::new();
+    for document in manifests.iter().flat_map(|m| m.split("\n---\n")) {
+        let kind = document.lines().find_map(|l| l.strip_prefix("kind: "));
+        let name = document
+            .lines()
+            .skip_while(|l| *l != "metadata:")
+            .find_map(|l| l.strip_prefix("  name: "));
+        if let (Some(kind), Some(name)) = (kind, name) {
+            ids.push((kind.trim().to_string(), name.trim().to_string()));
+        }
+    }
+    ids
+}
+
+/// `apps.v1.Deployment.shop.shop-deployment` → `Deployment shop/shop-deployment`
+fn resource_label(file: &str, namespace: &str) -> String {
+    let parts: Vec<&str> = file.split('.').collect();
+    match parts.iter().position(|p| p.starts_with(char::is_uppercase)) {
+        Some(i) => {
+            let rest = parts[i + 1..].join(".");
+            match rest.strip_prefix(&format!("{}.", namespace)) {
+                Some(name) => format!("{} {}/{}", parts[i], namespace, name),
+                // Cluster-scoped objects have an empty namespace segment
+                None => format!("{} {}", parts[i], rest.strip_prefix('.').unwrap_or(&rest)),
+            }
+        }
+        None => file.to_string(),
+    }
+}
+
+/// Parses the `diff -u -N` output `kubectl diff` produces, one section per object.
+fn parse_kubectl_diff(output: &str, namespace: &str) -> Vec<ResourceChange> {
+    let mut changes: Vec<ResourceChange> = Vec::new();
+    for line in output.lines() {
+        if line.starts_with("diff ") {
+            let path = line.split_whitespace().last().unwrap_or_default();
+            let file = path.rsplit('/').next().unwrap_or(path);
+            changes.push(ResourceChange {
+                kind: ChangeKind::Changed,
+                resource: resource_label(file, namespace),
+                diff: Vec::new(),
+            });
+            continue;
+        }
+        let current = match changes.last_mut() {
+            Some(current) => current,
+            None => continue,
+        };
+        if line.starts_with("--- ") || line.starts_with("+++ ") {
+            continue;
+        }
+        if line.starts_with("@@ -0,0 ") {
+            current.kind = ChangeKind::Added;
+        }
+        if current.kind == ChangeKind::Changed {
+            current.diff.push(line.to_string());
+        }
+    }
+    changes
+}
+
+fn kubectl_exists(resource: &str, name: &str, namespace: Option<&str>) -> io::Result<bool> {
+    let mut command = kubectl();
+    command.args(["get", resource, name, "-o", "name", "--ignore-not-found"]);
+    if let Some(namespace) = namespace {
+        command.args(["-n", namespace]);
+    }
+    let output = command.run()?;
+    // A missing CRD (e.g. KEDA not installed) means the object cannot exist
+    Ok(output.status.success() && !output.stdout.is_empty())
+}
+
+/// Compares `manifests` with the cluster using a server-side dry-run, so
+/// defaults and admission webhooks are applied before diffing. Secret data
+/// is masked by kubectl.
+pub fn kubernetes_changes(
+    manifests: &[String],
+    namespace: &str,
+    candidates: &[RemovalCandidate],
+) -> io::Result<Vec<ResourceChange>> {
+    let mut changes = Vec::new();
+
+    if !kubectl_exists("namespace", namespace, None)? {
+        // Nothing to dry-run against; every resource would be created
+        changes.push(ResourceChange {
+            kind: ChangeKind::Added,
+            resource: format!("Namespace {}", namespace),
+            diff: Vec::new(),
+        });
+        changes.extend(rendered_ids(manifests).into_iter().map(|(kind, name)| {
+            ResourceChange {
+                kind: ChangeKind::Added,
+                resource: format!("{} {}/{}", kind, namespace, name),
+                diff: Vec::new(),
+            }
+        }));
+        return Ok(changes);
+    }
+
+    let input = manifests
+        .iter()
+        .map(|m| m.trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n---\n");
+    let output = kubectl()
+        .args(["diff", "-f", "-"])
+        // Pin the format we parse, whatever the user configured
+        .env("KUBECTL_EXTERNAL_DIFF", "diff -u -N")
+        .run_with_input(input.as_bytes())?;
+
+    // kubectl diff exits 1 when differences were found and >1 on errors
+    match output.status.code() {
+        Some(0) | Some(1) => {}
+        _ => {
+            return Err(io::Error::other(format!(
+                "kubectl diff failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )))
+        }
+    }
+    changes.extend(parse_kubectl_diff(
+        &String::from_utf8_lossy(&output.stdout),
+        namespace,
+    ));
+
+    changes.extend(removals(manifests, namespace, candidates, |candidate| {
+        kubectl_exists(candidate.resource, &candidate.name, Some(namespace))
+    })?);
+
+    Ok(changes)
+}
+
+/// Candidates that `manifests` no longer render but that still `exist`.
+fn removals(
+    manifests: &[String],
+    namespace: &str,
+    candidates: &[RemovalCandidate],
+    mut exists: impl FnMut(&RemovalCandidate) -> io::Result<bool>,
+) -> io::Result<Vec<ResourceChange>> {
+    let rendered = rendered_ids(manifests);
+    let mut changes = Vec::new();
+    for candidate in candidates {
+        let still_rendered = rendered
+            .iter()
+            .any(|(kind, name)| kind == candidate.kind && *name == candidate.name);
+        if !still_rendered && exists(candidate)? {
+            changes.push(ResourceChange {
+                kind: ChangeKind::Removed,
+                resource: format!("{} {}/{}", candidate.kind, namespace, candidate.name),
+                diff: Vec::new(),
+            });
+        }
+    }
+    Ok(changes)
+}
+
+fn env_map(value: &Value) -> BTreeMap<String, String> {
+    value
+        .as_array()
+        .map(|vars| {
+            vars.iter()
+                .filter_map(|v| v.as_str()?.split_once('='))
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Compares the container `rustify deploy` would start with the running one.
+/// Values of secret variables are never printed.
+pub fn docker_changes(
+    container: &str,
+    image: &str,
+    port: &str,
+    project_env: &ProjectEnv,
+) -> io::Result<Vec<ResourceChange>> {
+    let resource = format!("Container {}", container);
+    let output = Command::new("docker").args(["inspect", container]).run()?;
+    if !output.status.success() {
+        return Ok(vec![ResourceChange {
+            kind: ChangeKind::Added,
+            resource,
+            diff: Vec::new(),
+        }]);
+    }
+
+    let inspect: Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let live = &inspect[0];
+    let mut diff = Vec::new();
+
+    let live_image = live["Config"]["Image"].as_str().unwrap_or_default();
+    if live_image != image {
+        diff.push(format!("-image: {}", live_image));
+        diff.push(format!("+image: {}", image));
+    }
+
+    let live_port = live["HostConfig"]["PortBindings"][format!("{}/tcp", port)][0]["HostPort"]
+        .as_str()
+        .unwrap_or_default();
+    if live_port != port {
+        let bound = live["HostConfig"]["PortBindings"]
+            .as_object()
+            .map(|ports| ports.keys().cloned().collect::<Vec<_>>().join(", "))
+            .unwrap_or_default();
+        diff.push(format!("-ports: {}", bound));
+        diff.push(format!("+ports: {}:{}/tcp", port, port));
+    }
+
+    let live_env = env_map(&live["Config"]["Env"]);
+    let shown = |key: &str, value: &str| {
+        if project_env.secret.contains_key(key) {
+            format!("{}=***", key)
+        } else {
+            format!("{}={}", key, value)
+        }
+    };
+    for (key, value) in project_env.all() {
+        match live_env.get(key) {
+            Some(current) if current == value => {}
+            Some(current) => {
+                diff.push(format!("-env: {}", shown(key, current)));
+                diff.push(format!("+env: {}", shown(key, value)));
+            }
+            None => diff.push(format!("+env: {}", shown(key, value))),
+        }
+    }
+
+    // Variables baked into the image are not ours to remove
+    let image_env = Command::new("docker")
+        .args(["image", "inspect", "--format", "{{json .Config.Env}}", live_image])
+        .run()
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| serde_json::from_slice::<Value>(&o.stdout).ok())
+        .map(|env| env_map(&env));
+    if let Some(image_env) = image_env {
+        for key in live_env.keys() {
+            let planned = project_env.plain.contains_key(key) || project_env.secret.contains_key(key);
+            if !planned && !image_env.contains_key(key) {
+                diff.push(format!("-env: {}=***", key));
+            }
+        }
+    }
+
+    if diff.is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok(vec![ResourceChange {
+        kind: ChangeKind::Changed,
+        resource,
+        diff,
+    }])
+}
+
+/// Exit code of `rustify plan`: 0 when nothing would change, 2 otherwise, so
+/// CI can tell drift from failures (1).
+pub fn exit_code(changes: &[ResourceChange]) -> i32 {
+    if changes.is_empty() {
+        0
+    } else {
+        2
+    }
+}
+
+/// Prints a colored per-resource summary followed by the totals.
+pub fn print_changes(changes: &[ResourceChange]) {
+    if changes.is_empty() {
+        println!("{}", "✅ No changes. The live state matches the configuration.".green());
+        return;
+    }
+
+    for change in changes {
+        let header = match change.kind {
+            ChangeKind::Added => format!("+ {} (added)", change.resource).green().bold(),
+            ChangeKind::Changed => format!("~ {} (changed)", change.resource).yellow().bold(),
+            ChangeKind::Removed => format!("- {} (removed)", change.resource).red().bold(),
+        };
+        println!("{}", header);
+        for line in &change.diff {
+            let line = if line.starts_with('+') {
+                line.green()
+            } else if line.starts_with('-') {
+                line.red()
+            } else if line.starts_with("@@") {
+                line.cyan()
+            } else {
+                line.dimmed()
+            };
+            println!("    {}", line);
+        }
+    }
+
+    let count = |kind| changes.iter().filter(|c| c.kind == kind).count();
+    println!(
+        "\nPlan: {} to add, {} to change, {} to remove.",
+        count(ChangeKind::Added).to_string().green(),
+        count(ChangeKind::Changed).to_string().yellow(),
+        count(ChangeKind::Removed).to_string().red()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEPLOYMENT: &str = "apiVersion: apps/v1\nkind: Deployment\nmetadata:\n  name: shop-deployment\n  namespace: shop\n  labels:\n    app: shop\nspec:\n  template:\n    metadata:\n      name: ignored\n";
+    const SERVICE: &str = "apiVersion: v1\nkind: Service\nmetadata:\n  name: shop-service\n  namespace: shop\n";
+
+    const DIFF: &str = "\
+diff -u -N /tmp/LIVE-1/apps.v1.Deployment.shop.shop-deployment /tmp/MERGED-2/apps.v1.Deployment.shop.shop-deployment
+--- /tmp/LIVE-1/apps.v1.Deployment.shop.shop-deployment\t2024-05-01 10:00:00
++++ /tmp/MERGED-2/apps.v1.Deployment.shop.shop-deployment\t2024-05-01 10:00:00
+@@ -30,7 +30,7 @@
+       containers:
+-      - image: shop:1
++      - image: shop:2
+         name: shop
+diff -u -N /tmp/LIVE-1/v1.ConfigMap.shop.shop-env /tmp/MERGED-2/v1.ConfigMap.shop.shop-env
+--- /tmp/LIVE-1/v1.ConfigMap.shop.shop-env\t1970-01-01 00:00:00
++++ /tmp/MERGED-2/v1.ConfigMap.shop.shop-env\t2024-05-01 10:00:00
+@@ -0,0 +1,8 @@
++apiVersion: v1
++kind: ConfigMap
+";
+
+    fn candidate(kind: &'static str, resource: &'static str, name: &str) -> RemovalCandidate {
+        RemovalCandidate {
+            kind,
+            resource,
+            name: name.to_string(),
+        }
+    }
+
+    fn summary(changes: &[ResourceChange]) -> Vec<(ChangeKind, &str)> {
+        changes.iter().map(|c| (c.kind, c.resource.as_str())).collect()
+    }
+
+    #[test]
+    fn reads_kinds_and_names_from_manifests() {
+        let manifests = vec![format!("{}---\n{}", DEPLOYMENT, SERVICE), "# empty\n".to_string()];
+        assert_eq!(
+            rendered_ids(&manifests),
+            [
+                ("Deployment".to_string(), "shop-deployment".to_string()),
+                ("Service".to_string(), "shop-service".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn labels_kubectl_diff_files() {
+        assert_eq!(
+            resource_label("apps.v1.Deployment.shop.shop-deployment", "shop"),
+            "Deployment shop/shop-deployment"
+        );
+        assert_eq!(
+            resource_label("networking.k8s.io.v1.Ingress.shop.shop.example.com", "shop"),
+            "Ingress shop/shop.example.com"
+        );
+        assert_eq!(resource_label("v1.Namespace..shop", "shop"), "Namespace shop");
+        assert_eq!(resource_label("unknown", "shop"), "unknown");
+    }
+
+    #[test]
+    fn classifies_changed_and_added_objects() {
+        let changes = parse_kubectl_diff(DIFF, "shop");
+        assert_eq!(
+            summary(&changes),
+            [
+                (ChangeKind::Changed, "Deployment shop/shop-deployment"),
+                (ChangeKind::Added, "ConfigMap shop/shop-env"),
+            ]
+        );
+        assert_eq!(
+            changes[0].diff,
+            ["@@ -30,7 +30,7 @@", "       containers:", "-      - image: shop:1", "+      - image: shop:2", "         name: shop"]
+        );
+        assert!(changes[1].diff.is_empty(), "additions carry no diff");
+        assert!(parse_kubectl_diff("", "shop").is_empty());
+    }
+
+    #[test]
+    fn reports_removals_only_for_existing_unrendered_candidates() {
+        let manifests = vec![DEPLOYMENT.to_string()];
+        let candidates = [
+            candidate("Deployment", "deployment", "shop-deployment"),
+            candidate("PodDisruptionBudget", "poddisruptionbudget", "shop-pdb"),
+            candidate("ConfigMap", "configmap", "shop-env"),
+        ];
+        let mut asked = Vec::new();
+        let changes = removals(&manifests, "shop", &candidates, |c| {
+            asked.push(c.name.clone());
+            Ok(c.resource == "poddisruptionbudget")
+        })
+        .unwrap();
+        assert_eq!(summary(&changes), [(ChangeKind::Removed, "PodDisruptionBudget shop/shop-pdb")]);
+        assert_eq!(asked, ["shop-pdb", "shop-env"], "rendered resources are not looked up");
+
+        let failed = removals(&manifests, "shop", &candidates, |_| {
+            Err(io::Error::other("no cluster"))
+        });
+        assert!(failed.is_err());
+    }
+
+    #[test]
+    fn exits_with_2_when_anything_changes() {
+        assert_eq!(exit_code(&[]), 0);
+        for kind in [ChangeKind::Added, ChangeKind::Changed, ChangeKind::Removed] {
+            let change = ResourceChange {
+                kind,
+                resource: "Service shop/shop-service".to_string(),
+                diff: Vec::new(),
+            };
+            assert_eq!(exit_code(&[change]), 2);
+        }
+    }
+
+    #[test]
+    fn reads_docker_env_lists() {
+        let env = env_map(&serde_json::json!(["A=1", "URL=http://x/?a=b", "BROKEN"]));
+        assert_eq!(env.get("A").map(String::as_str), Some("1"));
+        assert_eq!(env.get("URL").map(String::as_str), Some("http://x/?a=b"));
+        assert_eq!(env.len(), 2);
+        assert!(env_map(&Value::Null).is_empty());
+    }
+}
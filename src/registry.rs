@@ -0,0 +1,272 @@
+use openssl::base64;
+use serde_json::{json, Value};
+use super::effects::Effect;
+use std::{env, fs, io, path::PathBuf, process::Command};
+
+/// Key Docker uses for Docker Hub in `config.json` and credential helpers.
+const DOCKER_HUB_KEY: &str = "https://index.docker.io/v1/";
+
+#[derive(Debug, Clone)]
+pub struct RegistryCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+/// Host part of a registry reference such as `ghcr.io/acme` or `localhost:5000`.
+pub fn registry_host(registry: &str) -> &str {
+    let registry = registry
+        .trim_start_matches("https://")
+        .trim_start_matches("http://");
+    registry.split('/').next().unwrap_or(registry)
+}
+
+/// Key under which Docker stores credentials for `host`.
+pub fn docker_config_key(host: &str) -> String {
+    match host {
+        "docker.io" | "index.docker.io" | "registry-1.docker.io" => DOCKER_HUB_KEY.to_string(),
+        _ => host.to_string(),
+    }
+}
+
+fn docker_config_path() -> Option<PathBuf> {
+    if let Ok(dir) = env::var("DOCKER_CONFIG") {
+        return Some(PathBuf::from(dir).join("config.json"));
+    }
+    env::var("HOME")
+        .or_else(|_| env::var("USERPROFILE"))
+        .ok()
+        .map(|home| PathBuf::from(home).join(".docker").join("config.json"))
+}
+
+/// Normalizes an `auths` entry like `https://ghcr.io/v1/` to its host.
+fn normalize_auth_key(key: &str) -> &str {
+    if key == DOCKER_HUB_KEY {
+        return key;
+    }
+    registry_host(key)
+}
+
+/// Resolves credentials for `registry` the way the Docker CLI does: a
+/// per-registry credential helper first, then an inline `auths` entry, then
+/// the global `credsStore`.
+pub fn lookup_credentials(registry: &str) -> io::Result<Option<RegistryCredentials>> {
+    let path = match docker_config_path() {
+        Some(path) if path.exists() => path,
+        _ => return Ok(None),
+    };
+
+    let config: Value = serde_json::from_str(&fs::read_to_string(&path)?).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid {}: {}", path.display(), e),
+        )
+    })?;
+
+    let key = docker_config_key(registry_host(registry));
+    match credential_source(&config, &key) {
+        Some(Source::Helper(helper)) => credentials_from_helper(helper, &key),
+        Some(Source::Auth(auth)) => decode_auth(auth).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Where the Docker config keeps the credentials for a registry key.
+#[derive(Debug, PartialEq)]
+enum Source<'a> {
+    /// Name of a `docker-credential-*` helper.
+    Helper(&'a str),
+    /// Base64 `user:password` from an `auths` entry.
+    Auth(&'a str),
+}
+
+fn credential_source<'a>(config: &'a Value, key: &str) -> Option<Source<'a>> {
+    if let Some(helper) = config["credHelpers"][key].as_str() {
+        return Some(Source::Helper(helper));
+    }
+
+    if let Some(auths) = config["auths"].as_object() {
+        let entry = auths
+            .iter()
+            .find(|(k, _)| normalize_auth_key(k) == key)
+            .map(|(_, v)| v);
+        if let Some(auth) = entry.and_then(|e| e["auth"].as_str()) {
+            return Some(Source::Auth(auth));
+        }
+    }
+
+    config["credsStore"].as_str().map(Source::Helper)
+}
+
+fn decode_auth(auth: &str) -> io::Result<RegistryCredentials> {
+    let decoded = base64::decode_block(auth)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let decoded = String::from_utf8_lossy(&decoded);
+    let (username, password) = decoded.split_once(':').ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Malformed auth entry in Docker config",
+        )
+    })?;
+
+    Ok(RegistryCredentials {
+        username: username.to_string(),
+        password: password.to_string(),
+    })
+}
+
+fn credentials_from_helper(helper: &str, key: &str) -> io::Result<Option<RegistryCredentials>> {
+    let program = format!("docker-credential-{}", helper);
+    let output = Command::new(&program)
+        .arg("get")
+        .run_with_input(key.as_bytes())
+        .map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("Failed to run credential helper {}: {}", program, e),
+            )
+        })?;
+
+    // Helpers exit non-zero with "credentials not found" for unknown hosts
+    if !output.status.success() {
+        return Ok(None);
+    }
+
+    let response: Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    match (response["Username"].as_str(), response["Secret"].as_str()) {
+        (Some(username), Some(secret)) => Ok(Some(RegistryCredentials {
+            username: username.to_string(),
+            password: secret.to_string(),
+        })),
+        _ => Ok(None),
+    }
+}
+
+/// `.dockerconfigjson` payload holding `credentials` for `registry`.
+pub fn docker_config_json(registry: &str, credentials: &RegistryCredentials) -> String {
+    let auth = base64::encode_block(
+        format!("{}:{}", credentials.username, credentials.password).as_bytes(),
+    );
+    json!({
+        "auths": {
+            docker_config_key(registry_host(registry)): {
+                "username": credentials.username,
+                "password": credentials.password,
+                "auth": auth,
+            }
+        }
+    })
+    .to_string()
+}
+
+/// `kubernetes.io/dockerconfigjson` Secret usable as an `imagePullSecret`.
+pub fn pull_secret_manifest(
+    name: &str,
+    namespace: &str,
+    registry: &str,
+    credentials: &RegistryCredentials,
+) -> String {
+    let data = base64::encode_block(docker_config_json(registry, credentials).as_bytes());
+    format!(
+        r#"apiVersion: v1
+kind: Secret
+metadata:
+  name: {name}
+  namespace: {namespace}
+  labels:
+    app.kubernetes.io/managed-by: rustify
+type: kubernetes.io/dockerconfigjson
+data:
+  .dockerconfigjson: {data}
+"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auth(username: &str, password: &str) -> String {
+        base64::encode_block(format!("{}:{}", username, password).as_bytes())
+    }
+
+    #[test]
+    fn maps_registries_to_config_keys() {
+        assert_eq!(registry_host("https://ghcr.io/acme/app"), "ghcr.io");
+        assert_eq!(registry_host("localhost:5000"), "localhost:5000");
+        for hub in ["docker.io", "index.docker.io", "registry-1.docker.io"] {
+            assert_eq!(docker_config_key(hub), DOCKER_HUB_KEY);
+        }
+        assert_eq!(docker_config_key("ghcr.io"), "ghcr.io");
+        assert_eq!(normalize_auth_key("https://ghcr.io/v1/"), "ghcr.io");
+        assert_eq!(normalize_auth_key(DOCKER_HUB_KEY), DOCKER_HUB_KEY);
+    }
+
+    #[test]
+    fn per_registry_helper_wins_over_auths_and_store() {
+        let config = json!({
+            "credHelpers": { "ghcr.io": "gh" },
+            "auths": { "ghcr.io": { "auth": auth("u", "p") } },
+            "credsStore": "desktop",
+        });
+        assert_eq!(credential_source(&config, "ghcr.io"), Some(Source::Helper("gh")));
+    }
+
+    #[test]
+    fn inline_auth_wins_over_the_global_store() {
+        let encoded = auth("u", "p");
+        let config = json!({
+            "auths": { "https://ghcr.io/v1/": { "auth": encoded } },
+            "credsStore": "desktop",
+        });
+        assert_eq!(credential_source(&config, "ghcr.io"), Some(Source::Auth(&encoded)));
+        assert_eq!(
+            credential_source(&config, "quay.io"),
+            Some(Source::Helper("desktop"))
+        );
+    }
+
+    #[test]
+    fn auths_without_a_token_fall_through() {
+        let config = json!({ "auths": { "ghcr.io": {} } });
+        assert_eq!(credential_source(&config, "ghcr.io"), None);
+
+        let config = json!({ "auths": { "ghcr.io": {} }, "credsStore": "pass" });
+        assert_eq!(credential_source(&config, "ghcr.io"), Some(Source::Helper("pass")));
+    }
+
+    #[test]
+    fn docker_hub_entries_match_any_hub_alias() {
+        let encoded = auth("u", "p");
+        let config = json!({ "auths": { DOCKER_HUB_KEY: { "auth": encoded } } });
+        let key = docker_config_key(registry_host("docker.io/acme"));
+        assert_eq!(credential_source(&config, &key), Some(Source::Auth(&encoded)));
+    }
+
+    #[test]
+    fn decodes_auth_entries() {
+        let credentials = decode_auth(&auth("robot$ci", "pa:ss")).unwrap();
+        assert_eq!(credentials.username, "robot$ci");
+        assert_eq!(credentials.password, "pa:ss");
+
+        let err = decode_auth(&base64::encode_block(b"no-colon")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn docker_config_json_round_trips() {
+        let credentials = RegistryCredentials {
+            username: "u".to_string(),
+            password: "p".to_string(),
+        };
+        let config: Value =
+            serde_json::from_str(&docker_config_json("https://ghcr.io/acme", &credentials)).unwrap();
+        let Some(Source::Auth(encoded)) = credential_source(&config, "ghcr.io") else {
+            panic!("no auth entry in {}", config);
+        };
+        let decoded = decode_auth(encoded).unwrap();
+        assert_eq!((decoded.username.as_str(), decoded.password.as_str()), ("u", "p"));
+        assert_eq!(config["auths"]["ghcr.io"]["username"], "u");
+    }
+}
@@ -0,0 +1,319 @@
+use openssl::{
+    base64,
+    hash::MessageDigest,
+    memcmp,
+    pkey::PKey,
+    rand::rand_bytes,
+    sign::Signer,
+    symm::{decrypt_aead, encrypt_aead, Cipher},
+};
+use super::{artifacts, effects};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, env, fs, io, path::Path};
+
+/// Encrypted store, meant to be committed alongside the code.
+pub const STORE_FILE: &str = "rustify.secrets.json";
+/// Local key file used when `RUSTIFY_SECRETS_KEY` is not set. Never committed.
+pub const KEY_FILE: &str = ".rustify-secrets.key";
+pub const KEY_ENV: &str = "RUSTIFY_SECRETS_KEY";
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+/// Message authenticated by `keyCheck`.
+const KEY_CHECK_LABEL: &[u8] = b"rustify secrets key check";
+
+/// One AES-256-GCM envelope. The secret name is bound in as associated
+/// data, so ciphertexts cannot be swapped between names.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Envelope {
+    nonce: String,
+    ciphertext: String,
+    tag: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoreFile {
+    version: u32,
+    cipher: String,
+    /// HMAC-SHA256 of `KEY_CHECK_LABEL` under the store key, so a wrong key
+    /// is caught on open. Missing in stores written before it was added.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key_check: Option<String>,
+    #[serde(default)]
+    secrets: BTreeMap<String, Envelope>,
+}
+
+impl Default for StoreFile {
+    fn default() -> Self {
+        StoreFile {
+            version: 1,
+            cipher: "aes-256-gcm".to_string(),
+            key_check: None,
+            secrets: BTreeMap::new(),
+        }
+    }
+}
+
+pub struct SecretStore {
+    file: StoreFile,
+    key: Vec<u8>,
+}
+
+pub fn store_exists() -> bool {
+    Path::new(STORE_FILE).exists()
+}
+
+impl SecretStore {
+    /// Opens the store, creating a key on first use when `create` is set.
+    pub fn open(create: bool) -> io::Result<Self> {
+        let file = if store_exists() {
+            let file: StoreFile = serde_json::from_str(&fs::read_to_string(STORE_FILE)?)
+                .map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Invalid {}: {}", STORE_FILE, e),
+                    )
+                })?;
+            if file.cipher != "aes-256-gcm" {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unsupported cipher '{}' in {}", file.cipher, STORE_FILE),
+                ));
+            }
+            file
+        } else {
+            StoreFile::default()
+        };
+
+        let key = load_key(create && file.secrets.is_empty())?;
+        verify_key(&file, &key)?;
+        Ok(SecretStore { file, key })
+    }
+
+    pub fn names(&self) -> Vec<&String> {
+        self.file.secrets.keys().collect()
+    }
+
+    pub fn get(&self, name: &str) -> io::Result<Option<String>> {
+        match self.file.secrets.get(name) {
+            Some(envelope) => decrypt(&self.key, name, envelope).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn set(&mut self, name: &str, value: &str) -> io::Result<()> {
+        let envelope = encrypt(&self.key, name, value)?;
+        self.file.secrets.insert(name.to_string(), envelope);
+        self.save()
+    }
+
+    pub fn remove(&mut self, name: &str) -> io::Result<bool> {
+        let removed = self.file.secrets.remove(name).is_some();
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    /// Decrypts every secret into memory for delivery at deploy time.
+    pub fn decrypt_all(&self) -> io::Result<BTreeMap<String, String>> {
+        self.file
+            .secrets
+            .iter()
+            .map(|(name, envelope)| Ok((name.clone(), decrypt(&self.key, name, envelope)?)))
+            .collect()
+    }
+
+    fn save(&mut self) -> io::Result<()> {
+        self.file.key_check = Some(base64::encode_block(&key_check(&self.key)?));
+        let json = serde_json::to_string_pretty(&self.file)
+            .map_err(io::Error::other)?;
+        // Write then rename so an interrupted write never corrupts the store
+        let tmp = format!("{}.tmp", STORE_FILE);
+        effects::write(&tmp, json + "\n")?;
+        effects::rename(&tmp, STORE_FILE)
+    }
+}
+
+fn load_key(create: bool) -> io::Result<Vec<u8>> {
+    let encoded = if let Ok(value) = env::var(KEY_ENV) {
+        value
+    } else if Path::new(KEY_FILE).exists() {
+        fs::read_to_string(KEY_FILE)?
+    } else if create {
+        generate_key_file()?
+    } else {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "No secrets key found. Set {} or place the key in {}",
+                KEY_ENV, KEY_FILE
+            ),
+        ));
+    };
+
+    let key = base64::decode_block(encoded.trim())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid secrets key: {}", e)))?;
+    if key.len() != KEY_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Secrets key must be {} bytes, got {}", KEY_LEN, key.len()),
+        ));
+    }
+    Ok(key)
+}
+
+fn key_check(key: &[u8]) -> io::Result<Vec<u8>> {
+    let pkey = PKey::hmac(key).map_err(io::Error::other)?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &pkey).map_err(io::Error::other)?;
+    signer.update(KEY_CHECK_LABEL).map_err(io::Error::other)?;
+    signer.sign_to_vec().map_err(io::Error::other)
+}
+
+/// Fails unless `key` is the one `file` was written with. Stores without a
+/// `keyCheck` are checked by decrypting their first secret.
+fn verify_key(file: &StoreFile, key: &[u8]) -> io::Result<()> {
+    let matches = match (&file.key_check, file.secrets.iter().next()) {
+        (Some(expected), _) => {
+            let expected = base64::decode_block(expected).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Corrupt keyCheck in {}", STORE_FILE),
+                )
+            })?;
+            let actual = key_check(key)?;
+            expected.len() == actual.len() && memcmp::eq(&expected, &actual)
+        }
+        (None, Some((name, envelope))) => decrypt(key, name, envelope).is_ok(),
+        (None, None) => true,
+    };
+    if matches {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!(
+                "Secrets key does not match this store ({}); check {} or {}",
+                STORE_FILE, KEY_ENV, KEY_FILE
+            ),
+        ))
+    }
+}
+
+fn generate_key_file() -> io::Result<String> {
+    let mut key = [0u8; KEY_LEN];
+    rand_bytes(&mut key).map_err(io::Error::other)?;
+    let encoded = base64::encode_block(&key);
+
+    effects::write_private(KEY_FILE, format!("{}\n", encoded))?;
+
+    // Keep the key out of version control
+    artifacts::ensure_gitignored(KEY_FILE)?;
+
+    println!("🔑 Generated new secrets key in {} (back it up, it is not committed)", KEY_FILE);
+    Ok(encoded)
+}
+
+fn encrypt(key: &[u8], name: &str, value: &str) -> io::Result<Envelope> {
+    let mut nonce = [0u8; NONCE_LEN];
+    rand_bytes(&mut nonce).map_err(io::Error::other)?;
+    let mut tag = [0u8; TAG_LEN];
+
+    let ciphertext = encrypt_aead(
+        Cipher::aes_256_gcm(),
+        key,
+        Some(&nonce),
+        name.as_bytes(),
+        value.as_bytes(),
+        &mut tag,
+    )
+    .map_err(io::Error::other)?;
+
+    Ok(Envelope {
+        nonce: base64::encode_block(&nonce),
+        ciphertext: base64::encode_block(&ciphertext),
+        tag: base64::encode_block(&tag),
+    })
+}
+
+fn decrypt(key: &[u8], name: &str, envelope: &Envelope) -> io::Result<String> {
+    let invalid = |what: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Corrupt {} for secret '{}'", what, name),
+        )
+    };
+    let nonce = base64::decode_block(&envelope.nonce).map_err(|_| invalid("nonce"))?;
+    let ciphertext = base64::decode_block(&envelope.ciphertext).map_err(|_| invalid("ciphertext"))?;
+    let tag = base64::decode_block(&envelope.tag).map_err(|_| invalid("tag"))?;
+
+    let plaintext = decrypt_aead(
+        Cipher::aes_256_gcm(),
+        key,
+        Some(&nonce),
+        name.as_bytes(),
+        &ciphertext,
+        &tag,
+    )
+    .map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("Cannot decrypt secret '{}': wrong key or tampered store", name),
+        )
+    })?;
+
+    String::from_utf8(plaintext).map_err(|_| invalid("plaintext"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(key: &[u8], secrets: &[(&str, &str)]) -> StoreFile {
+        StoreFile {
+            key_check: Some(base64::encode_block(&key_check(key).unwrap())),
+            secrets: secrets
+                .iter()
+                .map(|(name, value)| (name.to_string(), encrypt(key, name, value).unwrap()))
+                .collect(),
+            ..StoreFile::default()
+        }
+    }
+
+    #[test]
+    fn accepts_the_store_key() {
+        let key = [7u8; KEY_LEN];
+        verify_key(&store(&key, &[]), &key).unwrap();
+        verify_key(&store(&key, &[("API_KEY", "s3cret")]), &key).unwrap();
+    }
+
+    #[test]
+    fn rejects_another_key() {
+        let file = store(&[7u8; KEY_LEN], &[]);
+        let err = verify_key(&file, &[8u8; KEY_LEN]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert!(err.to_string().contains("does not match this store"), "{}", err);
+    }
+
+    #[test]
+    fn checks_stores_without_key_check_by_decrypting() {
+        let key = [7u8; KEY_LEN];
+        let mut file = store(&key, &[("API_KEY", "s3cret")]);
+        file.key_check = None;
+        verify_key(&file, &key).unwrap();
+        assert!(verify_key(&file, &[8u8; KEY_LEN]).is_err());
+
+        file.secrets.clear();
+        verify_key(&file, &[8u8; KEY_LEN]).unwrap();
+    }
+
+    #[test]
+    fn secrets_round_trip_under_their_name() {
+        let key = [7u8; KEY_LEN];
+        let envelope = encrypt(&key, "API_KEY", "s3cret").unwrap();
+        assert_eq!(decrypt(&key, "API_KEY", &envelope).unwrap(), "s3cret");
+        assert!(decrypt(&key, "OTHER", &envelope).is_err());
+    }
+}
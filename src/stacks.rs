@@ -0,0 +1,548 @@
+use std::{fs, io, path::Path};
+
+/// Project types that do not run on Node.
+pub const STACKS: &[&str] = &["rust", "go", "python", "static"];
+
+const DEFAULT_PYTHON: &str = "3.12";
+const DEFAULT_GO: &str = "1.22";
+
+/// Application name from the project manifest, made safe for Kubernetes
+/// object names.
+pub fn app_name() -> Option<String> {
+    let name = if let Ok(manifest) = fs::read_to_string("Cargo.toml") {
+        toml_string(&manifest, "[package]", "name")
+    } else if let Ok(manifest) = fs::read_to_string("go.mod") {
+        manifest
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("module "))
+            .and_then(|module| module.trim().rsplit('/').next())
+            .map(str::to_string)
+    } else if let Ok(manifest) = fs::read_to_string("pyproject.toml") {
+        toml_string(&manifest, "[project]", "name")
+            .or_else(|| toml_string(&manifest, "[tool.poetry]", "name"))
+    } else {
+        None
+    }?;
+    let name: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    Some(name.trim_matches('-').to_string()).filter(|n| !n.is_empty())
+}
+
+/// Multi-stage Dockerfile for `stack`. Every image reads its port from
+/// `PORT` and runs as a non-root user.
+pub fn dockerfile(stack: &str, port: &str) -> io::Result<String> {
+    dockerfile_in(Path::new("."), stack, port)
+}
+
+fn dockerfile_in(root: &Path, stack: &str, port: &str) -> io::Result<String> {
+    match stack {
+        "rust" => rust(root, port),
+        "go" => Ok(go(root, port)),
+        "python" => python(root, port),
+        "static" => Ok(static_site(port)),
+        other => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("No Dockerfile for {} projects", other),
+        )),
+    }
+}
+
+/// `.dockerignore` entries for build output and local environments.
+pub fn dockerignore(stack: &str) -> &'static [&'static str] {
+    match stack {
+        "rust" => &["target"],
+        "python" => &[".venv", "venv", "__pycache__", "*.pyc", ".pytest_cache"],
+        "static" => &["Dockerfile", ".dockerignore"],
+        _ => &[],
+    }
+}
+
+/// Value of `key = "..."` in `section` of a TOML file. Enough for the
+/// manifest fields read here; not a TOML parser.
+fn toml_string(manifest: &str, section: &str, key: &str) -> Option<String> {
+    let mut current = "";
+    for line in manifest.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            current = line;
+            continue;
+        }
+        if current != section {
+            continue;
+        }
+        if let Some((name, value)) = line.split_once('=') {
+            if name.trim() == key {
+                let value = value.trim();
+                let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+                return value[1..].split(quote).next().map(str::to_string);
+            }
+        }
+    }
+    None
+}
+
+/// The binary `cargo build` produces: the first `[[bin]]`, else the package.
+pub fn rust_binary() -> io::Result<String> {
+    rust_binary_in(Path::new("."))
+}
+
+fn rust_binary_in(root: &Path) -> io::Result<String> {
+    let manifest = fs::read_to_string(root.join("Cargo.toml"))?;
+    toml_string(&manifest, "[[bin]]", "name")
+        .or_else(|| toml_string(&manifest, "[package]", "name"))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Cargo.toml has no [package] name; rustify deploys a single binary crate",
+            )
+        })
+}
+
+fn rust(root: &Path, port: &str) -> io::Result<String> {
+    let binary = rust_binary_in(root)?;
+    Ok(format!(
+        r#"# syntax=docker/dockerfile:1
+# cargo-chef builds the dependencies in their own layer, so they are only
+# rebuilt when Cargo.toml or Cargo.lock change
+FROM lukemathwalker/cargo-chef:latest-rust-1 AS chef
+WORKDIR /app
+
+FROM chef AS planner
+COPY . .
+RUN cargo chef prepare --recipe-path recipe.json
+
+FROM chef AS builder
+COPY --from=planner /app/recipe.json recipe.json
+RUN cargo chef cook --release --recipe-path recipe.json
+COPY . .
+RUN cargo build --release --bin {binary}
+
+FROM gcr.io/distroless/cc-debian12:nonroot
+COPY --from=builder /app/target/release/{binary} /usr/local/bin/app
+ENV PORT={port}
+EXPOSE {port}
+USER nonroot:nonroot
+ENTRYPOINT ["/usr/local/bin/app"]
+"#,
+        binary = binary,
+        port = port
+    ))
+}
+
+fn go(root: &Path, port: &str) -> String {
+    let version = fs::read_to_string(root.join("go.mod"))
+        .ok()
+        .and_then(|manifest| {
+            manifest.lines().find_map(|line| {
+                let version = line.trim().strip_prefix("go ")?.trim();
+                // `go 1.22.3` builds on the golang:1.22 image
+                let mut parts = version.split('.');
+                Some(format!("{}.{}", parts.next()?, parts.next()?))
+            })
+        })
+        .unwrap_or_else(|| DEFAULT_GO.to_string());
+    // A main package at the root, or the only command under cmd/
+    let package = if root.join("main.go").is_file() {
+        ".".to_string()
+    } else {
+        let commands: Vec<String> = fs::read_dir(root.join("cmd"))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect();
+        match commands.as_slice() {
+            [command] => format!("./cmd/{}", command),
+            _ => ".".to_string(),
+        }
+    };
+    format!(
+        r#"# syntax=docker/dockerfile:1
+FROM golang:{version} AS builder
+WORKDIR /src
+COPY go.mod go.sum* ./
+RUN go mod download
+COPY . .
+RUN CGO_ENABLED=0 go build -trimpath -ldflags="-s -w" -o /out/app {package}
+
+FROM gcr.io/distroless/static-debian12:nonroot
+COPY --from=builder /out/app /app
+ENV PORT={port}
+EXPOSE {port}
+USER nonroot:nonroot
+ENTRYPOINT ["/app"]
+"#,
+        version = version,
+        package = package,
+        port = port
+    )
+}
+
+fn python(root: &Path, port: &str) -> io::Result<String> {
+    let version = fs::read_to_string(root.join(".python-version"))
+        .ok()
+        .and_then(|v| {
+            let mut parts = v.trim().split('.');
+            Some(format!("{}.{}", parts.next()?, parts.next()?))
+        })
+        .unwrap_or_else(|| DEFAULT_PYTHON.to_string());
+    let command = python_command_in(root, port)?;
+
+    // uv when the project is locked with it, pip wheels otherwise
+    let build = if root.join("uv.lock").is_file() {
+        format!(
+            r#"FROM ghcr.io/astral-sh/uv:python{version}-bookworm-slim AS builder
+ENV UV_COMPILE_BYTECODE=1 UV_LINK_MODE=copy UV_PYTHON_DOWNLOADS=never
+WORKDIR /app
+COPY pyproject.toml uv.lock ./
+RUN uv sync --frozen --no-dev --no-install-project
+COPY . .
+RUN uv sync --frozen --no-dev
+
+FROM python:{version}-slim
+WORKDIR /app
+COPY --from=builder /app /app
+ENV PATH="/app/.venv/bin:$PATH""#,
+            version = version
+        )
+    } else {
+        // A pyproject.toml project without a lockfile needs its sources to
+        // build its own wheel
+        let (sources, wheel) = if root.join("requirements.txt").is_file() {
+            ("requirements.txt ./", "-r requirements.txt")
+        } else {
+            (". .", ".")
+        };
+        format!(
+            r#"FROM python:{version}-slim AS builder
+WORKDIR /app
+COPY {sources}
+RUN pip wheel --no-cache-dir --wheel-dir /wheels {wheel}
+
+FROM python:{version}-slim
+WORKDIR /app
+COPY --from=builder /wheels /wheels
+RUN pip install --no-cache-dir /wheels/* && rm -rf /wheels
+COPY . ."#,
+            version = version,
+            sources = sources,
+            wheel = wheel
+        )
+    };
+
+    Ok(format!(
+        r#"# syntax=docker/dockerfile:1
+{build}
+ENV PYTHONUNBUFFERED=1 PORT={port}
+RUN useradd --system --uid 10001 app
+USER app
+EXPOSE {port}
+CMD {command}
+"#,
+        build = build,
+        port = port,
+        command = command
+    ))
+}
+
+/// Whether `name` is listed in requirements.txt or pyproject.toml.
+fn python_dependency(root: &Path, name: &str) -> bool {
+    ["requirements.txt", "pyproject.toml"].iter().any(|file| {
+        fs::read_to_string(root.join(file)).is_ok_and(|text| {
+            let text = text.to_lowercase();
+            text.match_indices(name).any(|(i, _)| {
+                let before = text[..i].chars().last();
+                let after = text[i + name.len()..].chars().next();
+                let boundary = |c: Option<char>| {
+                    c.is_none_or(|c| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+                };
+                boundary(before) && boundary(after)
+            })
+        })
+    })
+}
+
+/// The server command for the project's web framework, as a JSON array.
+pub fn python_command(port: &str) -> io::Result<String> {
+    python_command_in(Path::new("."), port)
+}
+
+fn python_command_in(root: &Path, port: &str) -> io::Result<String> {
+    let bind = format!("0.0.0.0:{}", port);
+    let command: Vec<String> = if root.join("manage.py").is_file() {
+        let project = fs::read_dir(root)?
+            .flatten()
+            .find(|entry| entry.path().join("wsgi.py").is_file())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned());
+        match project {
+            Some(project) if python_dependency(root, "gunicorn") => vec![
+                "gunicorn".into(),
+                format!("{}.wsgi:application", project),
+                "--bind".into(),
+                bind,
+            ],
+            _ => vec!["python".into(), "manage.py".into(), "runserver".into(), bind],
+        }
+    } else {
+        let (file, module) = [("main.py", "main"), ("app.py", "app"), ("app/main.py", "app.main")]
+            .iter()
+            .find(|(file, _)| root.join(file).is_file())
+            .copied()
+            .unwrap_or(("main.py", "main"));
+        if python_dependency(root, "fastapi") || python_dependency(root, "starlette") {
+            vec![
+                "uvicorn".into(),
+                format!("{}:app", module),
+                "--host".into(),
+                "0.0.0.0".into(),
+                "--port".into(),
+                port.into(),
+            ]
+        } else if python_dependency(root, "gunicorn") {
+            vec!["gunicorn".into(), format!("{}:app", module), "--bind".into(), bind]
+        } else {
+            vec!["python".into(), file.into()]
+        }
+    };
+    let words = command
+        .iter()
+        .map(serde_json::to_string)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(io::Error::other)?;
+    Ok(format!("[{}]", words.join(", ")))
+}
+
+/// `ARG` instructions for `names`, one per line.
+pub fn arg_lines(names: &[&String]) -> String {
+    names.iter().map(|name| format!("ARG {}\n", name)).collect()
+}
+
+fn static_site(port: &str) -> String {
+    static_server("", ".", port, false)
+}
+
+/// Dockerfile that builds a JS project whose output is plain files and
+/// serves `output` without a Node runtime. `spa` answers unknown paths with
+/// `index.html` for client-side routing. `build_args` are declared in the
+/// build stage.
+pub fn static_build(output: &str, port: &str, spa: bool, build_args: &[&String]) -> String {
+    let (image, install) = if Path::new("bun.lockb").is_file() || Path::new("bun.lock").is_file() {
+        ("oven/bun:1", "bun install --frozen-lockfile")
+    } else if Path::new("pnpm-lock.yaml").is_file() {
+        ("node:22-alpine", "corepack enable && pnpm install --frozen-lockfile")
+    } else if Path::new("yarn.lock").is_file() {
+        ("node:22-alpine", "corepack enable && yarn install")
+    } else if Path::new("package-lock.json").is_file() {
+        ("node:22-alpine", "npm ci")
+    } else {
+        ("node:22-alpine", "npm install")
+    };
+    let run = if image.starts_with("oven/bun") { "bun run" } else { "npm run" };
+    let build = format!(
+        r#"FROM {image} AS build
+WORKDIR /app
+COPY . .
+RUN {install}
+{args}RUN {run} build
+
+"#,
+        image = image,
+        install = install,
+        args = arg_lines(build_args),
+        run = run
+    );
+    let output = format!("--from=build /app/{}", output.trim_end_matches('/'));
+    static_server(&build, &output, port, spa)
+}
+
+/// Caddy serving the site copied from `source` on `port`. A compress stage
+/// gzips and brotlis the text assets first so they are served
+/// precompressed; `stages` come before it.
+fn static_server(stages: &str, source: &str, port: &str, spa: bool) -> String {
+    let fallback = if spa {
+        "{path} {path}/ /index.html"
+    } else {
+        "{path} {path}/ {path}.html"
+    };
+    format!(
+        r#"# syntax=docker/dockerfile:1
+{stages}FROM alpine:3.20 AS compress
+RUN apk add --no-cache brotli gzip
+COPY {source} /srv
+RUN find /srv -type f -size +1k \( -name '*.html' -o -name '*.css' -o -name '*.js' \
+    -o -name '*.mjs' -o -name '*.json' -o -name '*.svg' -o -name '*.txt' -o -name '*.xml' \
+    -o -name '*.wasm' \) -exec gzip -9 -k {{}} \; -exec brotli -q 11 -k {{}} \;
+
+FROM caddy:2.8-alpine
+COPY <<'EOF' /etc/caddy/Caddyfile
+{{
+	admin off
+	auto_https off
+	persist_config off
+	servers {{
+		metrics
+	}}
+}}
+
+:{port} {{
+	root * /srv
+
+	# File names with a content hash never change; everything else is revalidated
+	@hashed path_regexp [.-][A-Za-z0-9_]{{8,}}\.(js|mjs|css|woff2?|png|jpe?g|gif|svg|webp|avif|ico|wasm)$
+	header @hashed Cache-Control "public, max-age=31536000, immutable"
+	header /_next/static/* Cache-Control "public, max-age=31536000, immutable"
+	header ?Cache-Control "no-cache"
+
+	handle /health {{
+		respond "ok" 200
+	}}
+
+	# Prometheus format, scraped from the app port
+	handle /metrics {{
+		metrics
+	}}
+
+	handle {{
+		try_files {fallback}
+		file_server {{
+			precompressed br gzip
+		}}
+	}}
+}}
+EOF
+COPY --from=compress /srv /srv
+# Caddy keeps its state under the XDG dirs; /tmp stays writable when the
+# root filesystem is read-only
+ENV XDG_CONFIG_HOME=/tmp/caddy XDG_DATA_HOME=/tmp/caddy
+USER 10001
+EXPOSE {port}
+"#,
+        stages = stages,
+        source = source,
+        fallback = fallback,
+        port = port
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, path::PathBuf};
+
+    /// Project files in a scratch directory, removed on drop.
+    struct Project {
+        dir: PathBuf,
+    }
+
+    impl Project {
+        fn new(name: &str, files: &[(&str, &str)]) -> Self {
+            let dir = env::temp_dir().join(format!("rustify-stacks-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            for (path, contents) in files {
+                let path = dir.join(path);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, contents).unwrap();
+            }
+            Project { dir }
+        }
+
+        fn dockerfile(&self, stack: &str) -> String {
+            dockerfile_in(&self.dir, stack, "8080").unwrap()
+        }
+    }
+
+    impl Drop for Project {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    /// Set `UPDATE_SNAPSHOTS=1` to rewrite the snapshot after an intended
+    /// Dockerfile change.
+    fn assert_snapshot(name: &str, actual: &str) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/snapshots/stacks")
+            .join(format!("{}.snap", name));
+        if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, actual).unwrap();
+            return;
+        }
+        let expected = fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            expected == actual,
+            "{} differs from tests/snapshots/stacks (rerun with UPDATE_SNAPSHOTS=1 if intended)",
+            name
+        );
+    }
+
+    #[test]
+    fn rust_matches_snapshot() {
+        let project = Project::new(
+            "rust",
+            &[(
+                "Cargo.toml",
+                "[package]\nname = \"shop-api\"\n\n[[bin]]\nname = \"server\"\npath = \"src/main.rs\"\n",
+            )],
+        );
+        assert_snapshot("rust", &project.dockerfile("rust"));
+    }
+
+    #[test]
+    fn go_matches_snapshot() {
+        let project = Project::new(
+            "go",
+            &[
+                ("go.mod", "module github.com/acme/shop\n\ngo 1.22.3\n"),
+                ("cmd/server/main.go", "package main\n"),
+            ],
+        );
+        assert_snapshot("go", &project.dockerfile("go"));
+    }
+
+    #[test]
+    fn python_uv_matches_snapshot() {
+        let project = Project::new(
+            "python-uv",
+            &[
+                ("pyproject.toml", "[project]\nname = \"shop\"\ndependencies = [\"fastapi>=0.110\"]\n"),
+                ("uv.lock", "version = 1\n"),
+                (".python-version", "3.11.9\n"),
+                ("app/main.py", "app = None\n"),
+            ],
+        );
+        assert_snapshot("python-uv", &project.dockerfile("python"));
+    }
+
+    #[test]
+    fn python_pip_matches_snapshot() {
+        let project = Project::new(
+            "python-pip",
+            &[
+                ("requirements.txt", "Django==5.0\ngunicorn==22.0\n"),
+                ("manage.py", ""),
+                ("shop/wsgi.py", ""),
+            ],
+        );
+        assert_snapshot("python-pip", &project.dockerfile("python"));
+    }
+
+    #[test]
+    fn static_matches_snapshot() {
+        let project = Project::new("static", &[("index.html", "<h1>shop</h1>\n")]);
+        assert_snapshot("static", &project.dockerfile("static"));
+    }
+
+    #[test]
+    fn rejects_unknown_stacks() {
+        let project = Project::new("unknown", &[]);
+        let err = dockerfile_in(&project.dir, "cobol", "8080").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(dockerfile_in(&project.dir, "rust", "8080").is_err(), "no Cargo.toml");
+    }
+}
@@ -0,0 +1,805 @@
+use super::{
+    catalog::Catalog,
+    effects::{self, Effect},
+    packagejson::{Edit, PackageJson},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{self, Command},
+};
+use walkdir::WalkDir;
+
+/// Manifest at the root of a user template directory.
+pub const MANIFEST_FILE: &str = "rustify-template.json";
+/// Registry of user templates inside the config directory.
+const REGISTRY_FILE: &str = "templates.json";
+
+/// Variable holding `name@version` of the package manager found installed,
+/// written to the `packageManager` field of the root package.json.
+pub const PACKAGE_MANAGER_SPEC: &str = "packageManagerSpec";
+
+/// Files shared by every JavaScript scaffold; a framework file with the same
+/// path replaces the shared one.
+const COMMON: &str = "common";
+
+/// `(path below templates/, contents)` for every embedded template file.
+macro_rules! embedded {
+    ($($path:literal),* $(,)?) => {
+        &[$(($path, include_str!(concat!("../templates/", $path)))),*]
+    };
+}
+
+const BUILTIN_FILES: &[(&str, &str)] = embedded![
+    "common/.dockerignore",
+    "common/.editorconfig",
+    "common/.eslintrc.json",
+    "common/.github/workflows/ci.yml",
+    "common/.prettierrc",
+    "common/Dockerfile",
+    "common/_gitignore",
+    "common/docker-compose.yml",
+    "angular/angular.json",
+    "angular/package.json",
+    "angular/src/app/app.component.ts",
+    "angular/src/index.html",
+    "angular/src/main.ts",
+    "angular/tsconfig.json",
+    "astro/astro.config.mjs",
+    "astro/package.json",
+    "astro/src/pages/index.astro",
+    "bun/package.json",
+    "bun/src/index.ts",
+    "bun/tsconfig.json",
+    "mern/client/index.html",
+    "mern/client/package.json",
+    "mern/client/src/App.jsx",
+    "mern/client/src/main.jsx",
+    "mern/client/vite.config.js",
+    "mern/docker-compose.yml",
+    "mern/Dockerfile",
+    "mern/package.json",
+    "mern/server/index.js",
+    "mern/server/package.json",
+    "next/.eslintrc.json",
+    "next/app/health/route.ts",
+    "next/app/layout.tsx",
+    "next/app/metrics/route.ts",
+    "next/app/page.tsx",
+    "next/next.config.js",
+    "next/package.json",
+    "next/tsconfig.json",
+    "nuxt/app.vue",
+    "nuxt/nuxt.config.ts",
+    "nuxt/package.json",
+    "nuxt/server/middleware/requests.ts",
+    "nuxt/server/routes/health.ts",
+    "nuxt/server/routes/metrics.ts",
+    "nuxt/server/utils/metrics.ts",
+    "react/index.html",
+    "react/package.json",
+    "react/src/App.tsx",
+    "react/src/main.tsx",
+    "react/tsconfig.json",
+    "react/vite.config.ts",
+    "remix/app/root.tsx",
+    "remix/app/routes/_index.tsx",
+    "remix/app/routes/health.ts",
+    "remix/app/routes/metrics.ts",
+    "remix/package.json",
+    "remix/vite.config.ts",
+    "svelte/index.html",
+    "svelte/package.json",
+    "svelte/src/App.svelte",
+    "svelte/src/main.ts",
+    "svelte/vite.config.ts",
+    "vue/.eslintrc.json",
+    "vue/index.html",
+    "vue/package.json",
+    "vue/src/App.vue",
+    "vue/src/main.ts",
+    "vue/vite.config.ts",
+];
+
+pub const BUILTIN: &[&str] = &[
+    "next", "react", "vue", "svelte", "astro", "remix", "mern", "angular", "nuxt", "bun",
+];
+
+pub const PACKAGE_MANAGERS: &[&str] = &["npm", "yarn", "pnpm", "bun"];
+
+/// Values substituted for `{{name}}` placeholders.
+pub type Variables = BTreeMap<String, String>;
+
+/// Turns a directory name into a valid npm package name.
+pub fn package_name(raw: &str) -> String {
+    let mut name = String::new();
+    for c in raw.trim().to_lowercase().chars() {
+        if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+            name.push(c);
+        } else if !name.ends_with('-') {
+            name.push('-');
+        }
+    }
+    let name = name.trim_matches(|c| c == '-' || c == '.' || c == '_');
+    if name.is_empty() {
+        "app".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Builds the variable set for a project; everything derived from the
+/// package manager is filled in here so templates stay declarative.
+pub fn variables(name: &str, port: &str, package_manager: &str) -> io::Result<Variables> {
+    if port.parse::<u16>().map_or(true, |p| p == 0) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid port: {}", port),
+        ));
+    }
+    let (install, run, lockfile, start) = match package_manager {
+        "npm" => ("npm install", "npm run", "package-lock.json", r#"["npm", "start"]"#),
+        "yarn" => ("yarn install", "yarn", "yarn.lock", r#"["yarn", "start"]"#),
+        "pnpm" => ("pnpm install", "pnpm", "pnpm-lock.yaml", r#"["pnpm", "start"]"#),
+        "bun" => ("bun install", "bun run", "bun.lockb", r#"["bun", "run", "start"]"#),
+        other => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Unsupported package manager: {} (expected one of {})",
+                    other,
+                    PACKAGE_MANAGERS.join(", ")
+                ),
+            ))
+        }
+    };
+    let image = if package_manager == "bun" { "oven/bun:1" } else { "node:20-alpine" };
+    let docker_setup = match package_manager {
+        "yarn" | "pnpm" => "RUN corepack enable\n",
+        _ => "",
+    };
+    let ci_setup = match package_manager {
+        "bun" => "      - uses: oven-sh/setup-bun@v1\n",
+        "pnpm" => concat!(
+            "      - uses: pnpm/action-setup@v4\n        with:\n          version: 9\n",
+            "      - uses: actions/setup-node@v4\n        with:\n          node-version: 20\n"
+        ),
+        _ => "      - uses: actions/setup-node@v4\n        with:\n          node-version: 20\n",
+    };
+
+    let mut vars = Variables::new();
+    for (key, value) in [
+        ("name", name),
+        ("port", port),
+        ("packageManager", package_manager),
+        ("install", install),
+        ("run", run),
+        ("lockfile", lockfile),
+        ("startCommand", start),
+        ("image", image),
+        ("dockerSetup", docker_setup),
+        ("ciSetup", ci_setup),
+    ] {
+        vars.insert(key.to_string(), value.to_string());
+    }
+    Ok(vars)
+}
+
+/// `name@version` of `package_manager` as installed here; `None` when it is
+/// missing or does not print a plain version.
+pub fn installed_package_manager(package_manager: &str) -> Option<String> {
+    let output = Command::new(package_manager).arg("--version").run().ok()?;
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let plain = version.starts_with(|c: char| c.is_ascii_digit())
+        && version
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+'));
+    (output.status.success() && plain).then(|| format!("{}@{}", package_manager, version))
+}
+
+/// Substitutes `{{variable}}` placeholders. `${{ ... }}` (GitHub Actions
+/// expressions) is left untouched; unknown variables are an error so a typo
+/// never ends up in a generated file.
+pub fn render(source: &str, template: &str, vars: &Variables) -> io::Result<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let escaped = rest[..start].ends_with('$');
+        let end = match rest[start..].find("}}") {
+            Some(end) => start + end,
+            None => break,
+        };
+        let key = &rest[start + 2..end];
+        let is_placeholder = !escaped
+            && !key.is_empty()
+            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        out.push_str(&rest[..start]);
+        if is_placeholder {
+            match vars.get(key) {
+                Some(value) => out.push_str(value),
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{}: unknown template variable {{{{{}}}}}", source, key),
+                    ))
+                }
+            }
+        } else {
+            out.push_str(&rest[start..end + 2]);
+        }
+        rest = &rest[end + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Output path of a template file; `_gitignore` is stored under another name
+/// so it does not apply to the templates directory itself.
+pub fn output_path(path: &str) -> String {
+    match path.rsplit_once('/') {
+        Some((dir, "_gitignore")) => format!("{}/.gitignore", dir),
+        None if path == "_gitignore" => ".gitignore".to_string(),
+        _ => path.to_string(),
+    }
+}
+
+/// `rustify-template.json` at the root of a template directory.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    #[serde(default)]
+    pub description: String,
+    /// Asked for in order, before any file is rendered.
+    #[serde(default)]
+    pub variables: Vec<VariableSpec>,
+    #[serde(default)]
+    pub files: Vec<FileRule>,
+    #[serde(default)]
+    pub hooks: Hooks,
+    /// Catalog set that `catalog:` ranges in package.json files resolve
+    /// against; built-in templates use their framework's.
+    pub catalog: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VariableSpec {
+    pub name: String,
+    pub prompt: Option<String>,
+    pub default: Option<String>,
+    #[serde(default)]
+    pub choices: Vec<String>,
+}
+
+/// Only renders the files matching `path` when `when` holds.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileRule {
+    /// A file path, or a directory ending in `/` or `/**`.
+    pub path: String,
+    /// `var`, `!var`, `var == value` or `var != value`.
+    pub when: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Hooks {
+    /// Shell commands run in the new project after the files are written.
+    #[serde(default)]
+    pub post_init: Vec<String>,
+}
+
+/// A loaded template: its manifest and file contents keyed by output path.
+pub struct Template {
+    pub manifest: Manifest,
+    pub files: BTreeMap<String, String>,
+}
+
+/// Files of a built-in template, shared files included, sorted by path.
+pub fn builtin(framework: &str) -> Option<Template> {
+    if !BUILTIN.contains(&framework) {
+        return None;
+    }
+    let mut files = BTreeMap::new();
+    for layer in [COMMON, framework] {
+        let prefix = format!("{}/", layer);
+        for (path, contents) in BUILTIN_FILES {
+            if let Some(relative) = path.strip_prefix(&prefix) {
+                files.insert(output_path(relative), contents.to_string());
+            }
+        }
+    }
+
+    let mut manifest = Manifest {
+        description: format!("Built-in {} project", framework),
+        catalog: Some(framework.to_string()),
+        ..Manifest::default()
+    };
+    if framework == "bun" {
+        manifest.variables.push(VariableSpec {
+            name: "packageManager".to_string(),
+            default: Some("bun".to_string()),
+            ..VariableSpec::default()
+        });
+    }
+    Some(Template { manifest, files })
+}
+
+fn is_git_url(source: &str) -> bool {
+    ["https://", "http://", "ssh://", "git://", "git@"]
+        .iter()
+        .any(|scheme| source.starts_with(scheme))
+        || source.trim_end_matches('/').ends_with(".git")
+}
+
+/// Reads a template directory: the manifest plus every other file in it.
+pub fn load_dir(dir: &Path) -> io::Result<Template> {
+    let manifest_path = dir.join(MANIFEST_FILE);
+    let manifest: Manifest = match fs::read_to_string(&manifest_path) {
+        Ok(json) => serde_json::from_str(&json).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid {}: {}", manifest_path.display(), e),
+            )
+        })?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} has no {}", dir.display(), MANIFEST_FILE),
+            ))
+        }
+        Err(e) => return Err(e),
+    };
+
+    let mut files = BTreeMap::new();
+    let walker = WalkDir::new(dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git");
+    for entry in walker {
+        let entry = entry.map_err(io::Error::other)?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative = entry
+            .path()
+            .strip_prefix(dir)
+            .unwrap_or(entry.path())
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect::<Vec<_>>()
+            .join("/");
+        if relative == MANIFEST_FILE {
+            continue;
+        }
+        let contents = fs::read_to_string(entry.path()).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("{}: template files must be UTF-8 text ({})", relative, e),
+            )
+        })?;
+        files.insert(output_path(&relative), contents);
+    }
+    Ok(Template { manifest, files })
+}
+
+/// Shallow-clones `url` (optionally `url#branch`) and loads it.
+fn load_git(url: &str) -> io::Result<Template> {
+    let (url, branch) = match url.rsplit_once('#') {
+        Some((url, branch)) => (url, Some(branch)),
+        None => (url, None),
+    };
+    let checkout = env::temp_dir().join(format!("rustify-template-{}", process::id()));
+    let _ = fs::remove_dir_all(&checkout);
+
+    println!("📥 Fetching template from {}", url);
+    let mut git = Command::new("git");
+    git.args(["clone", "--quiet", "--depth", "1"]);
+    if let Some(branch) = branch {
+        git.args(["--branch", branch]);
+    }
+    // The checkout is scratch space that is removed right after loading, so
+    // it is fetched even in dry-run mode
+    let output = git.arg(url).arg(&checkout).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "git clone {} failed: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    let template = load_dir(&checkout);
+    let _ = fs::remove_dir_all(&checkout);
+    template
+}
+
+fn load_source(source: &str) -> io::Result<Template> {
+    if is_git_url(source) {
+        load_git(source)
+    } else {
+        load_dir(Path::new(source))
+    }
+}
+
+/// Resolves `--template`: a directory, a git URL, a registry entry or a
+/// built-in framework, in that order.
+pub fn resolve(spec: &str) -> io::Result<Template> {
+    if is_git_url(spec) || Path::new(spec).is_dir() {
+        return load_source(spec);
+    }
+    if let Some(entry) = Registry::load()?.templates.get(spec) {
+        return load_source(&entry.source);
+    }
+    builtin(spec).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "Unknown template '{}'. Run `rustify templates list` to see the available ones.",
+                spec
+            ),
+        )
+    })
+}
+
+fn prompt(question: &str, default: Option<&str>) -> io::Result<String> {
+    match default {
+        Some(default) => print!("{} [{}]: ", question, default),
+        None => print!("{}: ", question),
+    }
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    let answer = answer.trim();
+    Ok(match (answer.is_empty(), default) {
+        (true, Some(default)) => default.to_string(),
+        _ => answer.to_string(),
+    })
+}
+
+/// Values for the manifest's variables: given on the command line, then
+/// asked for (when `interactive`), then the declared default.
+pub fn resolve_variables(
+    manifest: &Manifest,
+    given: &Variables,
+    interactive: bool,
+) -> io::Result<Variables> {
+    let mut resolved = given.clone();
+    for spec in &manifest.variables {
+        let value = match given.get(&spec.name) {
+            Some(value) => value.clone(),
+            None => match (&spec.prompt, interactive) {
+                (Some(question), true) => prompt(question, spec.default.as_deref())?,
+                _ => spec.default.clone().ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!(
+                            "Template variable '{}' has no value; pass --var {}=VALUE",
+                            spec.name, spec.name
+                        ),
+                    )
+                })?,
+            },
+        };
+        if !spec.choices.is_empty() && !spec.choices.contains(&value) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Invalid value '{}' for {} (expected one of {})",
+                    value,
+                    spec.name,
+                    spec.choices.join(", ")
+                ),
+            ));
+        }
+        resolved.insert(spec.name.clone(), value);
+    }
+    Ok(resolved)
+}
+
+fn truthy(value: &str) -> bool {
+    !matches!(
+        value.to_lowercase().as_str(),
+        "" | "false" | "no" | "n" | "0" | "off" | "none"
+    )
+}
+
+fn condition_holds(condition: &str, vars: &Variables) -> io::Result<bool> {
+    let lookup = |name: &str| {
+        vars.get(name.trim()).map(String::as_str).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Condition '{}' uses unknown variable '{}'", condition, name.trim()),
+            )
+        })
+    };
+    if let Some((name, value)) = condition.split_once("!=") {
+        return Ok(lookup(name)? != value.trim());
+    }
+    if let Some((name, value)) = condition.split_once("==") {
+        return Ok(lookup(name)? == value.trim());
+    }
+    match condition.trim().strip_prefix('!') {
+        Some(name) => Ok(!truthy(lookup(name)?)),
+        None => Ok(truthy(lookup(condition)?)),
+    }
+}
+
+fn rule_matches(pattern: &str, path: &str) -> bool {
+    let dir = pattern.trim_end_matches("**").trim_end_matches('/');
+    if pattern.ends_with('/') || pattern.ends_with("/**") {
+        path.starts_with(&format!("{}/", dir))
+    } else {
+        path == pattern
+    }
+}
+
+impl Template {
+    /// Files whose conditions hold for `vars`.
+    pub fn selected_files(&self, vars: &Variables) -> io::Result<BTreeMap<String, String>> {
+        let mut selected = BTreeMap::new();
+        'files: for (path, contents) in &self.files {
+            for rule in &self.manifest.files {
+                if rule_matches(&rule.path, path) && !condition_holds(&rule.when, vars)? {
+                    continue 'files;
+                }
+            }
+            selected.insert(path.clone(), contents.clone());
+        }
+        Ok(selected)
+    }
+
+    /// Runs the post-init hooks in the current directory.
+    pub fn run_hooks(&self, vars: &Variables) -> io::Result<()> {
+        for hook in &self.manifest.hooks.post_init {
+            let command = render("post-init hook", hook, vars)?;
+            println!("🪝 Running post-init hook: {}", command);
+            let status = if cfg!(windows) {
+                Command::new("cmd").args(["/C", &command]).run_status()?
+            } else {
+                Command::new("sh").args(["-c", &command]).run_status()?
+            };
+            if !status.success() {
+                return Err(io::Error::other(format!("Post-init hook failed: {}", command)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Renders every file first and only then writes, so a bad template leaves
+/// the directory untouched. Returns the written paths in order.
+pub fn write_files(
+    dir: &Path,
+    files: &BTreeMap<String, String>,
+    vars: &Variables,
+    catalog: Option<&str>,
+) -> io::Result<Vec<String>> {
+    let versions = if files.keys().any(|path| is_package_json(path)) {
+        Some(Catalog::load()?)
+    } else {
+        None
+    };
+    if let (Some(versions), Some(name)) = (&versions, catalog) {
+        for problem in versions.problems(Some(name)) {
+            println!("⚠️  Incompatible catalog versions: {}", problem);
+        }
+    }
+
+    let mut written = Vec::new();
+    for (path, contents) in render_files(files, vars, versions.as_ref(), catalog)? {
+        let target = dir.join(&path);
+        if let Some(parent) = target.parent() {
+            effects::create_dir_all(parent)?;
+        }
+        effects::write(&target, contents)?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+fn is_package_json(path: &str) -> bool {
+    path == "package.json" || path.ends_with("/package.json")
+}
+
+/// Every file with its placeholders filled in and, given `versions`, the
+/// `catalog:` ranges of its package.json files resolved.
+fn render_files(
+    files: &BTreeMap<String, String>,
+    vars: &Variables,
+    versions: Option<&Catalog>,
+    catalog: Option<&str>,
+) -> io::Result<Vec<(String, String)>> {
+    files
+        .iter()
+        .map(|(path, contents)| {
+            let mut rendered = render(path, contents, vars)?;
+            if let Some(versions) = versions.filter(|_| is_package_json(path)) {
+                rendered = versions.resolve(path, &rendered, catalog)?;
+            }
+            if let (true, Some(spec)) = (path == "package.json", vars.get(PACKAGE_MANAGER_SPEC)) {
+                let mut pkg = PackageJson::parse(&rendered)
+                    .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path, e)))?;
+                pkg.apply(&[Edit::PackageManager(spec)]);
+                rendered = pkg.render()?;
+            }
+            Ok((path.clone(), rendered))
+        })
+        .collect()
+}
+
+/// User templates, stored in the rustify config directory. Built-in
+/// frameworks are implicit entries that user entries may shadow.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Registry {
+    #[serde(default)]
+    pub templates: BTreeMap<String, RegistryEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryEntry {
+    /// Absolute directory path or git URL.
+    pub source: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+}
+
+/// `$XDG_CONFIG_HOME/rustify`, `~/.config/rustify` or `%APPDATA%\rustify`.
+pub fn config_dir() -> io::Result<PathBuf> {
+    if let Ok(dir) = env::var("XDG_CONFIG_HOME") {
+        return Ok(PathBuf::from(dir).join("rustify"));
+    }
+    if let Ok(dir) = env::var("APPDATA") {
+        return Ok(PathBuf::from(dir).join("rustify"));
+    }
+    env::var("HOME")
+        .map(|home| PathBuf::from(home).join(".config").join("rustify"))
+        .map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "Cannot locate home directory; set XDG_CONFIG_HOME",
+            )
+        })
+}
+
+impl Registry {
+    fn path() -> io::Result<PathBuf> {
+        Ok(config_dir()?.join(REGISTRY_FILE))
+    }
+
+    pub fn load() -> io::Result<Self> {
+        let path = Self::path()?;
+        match fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid {}: {}", path.display(), e),
+                )
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Registry::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path()?;
+        if let Some(dir) = path.parent() {
+            effects::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(io::Error::other)?;
+        effects::write(&path, json + "\n")
+    }
+
+    /// Registers `source` under `name` after checking that it loads.
+    pub fn add(&mut self, name: &str, source: &str, description: Option<&str>) -> io::Result<()> {
+        let source = if is_git_url(source) {
+            source.to_string()
+        } else {
+            fs::canonicalize(source)?.to_string_lossy().into_owned()
+        };
+        let template = load_source(&source)?;
+        let description = description
+            .map(str::to_string)
+            .unwrap_or(template.manifest.description);
+        self.templates
+            .insert(name.to_string(), RegistryEntry { source, description });
+        Ok(())
+    }
+
+    /// Returns false when there was no user entry called `name`.
+    pub fn remove(&mut self, name: &str) -> io::Result<bool> {
+        if self.templates.remove(name).is_some() {
+            return Ok(true);
+        }
+        if BUILTIN.contains(&name) {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("'{}' is a built-in template and cannot be removed", name),
+            ));
+        }
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every rendered file of a built-in template, in path order.
+    fn snapshot(framework: &str) -> String {
+        let template = builtin(framework).unwrap();
+        let vars = variables("demo", "3000", "npm").unwrap();
+        let files = template.selected_files(&vars).unwrap();
+        let rendered = render_files(
+            &files,
+            &vars,
+            Some(&Catalog::embedded().unwrap()),
+            template.manifest.catalog.as_deref(),
+        )
+        .unwrap();
+        rendered
+            .into_iter()
+            .map(|(path, contents)| format!("=== {} ===\n{}\n", path, contents))
+            .collect()
+    }
+
+    /// Set `UPDATE_SNAPSHOTS=1` to rewrite the snapshots after an intended
+    /// template change.
+    #[test]
+    fn builtin_templates_match_snapshots() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots/templates");
+        let update = env::var_os("UPDATE_SNAPSHOTS").is_some();
+        let mut stale = Vec::new();
+        for framework in BUILTIN {
+            let path = dir.join(format!("{}.snap", framework));
+            let actual = snapshot(framework);
+            if update {
+                fs::create_dir_all(&dir).unwrap();
+                fs::write(&path, &actual).unwrap();
+            } else if fs::read_to_string(&path).ok().as_deref() != Some(actual.as_str()) {
+                stale.push(framework.to_string());
+            }
+        }
+        assert!(
+            stale.is_empty(),
+            "templates differ from tests/snapshots/templates: {} (rerun with UPDATE_SNAPSHOTS=1 if intended)",
+            stale.join(", ")
+        );
+    }
+
+    #[test]
+    fn rendering_is_deterministic() {
+        for framework in BUILTIN {
+            assert_eq!(snapshot(framework), snapshot(framework), "{}", framework);
+        }
+    }
+
+    #[test]
+    fn pins_the_package_manager_in_the_root_package_json() {
+        let template = builtin("mern").unwrap();
+        let mut vars = variables("demo", "3000", "pnpm").unwrap();
+        vars.insert(PACKAGE_MANAGER_SPEC.to_string(), "pnpm@9.12.0".to_string());
+        let files = template.selected_files(&vars).unwrap();
+        let rendered: BTreeMap<_, _> = render_files(&files, &vars, None, None)
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert!(rendered["package.json"].contains("\"packageManager\": \"pnpm@9.12.0\""));
+        assert!(rendered
+            .iter()
+            .filter(|(path, _)| path.as_str() != "package.json")
+            .all(|(_, contents)| !contents.contains("packageManager")));
+    }
+
+    #[test]
+    fn unknown_variables_are_errors() {
+        let vars = variables("demo", "3000", "npm").unwrap();
+        let err = render("file", "{{nmae}}", &vars).unwrap_err();
+        assert!(err.to_string().contains("unknown template variable {{nmae}}"));
+        assert_eq!(render("ci", "${{ matrix.node }}", &vars).unwrap(), "${{ matrix.node }}");
+    }
+}
@@ -0,0 +1,13 @@
+import { Component } from '@angular/core';
+
+@Component({
+  selector: 'app-root',
+  standalone: true,
+  template: `
+    <main>
+      <h1>{{name}}</h1>
+      <p>Edit src/app/app.component.ts to get started.</p>
+    </main>
+  `,
+})
+export class AppComponent {}
@@ -0,0 +1,38 @@
+const port = Number(process.env.PORT ?? {{port}});
+
+let requests = 0;
+
+// Prometheus text format, scraped from the app port
+function metrics(): string {
+  return [
+    '# HELP http_requests_total Requests received.',
+    '# TYPE http_requests_total counter',
+    `http_requests_total ${requests}`,
+    '# HELP process_resident_memory_bytes Resident memory size in bytes.',
+    '# TYPE process_resident_memory_bytes gauge',
+    `process_resident_memory_bytes ${process.memoryUsage().rss}`,
+    '# HELP process_uptime_seconds Seconds since the process started.',
+    '# TYPE process_uptime_seconds gauge',
+    `process_uptime_seconds ${process.uptime()}`,
+    '',
+  ].join('\n');
+}
+
+const server = Bun.serve({
+  port,
+  fetch(req) {
+    requests++;
+    const { pathname } = new URL(req.url);
+    if (pathname === '/health') {
+      return new Response('ok');
+    }
+    if (pathname === '/metrics') {
+      return new Response(metrics(), {
+        headers: { 'Content-Type': 'text/plain; version=0.0.4' },
+      });
+    }
+    return new Response('Hello from {{name}}!');
+  },
+});
+
+console.log(`Listening on http://localhost:${server.port}`);
@@ -0,0 +1,18 @@
+import { useEffect, useState } from 'react';
+
+export default function App() {
+  const [status, setStatus] = useState(null);
+
+  useEffect(() => {
+    fetch('/api/status')
+      .then((res) => res.json())
+      .then(setStatus);
+  }, []);
+
+  return (
+    <main>
+      <h1>{{name}}</h1>
+      <pre>{JSON.stringify(status, null, 2)}</pre>
+    </main>
+  );
+}
@@ -0,0 +1,52 @@
+const path = require('path');
+const express = require('express');
+const mongoose = require('mongoose');
+
+const port = process.env.PORT || {{port}};
+const app = express();
+app.use(express.json());
+
+let requests = 0;
+app.use((req, res, next) => {
+  requests++;
+  next();
+});
+
+// Probed by Kubernetes; answers while the database is still connecting
+app.get('/health', (req, res) => res.send('ok'));
+
+// Prometheus text format, scraped from the app port
+app.get('/metrics', (req, res) => {
+  res.type('text/plain; version=0.0.4').send(
+    [
+      '# HELP http_requests_total Requests received.',
+      '# TYPE http_requests_total counter',
+      `http_requests_total ${requests}`,
+      '# HELP process_resident_memory_bytes Resident memory size in bytes.',
+      '# TYPE process_resident_memory_bytes gauge',
+      `process_resident_memory_bytes ${process.memoryUsage().rss}`,
+      '# HELP process_uptime_seconds Seconds since the process started.',
+      '# TYPE process_uptime_seconds gauge',
+      `process_uptime_seconds ${process.uptime()}`,
+      '# HELP mongodb_connected Whether the MongoDB connection is open.',
+      '# TYPE mongodb_connected gauge',
+      `mongodb_connected ${mongoose.connection.readyState === 1 ? 1 : 0}`,
+      '',
+    ].join('\n')
+  );
+});
+
+app.get('/api/status', (req, res) => {
+  res.json({ name: '{{name}}', database: mongoose.connection.readyState === 1 });
+});
+
+// Serve the built client
+const client = path.join(__dirname, '..', 'client', 'dist');
+app.use(express.static(client));
+app.get('*', (req, res) => res.sendFile(path.join(client, 'index.html')));
+
+if (process.env.MONGODB_URI) {
+  mongoose.connect(process.env.MONGODB_URI).catch((err) => console.error(err));
+}
+
+app.listen(port, () => console.log(`Listening on http://localhost:${port}`));
@@ -0,0 +1,6 @@
+// Probed by Kubernetes
+export const dynamic = 'force-dynamic';
+
+export function GET() {
+  return new Response('ok');
+}
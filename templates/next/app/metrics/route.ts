@@ -0,0 +1,18 @@
+// Prometheus text format, scraped from the app port
+export const dynamic = 'force-dynamic';
+export const runtime = 'nodejs';
+
+export function GET() {
+  const body = [
+    '# HELP process_resident_memory_bytes Resident memory size in bytes.',
+    '# TYPE process_resident_memory_bytes gauge',
+    `process_resident_memory_bytes ${process.memoryUsage().rss}`,
+    '# HELP process_uptime_seconds Seconds since the process started.',
+    '# TYPE process_uptime_seconds gauge',
+    `process_uptime_seconds ${process.uptime()}`,
+    '',
+  ].join('\n');
+  return new Response(body, {
+    headers: { 'Content-Type': 'text/plain; version=0.0.4' },
+  });
+}
@@ -0,0 +1,8 @@
+export default function Home() {
+  return (
+    <main>
+      <h1>{{name}}</h1>
+      <p>Edit app/page.tsx to get started.</p>
+    </main>
+  );
+}
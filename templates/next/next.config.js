@@ -0,0 +1,9 @@
+/** @type {import('next').NextConfig} */
+const nextConfig = {
+  reactStrictMode: true,
+  output: 'standalone',
+  poweredByHeader: false,
+  compress: true,
+};
+
+module.exports = nextConfig;
@@ -0,0 +1,6 @@
+export default defineNuxtConfig({
+  devServer: { port: {{port}} },
+  app: {
+    head: { title: '{{name}}' },
+  },
+});
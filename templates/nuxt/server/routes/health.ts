@@ -0,0 +1,2 @@
+// Probed by Kubernetes
+export default defineEventHandler(() => 'ok');
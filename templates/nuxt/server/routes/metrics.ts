@@ -0,0 +1,4 @@
+export default defineEventHandler((event) => {
+  setResponseHeader(event, 'Content-Type', 'text/plain; version=0.0.4');
+  return metrics();
+});
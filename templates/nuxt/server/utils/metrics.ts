@@ -0,0 +1,21 @@
+let requests = 0;
+
+export function countRequest() {
+  requests++;
+}
+
+// Prometheus text format, scraped from the app port
+export function metrics(): string {
+  return [
+    '# HELP http_requests_total Requests received.',
+    '# TYPE http_requests_total counter',
+    `http_requests_total ${requests}`,
+    '# HELP process_resident_memory_bytes Resident memory size in bytes.',
+    '# TYPE process_resident_memory_bytes gauge',
+    `process_resident_memory_bytes ${process.memoryUsage().rss}`,
+    '# HELP process_uptime_seconds Seconds since the process started.',
+    '# TYPE process_uptime_seconds gauge',
+    `process_uptime_seconds ${process.uptime()}`,
+    '',
+  ].join('\n');
+}
@@ -0,0 +1,8 @@
+export default function App() {
+  return (
+    <main>
+      <h1>{{name}}</h1>
+      <p>Edit src/App.tsx to get started.</p>
+    </main>
+  );
+}
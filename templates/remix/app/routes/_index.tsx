@@ -0,0 +1,8 @@
+export default function Index() {
+  return (
+    <main>
+      <h1>{{name}}</h1>
+      <p>Edit app/routes/_index.tsx to get started.</p>
+    </main>
+  );
+}
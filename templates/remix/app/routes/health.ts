@@ -0,0 +1,4 @@
+// Probed by Kubernetes
+export function loader() {
+  return new Response('ok');
+}